@@ -1,10 +1,14 @@
 //! Various operations for additive placements
 use super::*;
-use crate::computation::{AddOp, Constant, FillOp, MulOp, Placed, RevealOp, ShapeOp, ShlOp, SubOp};
-use crate::error::Result;
+use crate::computation::{
+    AddOp, Constant, FillOp, HostUnit, KnownType, LoadOp, MulOp, Placed, RevealOp, SaveOp, ShapeOp,
+    ShlOp, SubOp,
+};
+use crate::error::{Error, Result};
 use crate::execution::Session;
 use crate::host::HostPlacement;
 use crate::kernels::*;
+use crate::types::HostString;
 use moose_macros::with_context;
 
 impl ShapeOp {
@@ -283,6 +287,62 @@ impl ShlOp {
     }
 }
 
+impl SaveOp {
+    pub(crate) fn adt_kernel<S: Session, HostRingT>(
+        sess: &S,
+        plc: &HostPlacement,
+        key: m!(HostString),
+        x: AdtTensor<HostRingT>,
+    ) -> Result<m!(HostUnit)>
+    where
+        HostString: KnownType<S>,
+        HostUnit: KnownType<S>,
+        HostRingT: Placed<Placement = HostPlacement>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        HostPlacement: PlacementSave<S, m!(HostString), HostRingT, m!(HostUnit)>,
+    {
+        let adt = x.placement()?;
+        let owner_ix = adt
+            .owners
+            .iter()
+            .position(|owner| owner == &plc.owner)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "cannot save shares of a value on {:?} on {}, which is not one of its owners",
+                    adt, plc.owner
+                ))
+            })?;
+
+        let key = plc.storage_key(sess, share_key_suffix(&adt.owners, &plc.owner), &key);
+        Ok(plc.save(sess, &key, &x.shares[owner_ix]))
+    }
+}
+
+impl LoadOp {
+    pub(crate) fn adt_kernel<S: Session, HostRingT>(
+        sess: &S,
+        plc: &AdditivePlacement,
+        key: m!(HostString),
+        query: m!(HostString),
+    ) -> Result<AdtTensor<HostRingT>>
+    where
+        HostString: KnownType<S>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        HostPlacement: PlacementLoad<S, m!(HostString), m!(HostString), HostRingT>,
+    {
+        let (player0, player1) = plc.host_placements();
+
+        let load_share = |player: &HostPlacement| {
+            let key = player.storage_key(sess, share_key_suffix(&plc.owners, &player.owner), &key);
+            player.load(sess, &key, &query)
+        };
+
+        Ok(AdtTensor {
+            shares: [load_share(&player0), load_share(&player1)],
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -460,4 +520,49 @@ mod tests {
             )));
         });
     }
+
+    #[cfg(feature = "sync_execute")]
+    #[test]
+    fn test_save_load() {
+        use crate::computation::SessionId;
+        use crate::storage::{local::LocalSyncStorage, SyncStorage};
+        use std::rc::Rc;
+
+        let alice = HostPlacement::from("alice");
+        let bob = HostPlacement::from("bob");
+        let adt = AdditivePlacement::from(["alice", "bob"]);
+        let storage: Rc<dyn SyncStorage> = Rc::new(LocalSyncStorage::default());
+
+        let x = AdditiveRing64Tensor {
+            shares: [
+                alice.from_raw(array![1, 2, 3]),
+                bob.from_raw(array![4, 5, 6]),
+            ],
+        };
+
+        let sess0 = SyncSession::from_storage(
+            SessionId::random(),
+            Default::default(),
+            Default::default(),
+            Rc::clone(&storage),
+        );
+        let key = HostString("x".to_string(), alice.clone());
+        let _: HostUnit = alice.save(&sess0, &key, &x);
+        let _: HostUnit = bob.save(&sess0, &key, &x);
+
+        let bob_share = storage
+            .load("x/alice-bob/bob", &SessionId::random(), None, "")
+            .unwrap();
+        assert_eq!(bob_share, Value::from(x.shares[1].clone()));
+
+        let sess1 = SyncSession::from_storage(
+            SessionId::random(),
+            Default::default(),
+            Default::default(),
+            storage,
+        );
+        let query = HostString("".to_string(), alice);
+        let y: AdditiveRing64Tensor = adt.load(&sess1, &key, &query);
+        assert_eq!(y, x);
+    }
 }
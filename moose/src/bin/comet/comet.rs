@@ -2,11 +2,12 @@
 
 use clap::Parser;
 use moose::choreography::grpc::GrpcChoreography;
-use moose::networking::grpc::GrpcNetworkingManager;
+use moose::networking::grpc::{BatchingConfig, GrpcNetworkingManager};
+use moose::networking::rendezvous::BufferLimits;
 use moose::prelude::*;
-use moose::storage::filesystem::AsyncFilesystemStorage;
 use moose::tokio;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::time::Duration;
 use tonic::transport::Server;
 
 #[derive(Debug, Parser, Clone)]
@@ -27,6 +28,26 @@ pub struct Opt {
     /// Expected identity of choreographer; `certs` must be specified
     choreographer: Option<String>,
 
+    #[structopt(env, long, requires = "share_key")]
+    /// Directory in which to keep shares saved under it encrypted at rest; requires `share-key`
+    share_dir: Option<PathBuf>,
+
+    #[structopt(env, long, requires = "share_dir")]
+    /// File containing the 32-byte key encrypting shares in `share-dir`
+    share_key: Option<PathBuf>,
+
+    #[structopt(env, long)]
+    /// Send values to other parties in batches, holding each back for at most this many milliseconds
+    batch_window_ms: Option<u64>,
+
+    #[structopt(env, long, default_value = "300")]
+    /// Drop values buffered for sessions that have not started here after this many seconds
+    buffer_ttl_secs: u64,
+
+    #[structopt(env, long, default_value = "3600")]
+    /// Forget sessions this many seconds after they finished, including their results
+    result_retention_secs: u64,
+
     #[structopt(long)]
     /// Report telemetry to Jaeger
     telemetry: bool,
@@ -54,14 +75,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         None => GrpcNetworkingManager::without_tls(),
     };
+    let networking = match opt.batch_window_ms {
+        Some(window) => networking.with_batching(BatchingConfig {
+            window: Duration::from_millis(window),
+            ..Default::default()
+        }),
+        None => networking,
+    };
+    let networking = networking.with_buffer_limits(BufferLimits {
+        ttl: Duration::from_secs(opt.buffer_ttl_secs),
+        ..Default::default()
+    });
 
     let networking_server = networking.new_server();
+    let storage_strategy =
+        moose::reindeer::storage_strategy(opt.share_dir.as_deref(), opt.share_key.as_deref())?;
     let choreography = GrpcChoreography::new(
         own_identity,
         opt.choreographer,
         Box::new(move |session_id| networking.new_session(session_id)),
-        Box::new(|| Arc::new(AsyncFilesystemStorage::default())),
-    );
+        storage_strategy,
+    )
+    .with_result_retention(Duration::from_secs(opt.result_retention_secs));
 
     let mut server = Server::builder();
 
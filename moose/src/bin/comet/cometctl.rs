@@ -1,9 +1,11 @@
 //! CLI tool for interacting with a group of Comet reindeers
 
 use clap::{Parser, Subcommand};
+use itertools::Itertools;
 use moose::choreography::filesystem::{
     parse_session_config_file_with_computation, parse_session_config_file_without_computation,
 };
+use moose::choreography::grpc::{SessionState, SessionStatus};
 use moose::computation::SessionId;
 use moose::execution::grpc::GrpcMooseRuntime;
 use moose::tokio;
@@ -40,13 +42,28 @@ pub enum Commands {
     },
     /// Abort computation on cluster
     Abort {
-        /// Session config file to use
+        /// Session config file listing the parties to abort on
         session_config: PathBuf,
 
         #[clap(long)]
-        /// Session id to use
+        /// Session id of computation to abort
+        session_id: Option<String>,
+    },
+    /// Show status of computation on each member of the cluster
+    Status {
+        /// Session config file listing the parties to query
+        session_config: PathBuf,
+
+        #[clap(long)]
+        /// Session id of computation to query
         session_id: Option<String>,
     },
+    /// List sessions known to each member of the cluster
+    List {
+        #[clap(long)]
+        /// Session config file listing the parties to query
+        session_config: PathBuf,
+    },
     /// Retrieve results of computation from cluster (blocking)
     Results {
         /// Session config file to use
@@ -85,9 +102,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             session_config,
             session_id,
         } => {
-            let (_, default_session_id, role_assignments, computation) =
+            let (session_config, default_session_id, role_assignments, computation) =
                 parse_session_config_file_with_computation(&session_config)?;
-            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?;
+            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?
+                .with_timeouts(session_config.timeouts.into());
             let session_id = session_id
                 .map(|session_id| SessionId::try_from(session_id.as_ref()))
                 .unwrap_or(Ok(default_session_id))?;
@@ -108,6 +126,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .map(|session_id| SessionId::try_from(session_id.as_ref()))
                 .unwrap_or(Ok(default_session_id))?;
             runtime.abort_computation(&session_id).await?;
+            println!("Aborted session {}", session_id);
+        }
+        Commands::Status {
+            session_config,
+            session_id,
+        } => {
+            let (_, default_session_id, role_assignments) =
+                parse_session_config_file_without_computation(&session_config)?;
+            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?;
+            let session_id = session_id
+                .map(|session_id| SessionId::try_from(session_id.as_ref()))
+                .unwrap_or(Ok(default_session_id))?;
+            let statuses = runtime.session_status(&session_id).await?;
+            for (role, status) in statuses.iter().sorted_by_key(|(role, _)| role.0.clone()) {
+                println!("{}: {}", role, format_status(status));
+            }
+        }
+        Commands::List { session_config } => {
+            let (_, _, role_assignments) =
+                parse_session_config_file_without_computation(&session_config)?;
+            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?;
+            let sessions = runtime.list_sessions().await?;
+            for (role, statuses) in sessions.iter().sorted_by_key(|(role, _)| role.0.clone()) {
+                println!("{}:", role);
+                for status in statuses.iter().sorted_by_key(|status| status.elapsed_time) {
+                    println!("  {}", format_status(status));
+                }
+            }
         }
         Commands::Results {
             session_config,
@@ -126,9 +172,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             session_config,
             session_id,
         } => {
-            let (_, default_session_id, role_assignments, computation) =
+            let (session_config, default_session_id, role_assignments, computation) =
                 parse_session_config_file_with_computation(&session_config)?;
-            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?;
+            let runtime = GrpcMooseRuntime::new(role_assignments, tls_config)?
+                .with_timeouts(session_config.timeouts.into());
             let session_id = session_id
                 .map(|session_id| SessionId::try_from(session_id.as_ref()))
                 .unwrap_or(Ok(default_session_id))?;
@@ -143,3 +190,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+fn format_status(status: &SessionStatus) -> String {
+    let state = match &status.state {
+        SessionState::Pending => "pending".to_string(),
+        SessionState::Running => "running".to_string(),
+        SessionState::Completed => "completed".to_string(),
+        SessionState::Aborted => "aborted".to_string(),
+        SessionState::Failed(e) => format!("failed ({})", e),
+    };
+    format!(
+        "{} {}, {}/{} operations finished, elapsed {:?}",
+        status.session_id,
+        state,
+        status.finished_operations,
+        status.total_operations,
+        status.elapsed_time
+    )
+}
@@ -2,13 +2,17 @@
 
 use clap::Parser;
 use moose::computation::Operator;
+use moose::execution::{AsyncNetworkingImpl, Profile, Profiler};
 use moose::networking::local::LocalAsyncNetworking;
+use moose::networking::simulated::{LinkConditions, NetworkConditions, SimulatedAsyncNetworking};
 use moose::prelude::*;
 use moose::storage::local::LocalAsyncStorage;
 use moose::tokio;
+use moose::units::{parse_bandwidth, parse_drop_rate, parse_duration};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Parser, Clone)]
 #[structopt(about = "Run computation locally by simulating all roles as seperate identities")]
@@ -20,6 +24,62 @@ pub struct Opt {
 
     #[structopt(short, long, default_value = "dasher-session")]
     session_id: String,
+
+    /// Profile execution and print the most expensive operator kinds
+    #[structopt(long)]
+    profile: bool,
+
+    /// Number of operator kinds to print when profiling
+    #[structopt(long, default_value = "10")]
+    top: usize,
+
+    /// Write the profile as Chrome trace JSON to this file
+    #[structopt(long)]
+    trace: Option<String>,
+
+    /// Simulate this one-way latency between all parties, e.g. 20ms
+    #[structopt(long, value_parser = parse_duration)]
+    latency: Option<Duration>,
+
+    /// Simulate random additional latency of up to this duration
+    #[structopt(long, value_parser = parse_duration)]
+    jitter: Option<Duration>,
+
+    /// Simulate this bandwidth between all parties, e.g. 1Gbit
+    #[structopt(long, value_parser = parse_bandwidth)]
+    bandwidth: Option<u64>,
+
+    /// Simulate losing this fraction of messages, which are retransmitted after 200ms;
+    /// must be below 1
+    #[structopt(long, value_parser = parse_drop_rate)]
+    drop_rate: Option<f64>,
+
+    /// Allow simulated jitter to reorder messages; requires `jitter`
+    #[structopt(long, requires = "jitter")]
+    reorder: bool,
+}
+
+impl Opt {
+    fn network_conditions(&self) -> Option<NetworkConditions> {
+        if self.latency.is_none()
+            && self.jitter.is_none()
+            && self.bandwidth.is_none()
+            && self.drop_rate.is_none()
+        {
+            return None;
+        }
+        Some(NetworkConditions {
+            default: LinkConditions {
+                latency: self.latency.unwrap_or_default(),
+                jitter: self.jitter.unwrap_or_default(),
+                bandwidth: self.bandwidth,
+                drop_rate: self.drop_rate.unwrap_or_default(),
+                retransmission_timeout: Duration::from_millis(200),
+                reorder: self.reorder,
+            },
+            links: HashMap::new(),
+        })
+    }
 }
 
 #[tokio::main]
@@ -66,7 +126,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .collect()
     };
 
-    let networking = Arc::new(LocalAsyncNetworking::default());
+    let simulated = opt.network_conditions().map(|conditions| {
+        Arc::new(SimulatedAsyncNetworking::new(
+            LocalAsyncNetworking::default(),
+            conditions,
+        ))
+    });
+    let networking: AsyncNetworkingImpl = match &simulated {
+        Some(simulated) => Arc::clone(simulated) as AsyncNetworkingImpl,
+        None => Arc::new(LocalAsyncNetworking::default()),
+    };
 
     let storage = Arc::new(LocalAsyncStorage::default());
 
@@ -77,6 +146,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         networking,
         storage,
     );
+    let session = if opt.profile || opt.trace.is_some() {
+        session.with_profiler(Profiler::new())
+    } else {
+        session
+    };
 
     let mut outputs: HashMap<String, <AsyncSession as Session>::Value> = HashMap::new();
 
@@ -91,7 +165,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .map(|input_name| env.get(input_name).unwrap().clone())
                 .collect();
 
-            let result = session.execute(&op.kind, &op.placement, operands)?;
+            let result = session.execute_operation(&op.name, &op.kind, &op.placement, operands)?;
 
             if matches!(op.kind, Operator::Output(_)) {
                 // If it is an output, we need to make sure we capture it for returning.
@@ -111,7 +185,44 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     let session_handle = session.into_handle()?;
+    let profiler = session_handle.profiler();
     session_handle.join_on_first_error().await?;
 
+    if let Some(simulated) = simulated {
+        print!("{}", simulated.report());
+    }
+
+    if let Some(profile) = profiler.map(|profiler| profiler.profile()) {
+        if opt.profile {
+            print_profile(&profile, opt.top);
+        }
+        if let Some(path) = &opt.trace {
+            std::fs::write(path, profile.to_chrome_trace().to_string())?;
+            println!("Chrome trace written to {}", path);
+        }
+    }
+
     Ok(())
 }
+
+fn print_profile(profile: &Profile, top: usize) {
+    println!(
+        "{:<24} {:>8} {:>14} {:>14} {:>14}",
+        "Operator", "Count", "Compute (ms)", "Network (ms)", "Waiting (ms)"
+    );
+    for summary in profile.by_kind().iter().take(top) {
+        println!(
+            "{:<24} {:>8} {:>14.3} {:>14.3} {:>14.3}",
+            summary.kind,
+            summary.count,
+            summary.compute_time.as_secs_f64() * 1000.0,
+            summary.network_time.as_secs_f64() * 1000.0,
+            summary.wait_time.as_secs_f64() * 1000.0,
+        );
+    }
+    println!(
+        "Bytes sent: {} in {} messages",
+        profile.total_bytes_sent(),
+        profile.bytes_sent.len()
+    );
+}
@@ -1,10 +1,14 @@
 //! CLI tool for the Moose compiler.
 
 use clap::{Parser, Subcommand, ValueEnum};
-use moose::compilation::compile;
+use moose::compilation::communication::estimate_communication;
+use moose::compilation::leakage::{analyze_leakage, LeakagePolicy};
+use moose::compilation::shape_inference::InputShapes;
+use moose::compilation::{compile, Pass, DEFAULT_PASSES};
 use moose::prelude::Computation;
 use moose::textual::ToTextual;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::fs::{read_to_string, write};
 use std::path::{Path, PathBuf};
 
@@ -38,9 +42,32 @@ pub enum Commands {
         #[clap(value_enum, short, long, default_value = "textual")]
         output_format: ComputationFormat,
 
-        /// Comma-separated list of passes to apply in-order; default to all passes
+        /// Comma-separated list of passes to apply in-order; default to all passes followed by
+        /// a well-formedness check
         #[clap(short, long)]
         passes: Option<String>,
+
+        /// Shape of an input used by shape inference, given as `name=2,3`; may be repeated
+        #[clap(long = "input-shape", value_parser = parse_input_shape)]
+        input_shapes: Vec<(String, Vec<usize>)>,
+
+        /// Allow-list of plaintext flows used by the leakage check, in TOML
+        #[clap(long)]
+        leakage_policy: Option<PathBuf>,
+    },
+
+    /// Print every point where data of one role is revealed in plaintext to another role
+    Audit {
+        /// Input file
+        input: PathBuf,
+
+        /// Computation format
+        #[clap(value_enum, short, long, default_value = "textual")]
+        input_format: ComputationFormat,
+
+        /// Allow-list of plaintext flows, in TOML; fail if any flow is not allowed
+        #[clap(long)]
+        policy: Option<PathBuf>,
     },
 
     /// Collect stats about a computation
@@ -91,6 +118,20 @@ pub enum StatsCommands {
         #[clap(long)]
         by_operator: bool,
     },
+
+    /// Print estimated communication costs of a networked computation
+    Comm {
+        /// Input file
+        input: PathBuf,
+
+        /// Computation format
+        #[clap(value_enum, short, long, default_value = "textual")]
+        input_format: ComputationFormat,
+
+        /// Shape of an input, given as `name=2,3`; may be repeated
+        #[clap(long = "input-shape", value_parser = parse_input_shape)]
+        input_shapes: Vec<(String, Vec<usize>)>,
+    },
 }
 
 #[derive(Clone, Debug, ValueEnum)]
@@ -109,17 +150,64 @@ fn main() -> anyhow::Result<()> {
             input_format,
             output_format,
             passes,
+            input_shapes,
+            leakage_policy,
         } => {
-            let passes: Option<Vec<String>> = passes.clone().map(|p| {
-                p.split(',')
-                    .filter(|s| !s.is_empty())
-                    .map(|s| s.to_string())
-                    .collect()
-            });
+            let input_shapes: InputShapes = input_shapes.iter().cloned().collect();
+            let leakage_policy = leakage_policy.as_deref().map(read_policy).transpose()?;
+            let passes: Vec<Pass> = passes
+                .as_ref()
+                .map(|p| {
+                    p.split(',')
+                        .filter(|s| !s.is_empty())
+                        .map(|s| match Pass::try_from(s)? {
+                            Pass::ShapeInference(_) => {
+                                Ok(Pass::ShapeInference(input_shapes.clone()))
+                            }
+                            Pass::LeakageCheck(_) => Ok(Pass::LeakageCheck(
+                                leakage_policy.clone().unwrap_or_default(),
+                            )),
+                            pass => Ok(pass),
+                        })
+                        .collect::<anyhow::Result<_>>()
+                })
+                .transpose()?
+                .unwrap_or_else(|| {
+                    let mut passes = DEFAULT_PASSES.to_vec();
+                    passes.push(Pass::WellFormed);
+                    passes
+                });
             let comp = input_computation(input, input_format)?;
-            let comp = compile(comp, passes)?;
+            let comp = compile(comp, Some(passes))?;
             output_computation(&comp, output, output_format)?;
         }
+        Commands::Audit {
+            input,
+            input_format,
+            policy,
+        } => {
+            let comp = input_computation(input, input_format)?;
+            let policy = policy.as_deref().map(read_policy).transpose()?;
+            let flows = analyze_leakage(&comp)?;
+            let mut violations = 0;
+            for flow in &flows {
+                match &policy {
+                    Some(policy) if policy.allows(flow) => println!("{:>8} {}", "allowed", flow),
+                    Some(_) => {
+                        violations += 1;
+                        println!("{:>8} {}", "DENIED", flow);
+                    }
+                    None => println!("{}", flow),
+                }
+            }
+            println!("Flows: {}", flows.len());
+            if violations > 0 {
+                return Err(anyhow::anyhow!(
+                    "{} plaintext flow(s) not allowed by the policy",
+                    violations
+                ));
+            }
+        }
         Commands::Stats(StatsCommands::OpHist {
             input,
             input_format,
@@ -195,10 +283,48 @@ fn main() -> anyhow::Result<()> {
                 });
             print_sorted("Out degree", &out_degree_distribution);
         }
+        Commands::Stats(StatsCommands::Comm {
+            input,
+            input_format,
+            input_shapes,
+        }) => {
+            let comp = input_computation(input, input_format)?;
+            let input_shapes: InputShapes = input_shapes.iter().cloned().collect();
+            let stats = estimate_communication(&comp, &input_shapes)?;
+
+            let mut links: Vec<_> = stats.links.iter().collect();
+            links.sort_by_key(|((sender, receiver), _)| (sender.0.clone(), receiver.0.clone()));
+            println!("{:>10} {:>10} {:>15} Link", "Messages", "Unknown", "Bytes");
+            for ((sender, receiver), link) in links {
+                println!(
+                    "{:>10} {:>10} {:>15} {} -> {}",
+                    link.messages, link.unknown_messages, link.bytes, sender, receiver
+                );
+            }
+            println!("Rounds: {}", stats.rounds);
+        }
     }
     Ok(())
 }
 
+fn parse_input_shape(arg: &str) -> anyhow::Result<(String, Vec<usize>)> {
+    let (name, shape) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("Expected input shape of the form `name=2,3`"))?;
+    let shape = shape
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|d| d.trim().parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((name.to_string(), shape))
+}
+
+fn read_policy(path: &Path) -> anyhow::Result<LeakagePolicy> {
+    read_to_string(path)?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Failed to parse the leakage policy due to {}", e))
+}
+
 fn input_computation(input: &Path, format: &ComputationFormat) -> anyhow::Result<Computation> {
     match format {
         ComputationFormat::Textual => {
@@ -2,11 +2,10 @@
 
 use clap::Parser;
 use moose::choreography::filesystem::FilesystemChoreography;
-use moose::networking::grpc::GrpcNetworkingManager;
+use moose::networking::grpc::{BatchingConfig, GrpcNetworkingManager};
 use moose::prelude::*;
-use moose::storage::filesystem::AsyncFilesystemStorage;
 use moose::tokio;
-use std::sync::Arc;
+use std::path::PathBuf;
 use tonic::transport::Server;
 
 #[derive(Debug, Parser, Clone)]
@@ -35,6 +34,18 @@ pub struct Opt {
     /// Do not listen for new files but exit when existing have been processed
     no_listen: bool,
 
+    #[structopt(env, long, requires = "share_key")]
+    /// Directory in which to keep shares saved under it encrypted at rest; requires `share-key`
+    share_dir: Option<PathBuf>,
+
+    #[structopt(env, long, requires = "share_dir")]
+    /// File containing the 32-byte key encrypting shares in `share-dir`
+    share_key: Option<PathBuf>,
+
+    #[structopt(env, long)]
+    /// Send values to other parties in batches, holding each back for at most this many milliseconds
+    batch_window_ms: Option<u64>,
+
     #[structopt(long)]
     /// Report telemetry to Jaeger
     telemetry: bool,
@@ -61,6 +72,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         None => GrpcNetworkingManager::without_tls(),
     };
+    let manager = match opt.batch_window_ms {
+        Some(window) => manager.with_batching(BatchingConfig {
+            window: std::time::Duration::from_millis(window),
+            ..Default::default()
+        }),
+        None => manager,
+    };
+
+    let storage_strategy =
+        moose::reindeer::storage_strategy(opt.share_dir.as_deref(), opt.share_key.as_deref())?;
 
     let own_identity = Identity::from(opt.identity);
 
@@ -88,7 +109,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         own_identity,
         opt.sessions,
         Box::new(move |session_id| manager.new_session(session_id)),
-        Box::new(|| Arc::new(AsyncFilesystemStorage::default())),
+        storage_strategy,
     )
     .process(opt.ignore_existing, opt.no_listen)
     .await?;
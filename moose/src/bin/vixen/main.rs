@@ -1,12 +1,16 @@
 use clap::Parser;
 use moose::computation::Role;
+use moose::execution::AsyncNetworkingImpl;
 use moose::execution::Identity;
+use moose::networking::quic::QuicNetworking;
 use moose::networking::tcpstream::TcpStreamNetworking;
+use moose::networking::uds::UdsNetworking;
 use moose::prelude::*;
 use moose::storage::local::LocalAsyncStorage;
 use moose::tokio;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -25,8 +29,39 @@ pub struct Opt {
     #[structopt(long)]
     role_assignment: String,
 
+    /// JSON map from identities to addresses, or to `unix:` socket paths for co-located parties
     #[structopt(long)]
     hosts: String,
+
+    /// Directory with certificates, in which case all connections use TLS; not supported
+    /// with `unix:` socket paths
+    #[structopt(long)]
+    certs: Option<String>,
+
+    /// Use QUIC instead of TCP; requires `certs`
+    #[structopt(long, requires = "certs")]
+    quic: bool,
+}
+
+const UNIX_PREFIX: &str = "unix:";
+
+/// Strip the `unix:` prefix from every address, which must all be socket paths.
+fn unix_socket_paths(
+    hosts: HashMap<String, String>,
+) -> Result<HashMap<String, PathBuf>, Box<dyn std::error::Error>> {
+    hosts
+        .into_iter()
+        .map(
+            |(identity, address)| match address.strip_prefix(UNIX_PREFIX) {
+                Some(path) => Ok((identity, PathBuf::from(path))),
+                None => Err(format!(
+                    "cannot mix socket paths and network addresses, found '{}' for {}",
+                    address, identity
+                )
+                .into()),
+            },
+        )
+        .collect()
 }
 
 fn init_tracer() {
@@ -52,12 +87,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let storage = Arc::new(LocalAsyncStorage::default());
 
-    let networking = TcpStreamNetworking::new(&opt.placement, hosts).await?;
-    let networking = Arc::new(networking);
+    let my_cert_name = opt.placement.replace(':', "_");
+    let unix_sockets = hosts
+        .values()
+        .any(|address| address.starts_with(UNIX_PREFIX));
+    let session_id = moose::computation::SessionId::try_from(opt.session_id.as_ref())?;
 
-    let arguments = HashMap::new();
+    let networking: AsyncNetworkingImpl = match (opt.certs, opt.quic) {
+        (Some(_), _) if unix_sockets => {
+            return Err("--certs cannot be used with `unix:` socket paths".into())
+        }
+        (None, _) if unix_sockets => {
+            let socket_paths = unix_socket_paths(hosts)?;
+            let networking = Arc::new(UdsNetworking::new(&opt.placement, socket_paths).await?);
+            networking.new_session(session_id.clone())
+        }
+        (Some(ref certs_dir), true) => {
+            let tls_config = moose::reindeer::load_quic_tls_config(&my_cert_name, certs_dir)?;
+            let networking =
+                Arc::new(QuicNetworking::new(&opt.placement, hosts, tls_config).await?);
+            networking.new_session(session_id.clone())
+        }
+        (Some(ref certs_dir), false) => {
+            let tls_config = moose::reindeer::load_tcpstream_tls_config(&my_cert_name, certs_dir)?;
+            let networking = Arc::new(
+                TcpStreamNetworking::new_with_tls(&opt.placement, hosts, tls_config).await?,
+            );
+            networking.new_session(session_id.clone())
+        }
+        (None, _) => {
+            let networking = Arc::new(TcpStreamNetworking::new(&opt.placement, hosts).await?);
+            networking.new_session(session_id.clone())
+        }
+    };
 
-    let session_id = moose::computation::SessionId::try_from(opt.session_id.as_ref())?;
+    let arguments = HashMap::new();
 
     let role_assignment_map: HashMap<String, String> = serde_json::from_str(&opt.role_assignment)?;
     let role_assignment: HashMap<Role, Identity> = role_assignment_map
@@ -2,16 +2,20 @@
 
 use crate::choreography::{NetworkingStrategy, StorageStrategy};
 use crate::computation::Computation;
-use crate::execution::ExecutionContext;
 use crate::execution::RoleAssignment;
+use crate::execution::{AsyncSessionAbortHandle, ExecutionContext, SessionTimeouts};
 use crate::prelude::*;
+use crate::units::parse_duration;
+use dashmap::DashMap;
 use notify::{DebouncedEvent, Watcher};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Filesystem-based choreography.
 ///
@@ -25,11 +29,13 @@ use std::str::FromStr;
 ///
 /// `FilesystemChoreography` listens for changes to the sessions directory
 /// and will launch new sessions when new `.session` files are created.
+/// Sessions are aborted when their `.session` file is removed or modified.
 pub struct FilesystemChoreography {
     own_identity: Identity,
     sessions_dir: String,
     networking_strategy: NetworkingStrategy,
     storage_strategy: StorageStrategy,
+    running_sessions: Arc<DashMap<SessionId, AsyncSessionAbortHandle>>,
 }
 
 impl FilesystemChoreography {
@@ -44,6 +50,7 @@ impl FilesystemChoreography {
             sessions_dir,
             networking_strategy,
             storage_strategy,
+            running_sessions: Default::default(),
         }
     }
 
@@ -100,12 +107,20 @@ impl FilesystemChoreography {
         if path.is_file() {
             match path.extension() {
                 Some(ext) if ext == "session" => {
-                    let session_handle = self.launch_session(path).await?;
-                    let res = session_handle.join_on_first_error().await;
-                    if let Err(e) = res {
-                        tracing::error!("Session error: {}", e);
-                        return Result::Err(e.into());
-                    }
+                    let (session_id, session_handle) = self.launch_session(path).await?;
+                    self.running_sessions
+                        .insert(session_id.clone(), session_handle.abort_handle());
+
+                    // join in separate task so that the session can be aborted
+                    // while we keep processing filesystem events
+                    let running_sessions = Arc::clone(&self.running_sessions);
+                    tokio::spawn(async move {
+                        let res = session_handle.join_on_first_error().await;
+                        let _ = running_sessions.remove(&session_id);
+                        if let Err(e) = res {
+                            tracing::error!("Session error: {}", e);
+                        }
+                    });
                 }
                 Some(ext) if ext == "moose" => {
                     // ok to skip
@@ -122,14 +137,15 @@ impl FilesystemChoreography {
     async fn launch_session(
         &self,
         path: &Path,
-    ) -> Result<AsyncSessionHandle, Box<dyn std::error::Error>> {
+    ) -> Result<(SessionId, AsyncSessionHandle), Box<dyn std::error::Error>> {
         tracing::info!("Loading session from {:?}", path);
-        let (_, session_id, role_assignments, computation) =
+        let (session_config, session_id, role_assignments, computation) =
             parse_session_config_file_with_computation(path)?;
         let networking = (self.networking_strategy)(session_id.clone());
         let storage = (self.storage_strategy)();
 
-        let context = ExecutionContext::new(self.own_identity.clone(), networking, storage);
+        let context = ExecutionContext::new(self.own_identity.clone(), networking, storage)
+            .with_timeouts(session_config.timeouts.into());
 
         // TODO(Morten) for now we don't support arguments in this type of choreography;
         // we could be eg allowing them to be specified in the .session file (perhaps as
@@ -150,24 +166,40 @@ impl FilesystemChoreography {
         for (output_name, output_value) in outputs {
             let session_id = session_id.clone();
             tokio::spawn(async move {
-                let value = output_value.await.unwrap();
-                tracing::info!(
-                    "Output '{}' from '{}' ready: {:?}",
-                    output_name,
-                    session_id,
-                    value
-                );
+                match output_value.await {
+                    Ok(value) => tracing::info!(
+                        "Output '{}' from '{}' ready: {:?}",
+                        output_name,
+                        session_id,
+                        value
+                    ),
+                    // sessions that fail or are aborted never produce their outputs
+                    Err(_) => tracing::debug!(
+                        "Output '{}' from '{}' unavailable",
+                        output_name,
+                        session_id
+                    ),
+                }
             });
         }
 
-        Ok(handle)
+        Ok((session_id, handle))
     }
 
-    async fn abort_session_from_path(
-        &self,
-        _path: &Path,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // TODO
+    async fn abort_session_from_path(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        // NOTE the file may no longer exist so we derive the session id from its name only
+        match path.extension() {
+            Some(ext) if ext == "session" => {
+                let session_id = session_id_from_path(path)?;
+                if let Some((_, abort_handle)) = self.running_sessions.remove(&session_id) {
+                    tracing::info!("Aborting session {}", session_id);
+                    abort_handle.abort();
+                }
+            }
+            _ => {
+                // only sessions can be aborted
+            }
+        }
         Ok(())
     }
 }
@@ -176,6 +208,8 @@ impl FilesystemChoreography {
 pub struct SessionConfig {
     pub computation: ComputationConfig,
     pub roles: Vec<RoleConfig>,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
 }
 
 impl FromStr for SessionConfig {
@@ -205,6 +239,36 @@ pub struct RoleConfig {
     pub endpoint: String,
 }
 
+/// Optional `[timeouts]` table, with durations such as `"30s"` or `"500ms"`.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+pub struct TimeoutsConfig {
+    /// Maximum time to wait for any single value from another party.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub receive: Option<Duration>,
+    /// Maximum time since the start of the session to wait for values.
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub session: Option<Duration>,
+}
+
+impl From<TimeoutsConfig> for SessionTimeouts {
+    fn from(config: TimeoutsConfig) -> SessionTimeouts {
+        SessionTimeouts {
+            receive: config.receive,
+            session: config.session,
+        }
+    }
+}
+
+fn deserialize_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_duration(&s)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
 pub fn parse_session_config_file_with_computation(
     session_config_file: &Path,
 ) -> Result<(SessionConfig, SessionId, RoleAssignment, Computation), Box<dyn std::error::Error>> {
@@ -247,6 +311,14 @@ pub fn parse_session_config_file_without_computation(
         })
         .collect();
 
+    let session_id = session_id_from_path(session_config_file)?;
+
+    Ok((session_config, session_id, role_assignment))
+}
+
+fn session_id_from_path(
+    session_config_file: &Path,
+) -> Result<SessionId, Box<dyn std::error::Error>> {
     let session_id: SessionId = SessionId::try_from(
         session_config_file
             .file_stem()
@@ -254,6 +326,5 @@ pub fn parse_session_config_file_without_computation(
             .to_string_lossy()
             .borrow(),
     )?;
-
-    Ok((session_config, session_id, role_assignment))
+    Ok(session_id)
 }
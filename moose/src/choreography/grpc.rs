@@ -6,37 +6,198 @@ pub(crate) mod gen {
 }
 
 use self::gen::choreography_server::{Choreography, ChoreographyServer};
+use self::gen::SessionState as ProtoSessionState;
+use self::gen::SessionStatus as ProtoSessionStatus;
 use self::gen::{
-    AbortComputationRequest, AbortComputationResponse, LaunchComputationRequest,
-    LaunchComputationResponse, RetrieveResultsRequest, RetrieveResultsResponse,
+    AbortComputationRequest, AbortComputationResponse, GetSessionStatusRequest,
+    GetSessionStatusResponse, LaunchComputationRequest, LaunchComputationResponse,
+    ListSessionsRequest, ListSessionsResponse, RetrieveResultsRequest, RetrieveResultsResponse,
 };
 use super::{NetworkingStrategy, StorageStrategy};
-use crate::computation::{Operator, SessionId, Value};
-use crate::execution::ExecutionContext;
-use crate::execution::Identity;
+use crate::computation::{Computation, Operator, Role, SessionId, Value};
+use crate::error::Error;
+use crate::execution::{AsyncSessionAbortHandle, AsyncSessionProgress, AsyncValue, Identity};
+use crate::execution::{ExecutionContext, Profile, SessionTimeouts};
+use crate::textual::ToTextual;
 use async_cell::sync::AsyncCell;
 use async_trait::async_trait;
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+/// Default time for which the results of finished sessions are kept.
+const DEFAULT_RESULT_RETENTION: Duration = Duration::from_secs(60 * 60);
+
+/// Results of a session as seen by a single party.
+///
+/// If the session failed then `outputs` holds the root cause, which typically
+/// is an `Error::OperationFailed` naming the failing operation and its placement.
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ComputationOutputs {
-    pub outputs: HashMap<String, Value>,
+    pub outputs: Result<HashMap<String, Value>, Error>,
     pub elapsed_time: Option<Duration>,
+    /// Execution profile, if requested when launching the computation.
+    pub profile: Option<Profile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SessionState {
+    /// Session has been received and its networking and storage are being set up
+    Pending,
+    /// Session has been scheduled and is executing
+    Running,
+    /// Session finished and its results can be retrieved
+    Completed,
+    /// Session was aborted before finishing
+    Aborted,
+    /// Session failed with the given root cause
+    Failed(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SessionStatus {
+    /// Logical form of the session id
+    pub session_id: String,
+    pub state: SessionState,
+    pub elapsed_time: Duration,
+    pub finished_operations: usize,
+    pub total_operations: usize,
+}
+
+#[derive(Clone, Debug)]
+enum SessionResult {
+    Finished(ComputationOutputs),
+    Aborted,
+}
+
+/// Results and bookkeeping for a single session.
+struct ResultStore {
+    results: AsyncCell<SessionResult>,
+    launch_time: Instant,
+    state: RwLock<(SessionState, Option<Instant>)>,
+    progress: RwLock<Option<AsyncSessionProgress>>,
+    /// Whether the session was aborted, possibly before it started running.
+    abort_requested: AtomicBool,
+}
+
+impl ResultStore {
+    fn new() -> ResultStore {
+        ResultStore {
+            results: AsyncCell::new(),
+            launch_time: Instant::now(),
+            state: RwLock::new((SessionState::Pending, None)),
+            progress: RwLock::new(None),
+            abort_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the session finished longer than `retention` ago.
+    fn is_expired(&self, retention: Duration) -> bool {
+        matches!(self.state.read().1, Some(stop_time) if stop_time.elapsed() > retention)
+    }
+
+    fn running(&self, progress: AsyncSessionProgress) {
+        *self.progress.write() = Some(progress);
+        self.state.write().0 = SessionState::Running;
+    }
+
+    fn finish(&self, results: SessionResult) {
+        let state = match &results {
+            SessionResult::Finished(ComputationOutputs { outputs: Ok(_), .. }) => {
+                SessionState::Completed
+            }
+            SessionResult::Finished(ComputationOutputs {
+                outputs: Err(e), ..
+            }) => SessionState::Failed(e.to_string()),
+            SessionResult::Aborted => SessionState::Aborted,
+        };
+        *self.state.write() = (state, Some(Instant::now()));
+        self.results.set(results);
+    }
+
+    fn status(&self, session_id: &SessionId) -> SessionStatus {
+        let (state, stop_time) = self.state.read().clone();
+        let elapsed_time = stop_time
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.launch_time);
+        let (finished_operations, total_operations) = match &*self.progress.read() {
+            Some(progress) => (progress.finished_tasks(), progress.total_tasks()),
+            None => (0, 0),
+        };
+        SessionStatus {
+            session_id: session_id.to_string(),
+            state,
+            elapsed_time,
+            finished_operations,
+            total_operations,
+        }
+    }
+}
+
+impl From<SessionStatus> for ProtoSessionStatus {
+    fn from(status: SessionStatus) -> Self {
+        let (state, error) = match status.state {
+            SessionState::Pending => (ProtoSessionState::Pending, String::new()),
+            SessionState::Running => (ProtoSessionState::Running, String::new()),
+            SessionState::Completed => (ProtoSessionState::Completed, String::new()),
+            SessionState::Aborted => (ProtoSessionState::Aborted, String::new()),
+            SessionState::Failed(e) => (ProtoSessionState::Failed, e),
+        };
+        ProtoSessionStatus {
+            session_id: status.session_id,
+            state: state as i32,
+            error,
+            elapsed_time_ms: status.elapsed_time.as_millis() as u64,
+            finished_operations: status.finished_operations as u64,
+            total_operations: status.total_operations as u64,
+        }
+    }
+}
+
+impl TryFrom<ProtoSessionStatus> for SessionStatus {
+    type Error = Error;
+    fn try_from(status: ProtoSessionStatus) -> Result<Self, Self::Error> {
+        let state = match ProtoSessionState::from_i32(status.state) {
+            Some(ProtoSessionState::Pending) => SessionState::Pending,
+            Some(ProtoSessionState::Running) => SessionState::Running,
+            Some(ProtoSessionState::Completed) => SessionState::Completed,
+            Some(ProtoSessionState::Aborted) => SessionState::Aborted,
+            Some(ProtoSessionState::Failed) => SessionState::Failed(status.error),
+            None => {
+                return Err(Error::Unexpected(Some(format!(
+                    "unknown session state {}",
+                    status.state
+                ))))
+            }
+        };
+        Ok(SessionStatus {
+            session_id: status.session_id,
+            state,
+            elapsed_time: Duration::from_millis(status.elapsed_time_ms),
+            finished_operations: status.finished_operations as usize,
+            total_operations: status.total_operations as usize,
+        })
+    }
 }
 
-type ResultStores = DashMap<SessionId, Arc<AsyncCell<ComputationOutputs>>>;
+type ResultStores = DashMap<SessionId, Arc<ResultStore>>;
+
+type AbortHandles = DashMap<SessionId, AsyncSessionAbortHandle>;
 
 pub struct GrpcChoreography {
     own_identity: Identity,
     choreographer: Option<String>,
     result_stores: Arc<ResultStores>,
-    networking_strategy: NetworkingStrategy,
-    storage_strategy: StorageStrategy,
+    abort_handles: Arc<AbortHandles>,
+    networking_strategy: Arc<NetworkingStrategy>,
+    storage_strategy: Arc<StorageStrategy>,
+    result_retention: Duration,
 }
 
 impl GrpcChoreography {
@@ -50,8 +211,21 @@ impl GrpcChoreography {
             own_identity,
             choreographer,
             result_stores: Arc::new(ResultStores::default()),
-            networking_strategy,
-            storage_strategy,
+            abort_handles: Arc::new(AbortHandles::default()),
+            networking_strategy: Arc::new(networking_strategy),
+            storage_strategy: Arc::new(storage_strategy),
+            result_retention: DEFAULT_RESULT_RETENTION,
+        }
+    }
+
+    /// Keep the results and status of finished sessions for the given time.
+    ///
+    /// Sessions are forgotten once this time has passed since they finished, whether or not
+    /// their results have been retrieved, after which their ids may be reused.
+    pub fn with_result_retention(self, result_retention: Duration) -> GrpcChoreography {
+        GrpcChoreography {
+            result_retention,
+            ..self
         }
     }
 
@@ -61,6 +235,17 @@ impl GrpcChoreography {
 }
 
 impl GrpcChoreography {
+    /// Forget sessions that finished longer than the retention time ago.
+    fn evict_finished(&self) {
+        self.result_stores.retain(|session_id, result_store| {
+            let expired = result_store.is_expired(self.result_retention);
+            if expired {
+                tracing::debug!("Forgetting finished session {}", session_id);
+            }
+            !expired
+        });
+    }
+
     fn check_choreographer<T>(&self, request: &tonic::Request<T>) -> Result<(), tonic::Status> {
         let choreographer = crate::grpc::extract_sender(request).map_err(|_e| {
             tonic::Status::new(
@@ -93,6 +278,31 @@ impl GrpcChoreography {
     }
 }
 
+async fn collect_outputs(
+    computation: &Computation,
+    outputs: Vec<(usize, AsyncValue)>,
+) -> Result<HashMap<String, Value>, Error> {
+    let mut results = HashMap::with_capacity(outputs.len());
+    for (output_ix, output_value) in outputs {
+        let output_op = &computation.operations[output_ix];
+        let output_tag = match &output_op.kind {
+            Operator::Output(op) => Some(op.tag.clone()),
+            _ => None,
+        }
+        .unwrap();
+        // the session may finish without a root cause even though an output is missing,
+        // for instance if all errors were due to unavailable operands
+        let value = output_value.await.map_err(|_| Error::OperationFailed {
+            operation: output_op.name.clone(),
+            placement: output_op.placement.to_textual(),
+            error: Box::new(Error::OperandUnavailable),
+        })?;
+        results.insert(output_tag, value);
+    }
+    tracing::info!("Results ready, {:?}", results.keys());
+    Ok(results)
+}
+
 #[async_trait]
 impl Choreography for GrpcChoreography {
     async fn launch_computation(
@@ -111,97 +321,179 @@ impl Choreography for GrpcChoreography {
             )
         })?;
 
-        match self.result_stores.entry(session_id.clone()) {
-            Entry::Occupied(_) => Err(tonic::Status::new(
-                tonic::Code::Aborted,
-                "session id exists already or inconsistent metric and result map".to_string(),
-            )),
+        let computation: Computation =
+            bincode::deserialize(&request.computation).map_err(|_e| {
+                tonic::Status::new(
+                    tonic::Code::Aborted,
+                    "failed to parse computation".to_string(),
+                )
+            })?;
+
+        let arguments: HashMap<String, Value> =
+            bincode::deserialize(&request.arguments).map_err(|_e| {
+                tonic::Status::new(
+                    tonic::Code::Aborted,
+                    "failed to parse arguments".to_string(),
+                )
+            })?;
+
+        let role_assignments: HashMap<Role, Identity> =
+            bincode::deserialize(&request.role_assignment).map_err(|_e| {
+                tonic::Status::new(
+                    tonic::Code::Aborted,
+                    "failed to parse role assignment".to_string(),
+                )
+            })?;
+
+        // timeouts are optional for compatibility with older clients
+        let timeouts = if request.timeouts.is_empty() {
+            SessionTimeouts::default()
+        } else {
+            bincode::deserialize(&request.timeouts).map_err(|_e| {
+                tonic::Status::new(tonic::Code::Aborted, "failed to parse timeouts".to_string())
+            })?
+        };
+
+        self.evict_finished();
+        let result_store = match self.result_stores.entry(session_id.clone()) {
+            Entry::Occupied(_) => {
+                return Err(tonic::Status::new(
+                    tonic::Code::Aborted,
+                    "session id exists already or inconsistent metric and result map".to_string(),
+                ))
+            }
             Entry::Vacant(result_stores_entry) => {
-                let result_cell = AsyncCell::shared();
-                result_stores_entry.insert(result_cell);
+                let result_store = Arc::new(ResultStore::new());
+                result_stores_entry.insert(Arc::clone(&result_store));
+                result_store
+            }
+        };
 
-                let computation = bincode::deserialize(&request.computation).map_err(|_e| {
-                    tonic::Status::new(
-                        tonic::Code::Aborted,
-                        "failed to parse computation".to_string(),
-                    )
-                })?;
+        let own_identity = self.own_identity.clone();
+        let networking_strategy = Arc::clone(&self.networking_strategy);
+        let storage_strategy = Arc::clone(&self.storage_strategy);
+        let abort_handles = Arc::clone(&self.abort_handles);
+        let profile = request.profile;
 
-                let arguments = bincode::deserialize(&request.arguments).map_err(|_e| {
-                    tonic::Status::new(
-                        tonic::Code::Aborted,
-                        "failed to parse arguments".to_string(),
-                    )
-                })?;
-
-                let role_assignments =
-                    bincode::deserialize(&request.role_assignment).map_err(|_e| {
-                        tonic::Status::new(
-                            tonic::Code::Aborted,
-                            "failed to parse role assignment".to_string(),
-                        )
-                    })?;
-
-                let own_identity = self.own_identity.clone();
-                let networking = (self.networking_strategy)(session_id.clone());
-                let storage = (self.storage_strategy)();
-                let context = ExecutionContext::new(own_identity, networking, storage);
-
-                let execution_start_timer = Instant::now();
-
-                let (_handle, outputs) = context
-                    .execute_computation(
-                        session_id.clone(),
-                        &computation,
-                        arguments,
-                        role_assignments,
-                    )
-                    .await
-                    .map_err(|_e| {
-                        tonic::Status::new(
-                            tonic::Code::Aborted,
-                            "failed launch computation".to_string(),
-                        )
-                    })?;
-
-                let result_stores = Arc::clone(&self.result_stores);
-
-                tokio::spawn(async move {
-                    let mut results = HashMap::with_capacity(outputs.len());
-                    for (output_ix, output_value) in outputs {
-                        let value = output_value.await.unwrap();
-                        let output_op = &computation.operations[output_ix];
-                        let output_tag = match &output_op.kind {
-                            Operator::Output(op) => Some(op.tag.clone()),
-                            _ => None,
-                        }
-                        .unwrap();
-                        results.insert(output_tag, value);
-                    }
-                    tracing::info!("Results ready, {:?}", results.keys());
+        // the session is pending until networking and storage have been set up, which
+        // may take a while, so this is done after responding
+        tokio::spawn(async move {
+            let networking = (*networking_strategy)(session_id.clone());
+            let storage = (*storage_strategy)();
+            let context = ExecutionContext::new(own_identity, networking, storage)
+                .with_profiling(profile)
+                .with_timeouts(timeouts);
 
-                    let result_cell = result_stores
-                        .get(&session_id)
-                        .expect("session disappeared unexpectedly");
+            let execution_start_timer = Instant::now();
 
+            let launched = context
+                .execute_computation(
+                    session_id.clone(),
+                    &computation,
+                    arguments,
+                    role_assignments,
+                )
+                .await
+                .map_err(|e| match e.downcast::<Error>() {
+                    Ok(e) => *e,
+                    Err(e) => Error::LaunchFailed(e.to_string()),
+                });
+            let (handle, outputs) = match launched {
+                Ok(launched) => launched,
+                Err(e) => {
+                    tracing::error!("Failed to launch session {}: {}", session_id, e);
+                    result_store.finish(SessionResult::Finished(ComputationOutputs {
+                        outputs: Err(e),
+                        elapsed_time: None,
+                        profile: None,
+                    }));
+                    return;
+                }
+            };
+
+            abort_handles.insert(session_id.clone(), handle.abort_handle());
+            // the session may have been aborted while pending
+            if result_store.abort_requested.load(Ordering::SeqCst) {
+                handle.abort_handle().abort();
+            }
+            result_store.running(handle.progress());
+
+            let profiler = handle.profiler();
+            let join_result = handle.join_on_first_error().await;
+            let _ = abort_handles.remove(&session_id);
+
+            let root_cause = join_result.err().map(|e| match e.downcast::<Error>() {
+                Ok(e) => e,
+                // tasks that panicked or were cancelled
+                Err(e) => Error::Unexpected(Some(e.to_string())),
+            });
+
+            let session_result = match root_cause {
+                Some(Error::SessionAborted(_)) => {
+                    tracing::info!("Session {} aborted", session_id);
+                    SessionResult::Aborted
+                }
+                root_cause => {
+                    let outputs = match root_cause {
+                        Some(e) => {
+                            tracing::error!("Session {} failed: {}", session_id, e);
+                            Err(e)
+                        }
+                        None => collect_outputs(&computation, outputs).await,
+                    };
                     let execution_stop_timer = Instant::now();
                     let elapsed_time = execution_stop_timer.duration_since(execution_start_timer);
-                    result_cell.set(ComputationOutputs {
-                        outputs: results,
+                    SessionResult::Finished(ComputationOutputs {
+                        outputs,
                         elapsed_time: Some(elapsed_time),
-                    });
-                });
+                        profile: profiler.map(|profiler| profiler.profile()),
+                    })
+                }
+            };
 
-                Ok(tonic::Response::new(LaunchComputationResponse::default()))
-            }
-        }
+            result_store.finish(session_result);
+        });
+
+        Ok(tonic::Response::new(LaunchComputationResponse::default()))
     }
 
     async fn abort_computation(
         &self,
-        _request: tonic::Request<AbortComputationRequest>,
+        request: tonic::Request<AbortComputationRequest>,
     ) -> Result<tonic::Response<AbortComputationResponse>, tonic::Status> {
-        unimplemented!()
+        tracing::info!("Aborting computation");
+
+        self.check_choreographer(&request)?;
+        let request = request.into_inner();
+
+        let session_id = bincode::deserialize::<SessionId>(&request.session_id).map_err(|_e| {
+            tonic::Status::new(
+                tonic::Code::Aborted,
+                "failed to parse session id".to_string(),
+            )
+        })?;
+
+        self.evict_finished();
+        match self.result_stores.get(&session_id) {
+            Some(result_store) => result_store.abort_requested.store(true, Ordering::SeqCst),
+            None => {
+                return Err(tonic::Status::new(
+                    tonic::Code::NotFound,
+                    "unknown session id".to_string(),
+                ))
+            }
+        }
+
+        // Aborting cancels all tasks of the session, which in turn drops their
+        // networking handles and with that the session's rendezvous cells.
+        // The task joining on the session marks the result cell as aborted.
+        // Sessions that are still pending are aborted once they have been set up,
+        // and sessions that have already finished are left untouched.
+        if let Some((_, abort_handle)) = self.abort_handles.remove(&session_id) {
+            abort_handle.abort();
+        }
+
+        Ok(tonic::Response::new(AbortComputationResponse::default()))
     }
 
     async fn retrieve_results(
@@ -218,12 +510,51 @@ impl Choreography for GrpcChoreography {
             )
         })?;
 
+        self.evict_finished();
         match self.result_stores.get(&session_id) {
-            Some(results) => {
-                let results = results.value().get().await;
-                let values = bincode::serialize(&results).expect("failed to serialize results");
+            Some(result_store) => {
+                // release the map guard before awaiting
+                let result_store = Arc::clone(result_store.value());
+                match result_store.results.get().await {
+                    SessionResult::Finished(results) => {
+                        let values =
+                            bincode::serialize(&results).expect("failed to serialize results");
+                        Ok(tonic::Response::new(RetrieveResultsResponse { values }))
+                    }
+                    SessionResult::Aborted => Err(tonic::Status::new(
+                        tonic::Code::Aborted,
+                        "session was aborted".to_string(),
+                    )),
+                }
+            }
+            None => Err(tonic::Status::new(
+                tonic::Code::NotFound,
+                "unknown session id".to_string(),
+            )),
+        }
+    }
+
+    async fn get_session_status(
+        &self,
+        request: tonic::Request<GetSessionStatusRequest>,
+    ) -> Result<tonic::Response<GetSessionStatusResponse>, tonic::Status> {
+        self.check_choreographer(&request)?;
+        let request = request.into_inner();
 
-                Ok(tonic::Response::new(RetrieveResultsResponse { values }))
+        let session_id = bincode::deserialize::<SessionId>(&request.session_id).map_err(|_e| {
+            tonic::Status::new(
+                tonic::Code::Aborted,
+                "failed to parse session id".to_string(),
+            )
+        })?;
+
+        self.evict_finished();
+        match self.result_stores.get(&session_id) {
+            Some(result_store) => {
+                let status = result_store.status(&session_id).into();
+                Ok(tonic::Response::new(GetSessionStatusResponse {
+                    status: Some(status),
+                }))
             }
             None => Err(tonic::Status::new(
                 tonic::Code::NotFound,
@@ -231,4 +562,20 @@ impl Choreography for GrpcChoreography {
             )),
         }
     }
+
+    async fn list_sessions(
+        &self,
+        request: tonic::Request<ListSessionsRequest>,
+    ) -> Result<tonic::Response<ListSessionsResponse>, tonic::Status> {
+        self.check_choreographer(&request)?;
+
+        self.evict_finished();
+        let sessions = self
+            .result_stores
+            .iter()
+            .map(|entry| entry.value().status(entry.key()).into())
+            .collect();
+
+        Ok(tonic::Response::new(ListSessionsResponse { sessions }))
+    }
 }
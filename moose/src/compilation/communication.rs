@@ -0,0 +1,173 @@
+//! Static estimation of communication costs.
+
+use super::shape_inference::{InputShapes, ShapeTable};
+use crate::computation::*;
+use crate::error::Error;
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::HashMap;
+
+/// Traffic estimated between a single pair of roles.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct LinkStats {
+    /// Number of messages sent.
+    pub messages: usize,
+    /// Estimated number of payload bytes sent, excluding messages of unknown size.
+    pub bytes: usize,
+    /// Number of messages for which no size could be estimated.
+    pub unknown_messages: usize,
+}
+
+/// Communication costs of a networked computation.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct CommunicationStats {
+    /// Traffic per (sender, receiver) pair.
+    pub links: HashMap<(Role, Role), LinkStats>,
+    /// Number of communication rounds on the critical path.
+    pub rounds: usize,
+}
+
+/// Estimates communication costs of a computation on which the networking pass has been run.
+///
+/// Message sizes are derived from the signatures of `Send` operations together with shapes
+/// inferred from constants and from `input_shapes`. Sizes are estimates of the raw payload
+/// and do not include serialization overhead.
+pub fn estimate_communication(
+    comp: &Computation,
+    input_shapes: &InputShapes,
+) -> anyhow::Result<CommunicationStats> {
+    let graph = comp.as_graph();
+    let order = petgraph::algo::toposort(&graph, None).map_err(|_| {
+        Error::MalformedComputation("cycle detected in the computation graph".into())
+    })?;
+
+    let mut shapes = ShapeTable::default();
+    let mut stats = CommunicationStats::default();
+    // Number of rounds needed before each operation can be evaluated
+    let mut depth: Vec<usize> = vec![0; comp.operations.len()];
+
+    for node in order {
+        let index = graph[node].index;
+        let op = &comp.operations[index];
+
+        depth[index] = graph
+            .edges_directed(node, Direction::Incoming)
+            .map(|edge| {
+                let source = graph[edge.source()].index;
+                match (&comp.operations[source].kind, &op.kind) {
+                    (Operator::Send(_), Operator::Receive(_)) => depth[source] + 1,
+                    _ => depth[source],
+                }
+            })
+            .max()
+            .unwrap_or(0);
+
+        shapes.propagate(op, input_shapes)?;
+
+        if let Operator::Send(send_op) = &op.kind {
+            let sender = match &op.placement {
+                Placement::Host(plc) => plc.owner.clone(),
+                plc => {
+                    return Err(Error::MalformedComputation(format!(
+                        "Send operation '{}' is not placed on a host but on {:?}",
+                        op.name, plc
+                    ))
+                    .into())
+                }
+            };
+            let link = stats
+                .links
+                .entry((sender, send_op.receiver.clone()))
+                .or_default();
+            link.messages += 1;
+            match payload_bytes(send_op.sig.arg(0)?, &op.inputs[0], &shapes) {
+                Some(bytes) => link.bytes += bytes,
+                None => link.unknown_messages += 1,
+            }
+        }
+    }
+
+    stats.rounds = depth.into_iter().max().unwrap_or(0);
+    Ok(stats)
+}
+
+fn payload_bytes(ty: Ty, value: &str, shapes: &ShapeTable) -> Option<usize> {
+    match ty {
+        Ty::HostUnit => Some(0),
+        Ty::HostSeed | Ty::HostPrfKey => Some(16),
+        Ty::HostShape => shapes.values.get(value).map(|shape| 8 * shape.len()),
+        ty => {
+            let bits = element_bits(ty)?;
+            let elements: usize = shapes.tensors.get(value)?.iter().product();
+            Some((bits * elements + 7) / 8)
+        }
+    }
+}
+
+fn element_bits(ty: Ty) -> Option<usize> {
+    match ty {
+        Ty::HostBitTensor
+        | Ty::HostBitArray64
+        | Ty::HostBitArray128
+        | Ty::HostBitArray224
+        | Ty::HostBitArray256 => Some(1),
+        Ty::HostInt8Tensor | Ty::HostUint8Tensor => Some(8),
+        Ty::HostInt16Tensor | Ty::HostUint16Tensor => Some(16),
+        Ty::HostFloat32Tensor | Ty::HostInt32Tensor | Ty::HostUint32Tensor => Some(32),
+        Ty::HostRing64Tensor
+        | Ty::HostFixed64Tensor
+        | Ty::HostFloat64Tensor
+        | Ty::HostInt64Tensor
+        | Ty::HostUint64Tensor => Some(64),
+        Ty::HostRing128Tensor | Ty::HostFixed128Tensor => Some(128),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_estimate_communication() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Input{arg_name = "x"}: () -> HostFloat64Tensor @Host(alice)
+        send_x = Send{rendezvous_key = 30313233343536373839616263646566, receiver = "bob"}: (HostFloat64Tensor) -> HostUnit (x) @Host(alice)
+        recv_x = Receive{rendezvous_key = 30313233343536373839616263646566, sender = "alice"}: () -> HostFloat64Tensor () @Host(bob)
+        y = Constant{value = HostFloat64Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat64Tensor @Host(bob)
+        dot = Dot: (HostFloat64Tensor, HostFloat64Tensor) -> HostFloat64Tensor (recv_x, y) @Host(bob)
+        send_dot = Send{rendezvous_key = 30313233343536373839616263646567, receiver = "alice"}: (HostFloat64Tensor) -> HostUnit (dot) @Host(bob)
+        recv_dot = Receive{rendezvous_key = 30313233343536373839616263646567, sender = "bob"}: () -> HostFloat64Tensor () @Host(alice)
+        z = Output{tag = "z"}: (HostFloat64Tensor) -> HostFloat64Tensor (recv_dot) @Host(alice)"#;
+        let comp: Computation = source.try_into()?;
+
+        let input_shapes = HashMap::from([("x".to_string(), vec![3, 2])]);
+        let stats = estimate_communication(&comp, &input_shapes)?;
+        assert_eq!(stats.rounds, 2);
+
+        let alice = Role::from("alice");
+        let bob = Role::from("bob");
+        assert_eq!(
+            stats.links[&(alice.clone(), bob.clone())],
+            LinkStats {
+                messages: 1,
+                bytes: 3 * 2 * 8,
+                unknown_messages: 0,
+            }
+        );
+        assert_eq!(
+            stats.links[&(bob, alice)],
+            LinkStats {
+                messages: 1,
+                bytes: 3 * 2 * 8,
+                unknown_messages: 0,
+            }
+        );
+
+        // Without input shapes the sizes are unknown
+        let stats = estimate_communication(&comp, &HashMap::new())?;
+        assert!(stats.links.values().all(|link| link.unknown_messages == 1));
+        Ok(())
+    }
+}
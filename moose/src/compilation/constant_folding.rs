@@ -0,0 +1,315 @@
+use super::shape_inference::{InputShapes, ShapeTable};
+use crate::computation::*;
+use crate::execution::synchronous::SyncSession;
+use crate::execution::Session;
+use crate::floatingpoint::FloatTensor;
+use crate::logical::{AbstractShape, AbstractTensor};
+use std::collections::HashMap;
+
+/// Largest serialized size of a value that is folded into a constant, in bytes.
+const MAX_FOLDED_BYTES: u64 = 1 << 20;
+
+/// Evaluates host-placed operations whose inputs are all constants and replaces them with
+/// constant operations.
+///
+/// Only operations whose inputs are placed on the same host as the operation itself are
+/// folded, so values never move between placements and no secret material is introduced
+/// into the graph. Only the operations listed in `is_foldable` are folded, and only when
+/// their result is at most `MAX_FOLDED_BYTES` once serialized. Constants that are no longer
+/// used after folding are left to the pruning pass.
+///
+/// Host kernels may panic on operands they do not support, so operands are checked with
+/// shape inference before evaluating an operation, and operations whose shapes are not
+/// known or do not match are never evaluated. Arithmetic is only folded on floating-point
+/// operands, since integer kernels panic on overflow and division by zero.
+pub fn constant_folding(comp: Computation) -> anyhow::Result<Computation> {
+    let mut comp = if super::toposort::is_toposorted(&comp) {
+        comp
+    } else {
+        super::toposort::toposort(comp)?
+    };
+
+    let session = SyncSession::from_session_id(SessionId::random());
+    let mut shapes = ShapeTable::default();
+
+    // Values of all constant operations found so far, including folded ones
+    let mut env: HashMap<String, (Placement, Value)> = HashMap::new();
+
+    for op in comp.operations.iter_mut() {
+        let shape_known = match shapes.propagate(op, &InputShapes::default()) {
+            Ok(()) => shapes.tensors.contains_key(&op.name) || shapes.values.contains_key(&op.name),
+            Err(_) => false,
+        };
+
+        if !matches!(op.placement, Placement::Host(_)) {
+            continue;
+        }
+
+        if matches!(op.kind, Operator::Constant(_)) {
+            if let Ok(value) = session.execute(&op.kind, &op.placement, vec![]) {
+                env.insert(op.name.clone(), (op.placement.clone(), value));
+            }
+            continue;
+        }
+
+        if op.inputs.is_empty() || !is_foldable(&op.kind) || !shape_known {
+            continue;
+        }
+
+        let operands: Option<Vec<Value>> = op
+            .inputs
+            .iter()
+            .map(|input| match env.get(input) {
+                Some((plc, value)) if plc == &op.placement => Some(value.clone()),
+                _ => None,
+            })
+            .collect();
+        let operands = match operands {
+            Some(operands) => operands,
+            None => continue,
+        };
+
+        // integer arithmetic panics on overflow and division by zero, while floating-point
+        // arithmetic gives infinities
+        let arithmetic = matches!(
+            op.kind,
+            Operator::Add(_)
+                | Operator::Sub(_)
+                | Operator::Mul(_)
+                | Operator::Div(_)
+                | Operator::Neg(_)
+        );
+        if arithmetic && !operands.iter().all(is_float) {
+            continue;
+        }
+
+        // Operations for which no kernel exists, or whose kernel fails on the given operands,
+        // are simply left in place for the error to surface at runtime
+        let value = match session.execute(&op.kind, &op.placement, operands) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+
+        let size = bincode::serialized_size(&value).unwrap_or(u64::MAX);
+        if size > MAX_FOLDED_BYTES {
+            continue;
+        }
+
+        if let Some(constant) = into_constant(value.clone()) {
+            op.kind = ConstantOp {
+                sig: Signature::nullary(value.ty()),
+                value: constant,
+            }
+            .into();
+            op.inputs = vec![];
+            env.insert(op.name.clone(), (op.placement.clone(), value));
+        }
+    }
+
+    Ok(comp)
+}
+
+/// Returns true for the operations that may be evaluated at compile time.
+///
+/// This is an allow-list of deterministic operations on plain host values, so that new
+/// operations, such as ones involving randomness or secret sharing, are never folded by
+/// accident.
+fn is_foldable(op: &Operator) -> bool {
+    use Operator::*;
+    matches!(
+        op,
+        Shape(_)
+            | Cast(_)
+            | Reshape(_)
+            | ExpandDims(_)
+            | Add(_)
+            | Sub(_)
+            | Mul(_)
+            | Div(_)
+            | Neg(_)
+    )
+}
+
+fn is_float(value: &Value) -> bool {
+    match value {
+        Value::HostFloat32Tensor(_) | Value::HostFloat64Tensor(_) => true,
+        Value::Tensor(x) => matches!(**x, AbstractTensor::Float32(_) | AbstractTensor::Float64(_)),
+        _ => false,
+    }
+}
+
+/// Converts a host value into a constant, if it can be represented as one.
+///
+/// Seeds and keys are deliberately not converted.
+fn into_constant(value: Value) -> Option<Constant> {
+    match value {
+        Value::HostShape(x) => Some(Constant::RawShape(x.0)),
+        Value::HostString(x) => Some(Constant::String(x.0)),
+        Value::HostBitTensor(x) => Some(Constant::HostBitTensor(*x)),
+        Value::HostRing64Tensor(x) => Some(Constant::HostRing64Tensor(*x)),
+        Value::HostRing128Tensor(x) => Some(Constant::HostRing128Tensor(*x)),
+        Value::HostFloat32Tensor(x) => Some(Constant::HostFloat32Tensor(*x)),
+        Value::HostFloat64Tensor(x) => Some(Constant::HostFloat64Tensor(*x)),
+        Value::HostInt8Tensor(x) => Some(Constant::HostInt8Tensor(*x)),
+        Value::HostInt16Tensor(x) => Some(Constant::HostInt16Tensor(*x)),
+        Value::HostInt32Tensor(x) => Some(Constant::HostInt32Tensor(*x)),
+        Value::HostInt64Tensor(x) => Some(Constant::HostInt64Tensor(*x)),
+        Value::HostUint8Tensor(x) => Some(Constant::HostUint8Tensor(*x)),
+        Value::HostUint16Tensor(x) => Some(Constant::HostUint16Tensor(*x)),
+        Value::HostUint32Tensor(x) => Some(Constant::HostUint32Tensor(*x)),
+        Value::HostUint64Tensor(x) => Some(Constant::HostUint64Tensor(*x)),
+        Value::Shape(x) => match *x {
+            AbstractShape::Host(x) => Some(Constant::RawShape(x.0)),
+            _ => None,
+        },
+        Value::Tensor(x) => match *x {
+            AbstractTensor::Float32(FloatTensor::Host(x)) => Some(Constant::HostFloat32Tensor(x)),
+            AbstractTensor::Float64(FloatTensor::Host(x)) => Some(Constant::HostFloat64Tensor(x)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compilation::shape_inference::infer_shapes;
+    use std::convert::TryInto;
+
+    fn find<'c>(comp: &'c Computation, name: &str) -> &'c Operation {
+        comp.operations.iter().find(|op| op.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_fold_chain() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        shape = Shape: (HostFloat32Tensor) -> HostShape (x) @Host(alice)
+        double = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, x) @Host(alice)
+        prod = Mul: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (double, x) @Host(alice)
+        y = Input{arg_name = "y"}: () -> HostFloat32Tensor @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (prod, y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (add) @Host(alice)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert_eq!(comp.operations.len(), 7);
+
+        let shape = find(&comp, "shape");
+        assert!(matches!(shape.kind, Operator::Constant(_)));
+        assert!(shape.inputs.is_empty());
+
+        let prod = find(&comp, "prod");
+        match &prod.kind {
+            Operator::Constant(op) => {
+                assert_eq!(op.sig.ret(), Ty::HostFloat32Tensor);
+                assert!(matches!(op.value, Constant::HostFloat32Tensor(_)));
+            }
+            _ => panic!("expected `prod` to be folded"),
+        }
+
+        assert!(matches!(find(&comp, "add").kind, Operator::Add(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_outside_allow_list() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        shape = Constant{value=HostShape([2, 2])}: () -> HostShape @Host(alice)
+        ones = Ones: (HostShape) -> HostFloat32Tensor (shape) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (ones) @Host(alice)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert!(matches!(find(&comp, "ones").kind, Operator::Ones(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_of_mismatching_shapes() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([1.0, 2.0, 3.0])}: () -> HostFloat32Tensor @Host(alice)
+        y = Constant{value=HostFloat32Tensor([1.0, 2.0])}: () -> HostFloat32Tensor @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (add) @Host(alice)"#;
+        let comp: Computation = source.try_into()?;
+
+        // the operation is left for the error to be reported when checking shapes
+        let comp = constant_folding(comp)?;
+        assert!(matches!(find(&comp, "add").kind, Operator::Add(_)));
+        let res = infer_shapes(&comp, &InputShapes::default());
+        assert!(res.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_of_integer_division() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostInt64Tensor([1, 2])}: () -> HostInt64Tensor @Host(alice)
+        y = Constant{value=HostInt64Tensor([1, 0])}: () -> HostInt64Tensor @Host(alice)
+        div = Div: (HostInt64Tensor, HostInt64Tensor) -> HostInt64Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostInt64Tensor) -> HostInt64Tensor (div) @Host(alice)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert!(matches!(find(&comp, "div").kind, Operator::Div(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_of_integer_arithmetic() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostInt8Tensor([127])}: () -> HostInt8Tensor @Host(alice)
+        y = Constant{value=HostInt8Tensor([1])}: () -> HostInt8Tensor @Host(alice)
+        add = Add: (HostInt8Tensor, HostInt8Tensor) -> HostInt8Tensor (x, y) @Host(alice)
+        mul = Mul: (HostInt8Tensor, HostInt8Tensor) -> HostInt8Tensor (x, x) @Host(alice)
+        z = Output{tag = "z"}: (HostInt8Tensor) -> HostInt8Tensor (add) @Host(alice)
+        w = Output{tag = "w"}: (HostInt8Tensor) -> HostInt8Tensor (mul) @Host(alice)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert!(matches!(find(&comp, "add").kind, Operator::Add(_)));
+        assert!(matches!(find(&comp, "mul").kind, Operator::Mul(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_of_large_values() -> std::result::Result<(), anyhow::Error> {
+        // broadcasting a column and a row of 512 values gives 512 * 512 * 8 bytes
+        let column = vec!["[1.0]"; 512].join(", ");
+        let row = vec!["1.0"; 512].join(", ");
+        let source = format!(
+            r#"
+        x = Constant{{value=HostFloat64Tensor([{}])}}: () -> HostFloat64Tensor @Host(alice)
+        y = Constant{{value=HostFloat64Tensor([[{}]])}}: () -> HostFloat64Tensor @Host(alice)
+        add = Add: (HostFloat64Tensor, HostFloat64Tensor) -> HostFloat64Tensor (x, y) @Host(alice)
+        z = Output{{tag = "z"}}: (HostFloat64Tensor) -> HostFloat64Tensor (add) @Host(alice)"#,
+            column, row
+        );
+
+        let comp = constant_folding(source.as_str().try_into()?)?;
+        assert!(matches!(find(&comp, "add").kind, Operator::Add(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_across_placements() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        shape = Shape: (HostFloat32Tensor) -> HostShape (x) @Host(bob)
+        z = Output{tag = "z"}: (HostShape) -> HostShape (shape) @Host(bob)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert!(matches!(find(&comp, "shape").kind, Operator::Shape(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_fold_of_randomness() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        shape = Constant{value=HostShape([2, 2])}: () -> HostShape @Host(alice)
+        x = Sample{}: (HostShape) -> HostRing64Tensor (shape) @Host(alice)
+        z = Output{tag = "z"}: (HostRing64Tensor) -> HostRing64Tensor (x) @Host(alice)"#;
+
+        let comp = constant_folding(source.try_into()?)?;
+        assert!(matches!(find(&comp, "x").kind, Operator::Sample(_)));
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::computation::{Computation, Operator, Placement};
+use bitvec::prelude::*;
+use std::collections::HashMap;
+
+/// Merges structurally identical operations.
+///
+/// Two operations are considered identical if they have the same operator (including
+/// attributes and signature), the same inputs, and the same placement; all uses of the
+/// later one are redirected to the first one. Operations with side effects or randomness
+/// are never merged.
+pub fn common_subexpression_elimination(comp: Computation) -> anyhow::Result<Computation> {
+    // Inputs must be merged before their users; computations coming out of lowering
+    // are typically already sorted, in which case we preserve their order
+    let mut comp = if super::toposort::is_toposorted(&comp) {
+        comp
+    } else {
+        super::toposort::toposort(comp)?
+    };
+
+    // Names of merged operations mapped to the names of the operations replacing them
+    let mut replacements: HashMap<String, String> = HashMap::new();
+    // Operations to keep
+    let mut keep: BitVec<u8, Lsb0> = BitVec::repeat(true, comp.operations.len());
+
+    {
+        let mut seen: HashMap<(&Operator, Vec<String>, &Placement), &String> =
+            HashMap::with_capacity(comp.operations.len());
+
+        for (i, op) in comp.operations.iter().enumerate() {
+            if !is_mergeable(&op.kind) {
+                continue;
+            }
+
+            let inputs: Vec<String> = op
+                .inputs
+                .iter()
+                .map(|input| replacements.get(input).unwrap_or(input).clone())
+                .collect();
+
+            match seen.get(&(&op.kind, inputs.clone(), &op.placement)) {
+                Some(&existing) => {
+                    replacements.insert(op.name.clone(), existing.clone());
+                    keep.set(i, false);
+                }
+                None => {
+                    seen.insert((&op.kind, inputs, &op.placement), &op.name);
+                }
+            }
+        }
+    }
+
+    if replacements.is_empty() {
+        return Ok(comp);
+    }
+
+    let mut iter = keep.iter();
+    comp.operations.retain(|_| *iter.next().unwrap());
+
+    for op in comp.operations.iter_mut() {
+        for input in op.inputs.iter_mut() {
+            if let Some(replacement) = replacements.get(input) {
+                *input = replacement.clone();
+            }
+        }
+    }
+
+    Ok(comp)
+}
+
+/// Returns false for operations that may not be merged with others.
+///
+/// This covers operations with side effects as well as operations that produce or derive
+/// randomness, where merging could lead to correlated values and hence leak information.
+fn is_mergeable(op: &Operator) -> bool {
+    use Operator::*;
+    !matches!(
+        op,
+        Sample(_)
+            | SampleSeeded(_)
+            | PrfKeyGen(_)
+            | DeriveSeed(_)
+            | Send(_)
+            | Receive(_)
+            | Save(_)
+            | Output(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::textual::ToTextual;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_nothing_to_merge() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        y = Constant{value=HostFloat32Tensor([[5.0, 6.0], [7.0, 8.0]])}: () -> HostFloat32Tensor @Host(alice)
+        mul = Mul: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (mul) @Host(alice)"#;
+
+        let comp = common_subexpression_elimination(source.try_into()?)?;
+        assert_eq!(comp.operations.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_transitive_merge() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        y = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        shape_x = Shape: (HostFloat32Tensor) -> HostShape (x) @Host(alice)
+        shape_y = Shape: (HostFloat32Tensor) -> HostShape (y) @Host(alice)
+        ones_x = Ones: (HostShape) -> HostFloat32Tensor (shape_x) @Host(alice)
+        ones_y = Ones: (HostShape) -> HostFloat32Tensor (shape_y) @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (ones_x, ones_y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (add) @Host(alice)"#;
+
+        let comp = common_subexpression_elimination(source.try_into()?)?;
+        // `y`, `shape_y` and `ones_y` should all be merged
+        assert_eq!(comp.operations.len(), 5);
+        let comp = comp.to_textual();
+        assert!(comp.contains(
+            "add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (ones_x, ones_x) @Host(alice)"
+        ));
+        assert!(!comp.contains("shape_y"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_merge_across_placements() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        y = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(bob)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (add) @Host(alice)"#;
+
+        let comp = common_subexpression_elimination(source.try_into()?)?;
+        assert_eq!(comp.operations.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_merge_of_randomness() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        shape = Constant{value=HostShape([2, 2])}: () -> HostShape @Host(alice)
+        x = Sample{}: (HostShape) -> HostRing64Tensor (shape) @Host(alice)
+        y = Sample{}: (HostShape) -> HostRing64Tensor (shape) @Host(alice)
+        add = Add: (HostRing64Tensor, HostRing64Tensor) -> HostRing64Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostRing64Tensor) -> HostRing64Tensor (add) @Host(alice)"#;
+
+        let comp = common_subexpression_elimination(source.try_into()?)?;
+        assert_eq!(comp.operations.len(), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! Static analysis of which roles learn which data in plaintext.
+
+use crate::computation::*;
+use crate::error::Error;
+use crate::textual::ToTextual;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// A point at which data owned by one role becomes visible in plaintext to another role.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Flow {
+    /// Role that contributed the data through an input, load or constant.
+    pub owner: Role,
+    /// Role that can see the data in plaintext.
+    pub recipient: Role,
+    /// Name of the operation at which the data becomes visible.
+    pub operation: String,
+    /// Placement of the operation at which the data becomes visible.
+    pub placement: Placement,
+}
+
+impl std::fmt::Display for Flow {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "data of {} revealed to {} by '{}' on {}",
+            self.owner,
+            self.recipient,
+            self.operation,
+            self.placement.to_textual()
+        )
+    }
+}
+
+/// Allow-list of flows that are accepted by the leakage check.
+///
+/// Policies are written in TOML, for instance:
+///
+/// ```toml
+/// [[allow]]
+/// from = "bob"
+/// to = "alice"
+/// operation = "output_0"
+/// ```
+///
+/// Omitted fields match any value.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct LeakagePolicy {
+    #[serde(default)]
+    pub allow: Vec<AllowedFlow>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct AllowedFlow {
+    pub from: Option<Role>,
+    pub to: Option<Role>,
+    pub operation: Option<String>,
+}
+
+impl FromStr for LeakagePolicy {
+    type Err = toml::de::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+impl LeakagePolicy {
+    pub fn allows(&self, flow: &Flow) -> bool {
+        self.allow.iter().any(|allowed| {
+            allowed.from.as_ref().map_or(true, |r| r == &flow.owner)
+                && allowed.to.as_ref().map_or(true, |r| r == &flow.recipient)
+                && allowed
+                    .operation
+                    .as_ref()
+                    .map_or(true, |op| op == &flow.operation)
+        })
+    }
+}
+
+/// Finds every point where data owned by one role flows in plaintext to another role.
+///
+/// Data is owned by the host on which it enters the computation through `Input`, `Load` or
+/// `Constant` operations, and ownership follows the data through all operations. Data is
+/// visible in plaintext on host and mirrored placements but not on replicated or additive
+/// placements, so a flow is reported whenever an operation on a host or mirrored placement
+/// consumes a value from a different placement that carries data of another role. Values
+/// passed through `Send` and `Receive` are reported at the receiving operation.
+///
+/// The analysis is meant for computations before lowering; after lowering, host placements
+/// also hold secret shares, which would be reported as plaintext.
+pub fn analyze_leakage(comp: &Computation) -> anyhow::Result<Vec<Flow>> {
+    let graph = comp.as_graph();
+    let order = petgraph::algo::toposort(&graph, None).map_err(|_| {
+        Error::MalformedComputation("cycle detected in the computation graph".into())
+    })?;
+
+    let indices: HashMap<&String, usize> = comp
+        .operations
+        .iter()
+        .enumerate()
+        .map(|(i, op)| (&op.name, i))
+        .collect();
+
+    // Roles whose data each operation depends on
+    let mut owners: Vec<HashSet<Role>> = vec![HashSet::new(); comp.operations.len()];
+    // Owners of values sent under each rendezvous key
+    let mut sent: HashMap<&RendezvousKey, HashSet<Role>> = HashMap::new();
+    let mut flows: Vec<Flow> = Vec::new();
+
+    for node in order {
+        let index = graph[node].index;
+        let op = &comp.operations[index];
+        let recipients = plaintext_viewers(&op.placement);
+
+        let mut incoming: Vec<HashSet<Role>> = Vec::with_capacity(op.inputs.len());
+        for input in &op.inputs {
+            let input_index = *indices.get(input).ok_or_else(|| {
+                Error::MalformedEnvironment(format!(
+                    "input '{}' of operation '{}' is not defined",
+                    input, op.name
+                ))
+            })?;
+            if comp.operations[input_index].placement != op.placement {
+                incoming.push(owners[input_index].clone());
+            }
+            let input_owners = owners[input_index].clone();
+            owners[index].extend(input_owners);
+        }
+
+        match (&op.kind, &op.placement) {
+            (
+                Operator::Input(_) | Operator::Load(_) | Operator::Constant(_),
+                Placement::Host(plc),
+            ) => {
+                owners[index].insert(plc.owner.clone());
+            }
+            (Operator::Send(send_op), _) => {
+                sent.insert(&send_op.rendezvous_key, owners[index].clone());
+            }
+            (Operator::Receive(receive_op), _) => {
+                let received = sent
+                    .get(&receive_op.rendezvous_key)
+                    .cloned()
+                    .unwrap_or_default();
+                owners[index].extend(received.iter().cloned());
+                incoming.push(received);
+            }
+            _ => (),
+        }
+
+        let mut new_flows: Vec<Flow> = incoming
+            .iter()
+            .flatten()
+            .flat_map(|owner| {
+                recipients
+                    .iter()
+                    .filter(move |recipient| *recipient != owner)
+                    .map(move |recipient| Flow {
+                        owner: owner.clone(),
+                        recipient: recipient.clone(),
+                        operation: op.name.clone(),
+                        placement: op.placement.clone(),
+                    })
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        new_flows.sort_by(|a, b| (&a.owner.0, &a.recipient.0).cmp(&(&b.owner.0, &b.recipient.0)));
+        flows.extend(new_flows);
+    }
+
+    Ok(flows)
+}
+
+/// Runs the leakage analysis and fails if any flow is not allowed by the policy.
+pub(crate) fn leakage_check(
+    comp: Computation,
+    policy: &LeakagePolicy,
+) -> anyhow::Result<Computation> {
+    let violations: Vec<String> = analyze_leakage(&comp)?
+        .into_iter()
+        .filter(|flow| !policy.allows(flow))
+        .map(|flow| flow.to_string())
+        .collect();
+    if !violations.is_empty() {
+        return Err(Error::Compilation(format!(
+            "Plaintext flows not allowed by leakage policy: {}",
+            violations.join("; ")
+        ))
+        .into());
+    }
+    Ok(comp)
+}
+
+/// Roles that can see values on the given placement in plaintext.
+fn plaintext_viewers(plc: &Placement) -> &[Role] {
+    match plc {
+        Placement::Host(plc) => std::slice::from_ref(&plc.owner),
+        Placement::Mirrored3(plc) => &plc.owners,
+        Placement::Replicated(_) | Placement::Additive(_) => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    const SOURCE: &str = r#"
+    x = Input{arg_name = "x"}: () -> Tensor<Float64> @Host(alice)
+    y = Input{arg_name = "y"}: () -> Tensor<Float64> @Host(bob)
+    x_fixed = Cast: (Tensor<Float64>) -> Tensor<Fixed128(24, 40)> (x) @Host(alice)
+    y_fixed = Cast: (Tensor<Float64>) -> Tensor<Fixed128(24, 40)> (y) @Host(bob)
+    mul = Mul: (Tensor<Fixed128(24, 40)>, Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (x_fixed, y_fixed) @Replicated(alice, bob, carole)
+    mul_host = Identity: (Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (mul) @Host(carole)
+    z = Output{tag = "z"}: (Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (mul_host) @Host(carole)"#;
+
+    #[test]
+    fn test_analyze_leakage() -> std::result::Result<(), anyhow::Error> {
+        let comp: Computation = SOURCE.try_into()?;
+        let flows = analyze_leakage(&comp)?;
+        let flows: Vec<(&str, &str, &str)> = flows
+            .iter()
+            .map(|flow| {
+                (
+                    flow.owner.0.as_str(),
+                    flow.recipient.0.as_str(),
+                    flow.operation.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            flows,
+            vec![
+                ("alice", "carole", "mul_host"),
+                ("bob", "carole", "mul_host")
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reveal_chain() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Input{arg_name = "x"}: () -> Tensor<Fixed128(24, 40)> @Host(alice)
+        y = Input{arg_name = "y"}: () -> Tensor<Fixed128(24, 40)> @Host(bob)
+        mul = Mul: (Tensor<Fixed128(24, 40)>, Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (x, y) @Replicated(alice, bob, carole)
+        mul_host = Identity: (Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (mul) @Host(alice)
+        mul_bob = Identity: (Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (mul_host) @Host(bob)"#;
+        let comp: Computation = source.try_into()?;
+        let flows = analyze_leakage(&comp)?;
+        assert_eq!(flows.len(), 2);
+        assert_eq!(flows[0].owner, Role::from("bob"));
+        assert_eq!(flows[0].recipient, Role::from("alice"));
+        assert_eq!(flows[1].owner, Role::from("alice"));
+        assert_eq!(flows[1].recipient, Role::from("bob"));
+        assert_eq!(flows[1].operation, "mul_bob");
+        Ok(())
+    }
+
+    #[test]
+    fn test_leakage_check_policy() -> std::result::Result<(), anyhow::Error> {
+        let comp: Computation = SOURCE.try_into()?;
+
+        let err = leakage_check(comp.clone(), &LeakagePolicy::default()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("data of alice revealed to carole by 'mul_host' on @Host(carole)"));
+
+        let policy: LeakagePolicy = r#"
+        [[allow]]
+        from = "alice"
+        to = "carole"
+
+        [[allow]]
+        to = "carole"
+        operation = "mul_host"
+        "#
+        .parse()?;
+        leakage_check(comp.clone(), &policy)?;
+
+        let policy: LeakagePolicy = r#"
+        [[allow]]
+        from = "alice"
+        "#
+        .parse()?;
+        let err = leakage_check(comp, &policy).unwrap_err().to_string();
+        assert!(!err.contains("data of alice"));
+        assert!(err.contains("data of bob"));
+        Ok(())
+    }
+}
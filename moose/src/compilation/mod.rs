@@ -2,13 +2,20 @@
 
 use crate::computation::Computation;
 use crate::textual::ToTextual;
+use leakage::LeakagePolicy;
+use shape_inference::InputShapes;
 use std::convert::TryFrom;
 
+pub mod communication;
+mod constant_folding;
+mod cse;
 mod deprecated_shape;
+pub mod leakage;
 mod lowering;
 mod networking;
 mod print;
 mod pruning;
+pub mod shape_inference;
 pub mod toposort;
 mod typing;
 mod well_formed;
@@ -40,8 +47,16 @@ pub enum Pass {
     Toposort,
     /// Perform basic type inference of operations.
     Typing,
-    /// Check well-formedness.
+    /// Check well-formedness, including types, roles, and availability of kernels.
     WellFormed,
+    /// Merge structurally identical operations.
+    Cse,
+    /// Evaluate host operations on constants at compile time.
+    ConstantFold,
+    /// Infer static shapes from the given input shapes and fill in shape-derived attributes.
+    ShapeInference(InputShapes),
+    /// Fail if data flows in plaintext between roles in ways not allowed by the given policy.
+    LeakageCheck(LeakagePolicy),
     DeprecatedShape, // Support HostShape in the logical dialect (for pre-0.2.0 computations)
 }
 
@@ -55,6 +70,12 @@ impl Pass {
             Pass::Lowering => self::lowering::lowering(comp),
             Pass::Typing => self::typing::update_types_one_hop(comp),
             Pass::WellFormed => self::well_formed::well_formed(comp),
+            Pass::Cse => self::cse::common_subexpression_elimination(comp),
+            Pass::ConstantFold => self::constant_folding::constant_folding(comp),
+            Pass::ShapeInference(input_shapes) => {
+                self::shape_inference::shape_inference(comp, input_shapes)
+            }
+            Pass::LeakageCheck(policy) => self::leakage::leakage_check(comp, policy),
             Pass::DeprecatedShape => self::deprecated_shape::deprecated_shape_support(comp),
             Pass::Dump => {
                 println!("{}", comp.to_textual());
@@ -76,6 +97,10 @@ impl TryFrom<&str> for Pass {
             "toposort" => Ok(Pass::Toposort),
             "typing" => Ok(Pass::Typing),
             "wellformed" => Ok(Pass::WellFormed),
+            "cse" => Ok(Pass::Cse),
+            "constantfold" => Ok(Pass::ConstantFold),
+            "shapeinference" => Ok(Pass::ShapeInference(InputShapes::new())),
+            "leakagecheck" => Ok(Pass::LeakageCheck(LeakagePolicy::default())),
             "dump" => Ok(Pass::Dump),
             "deprecatedShape" => Ok(Pass::DeprecatedShape),
             missing_pass => Err(anyhow::anyhow!("Unknown pass requested: {}", missing_pass)),
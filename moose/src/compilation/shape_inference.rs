@@ -0,0 +1,521 @@
+//! Static shape inference.
+
+use crate::computation::*;
+use crate::error::Error;
+use crate::host::SliceInfoElem;
+use crate::textual::ToTextual;
+use std::collections::HashMap;
+
+/// Shapes of inputs, indexed by the argument name of `Input` operations or by the operation
+/// name of `Load` operations.
+pub type InputShapes = HashMap<String, Vec<usize>>;
+
+/// Concrete shapes inferred for the operations of a computation, indexed by operation name.
+#[derive(Default, Clone, Debug)]
+pub struct ShapeTable {
+    /// Shapes of tensor-valued operations.
+    pub tensors: HashMap<String, Vec<usize>>,
+    /// Values of shape-valued operations.
+    pub values: HashMap<String, Vec<usize>>,
+    /// Inputs of send operations, used to find the shapes of matching receive operations.
+    sent: HashMap<RendezvousKey, String>,
+}
+
+/// Infers concrete shapes of all operations for which they can be derived from constants and
+/// from the given input shapes.
+///
+/// Operations whose shapes cannot be derived are left out of the table; inputs with
+/// incompatible shapes are reported as errors.
+pub fn infer_shapes(comp: &Computation, input_shapes: &InputShapes) -> anyhow::Result<ShapeTable> {
+    let graph = comp.as_graph();
+    let order = petgraph::algo::toposort(&graph, None).map_err(|_| {
+        Error::MalformedComputation("cycle detected in the computation graph".into())
+    })?;
+
+    let mut table = ShapeTable::default();
+    for node in order {
+        table.propagate(&comp.operations[graph[node].index], input_shapes)?;
+    }
+    Ok(table)
+}
+
+/// Infers shapes and fills in shape-derived operator attributes.
+///
+/// A missing `upmost_index` on `Argmax`, `RingFixedpointArgmax` and `Softmax` operations
+/// is set to the size of the axis the operation is applied to; argmax operations without
+/// an axis are applied to axis 0.
+pub fn shape_inference(
+    mut comp: Computation,
+    input_shapes: &InputShapes,
+) -> anyhow::Result<Computation> {
+    let table = infer_shapes(&comp, input_shapes)?;
+
+    for op in comp.operations.iter_mut() {
+        let Operation {
+            name,
+            kind,
+            inputs,
+            placement,
+        } = op;
+        let (axis, upmost_index) = match kind {
+            Operator::Argmax(o) => (o.axis.unwrap_or(0), &mut o.upmost_index),
+            Operator::RingFixedpointArgmax(o) => (o.axis.unwrap_or(0), &mut o.upmost_index),
+            Operator::Softmax(o) => (o.axis, &mut o.upmost_index),
+            _ => continue,
+        };
+        let len = match table.tensors.get(&inputs[0]) {
+            Some(x) => *x
+                .get(axis)
+                .ok_or_else(|| mismatch(name, placement, "axis out of bounds"))?,
+            None => continue,
+        };
+        match upmost_index {
+            None => *upmost_index = Some(len),
+            Some(index) if *index > len => {
+                return Err(mismatch(
+                    name,
+                    placement,
+                    &format!(
+                        "upmost index {} exceeds size {} of axis {}",
+                        index, len, axis
+                    ),
+                ))
+            }
+            Some(_) => (),
+        }
+    }
+
+    Ok(comp)
+}
+
+fn mismatch(name: &str, plc: &Placement, reason: &str) -> anyhow::Error {
+    Error::Compilation(format!(
+        "Shape mismatch in operation '{}' on {}: {}",
+        name,
+        plc.to_textual(),
+        reason
+    ))
+    .into()
+}
+
+/// Returns early with `Ok(None)` if a shape is unknown.
+macro_rules! known {
+    ($x:expr) => {
+        match $x {
+            Some(x) => x,
+            None => return Ok(None),
+        }
+    };
+}
+
+impl ShapeTable {
+    /// Extends the table with the shapes of a single operation.
+    ///
+    /// Operations must be visited in topological order, with send operations visited before
+    /// their matching receive operations.
+    pub(crate) fn propagate(
+        &mut self,
+        op: &Operation,
+        input_shapes: &InputShapes,
+    ) -> anyhow::Result<()> {
+        if let Operator::Send(send_op) = &op.kind {
+            self.sent
+                .insert(send_op.rendezvous_key.clone(), op.inputs[0].clone());
+        }
+        if let Some(shape) = self.infer_value(op) {
+            self.values.insert(op.name.clone(), shape);
+        }
+        if let Some(shape) = self.infer_tensor(op, input_shapes)? {
+            self.tensors.insert(op.name.clone(), shape);
+        }
+        Ok(())
+    }
+
+    fn tensor(&self, op: &Operation, i: usize) -> Option<&Vec<usize>> {
+        op.inputs.get(i).and_then(|input| self.tensors.get(input))
+    }
+
+    fn value(&self, op: &Operation, i: usize) -> Option<&Vec<usize>> {
+        op.inputs.get(i).and_then(|input| self.values.get(input))
+    }
+
+    /// Infers the value of shape-valued operations.
+    fn infer_value(&self, op: &Operation) -> Option<Vec<usize>> {
+        match &op.kind {
+            Operator::Constant(c) => match &c.value {
+                Constant::RawShape(x) => Some(x.0.clone()),
+                _ => None,
+            },
+            Operator::Shape(_) => self.tensor(op, 0).cloned(),
+            Operator::Receive(r) => self.values.get(self.sent.get(&r.rendezvous_key)?).cloned(),
+            Operator::Slice(s) => {
+                let x = self.value(op, 0)?;
+                let (start, end) = slice_range(x.len(), s.slice.0.first()?)?;
+                Some(x[start..end].to_vec())
+            }
+            _ => None,
+        }
+    }
+
+    /// Infers the shape of tensor-valued operations.
+    fn infer_tensor(
+        &self,
+        op: &Operation,
+        input_shapes: &InputShapes,
+    ) -> anyhow::Result<Option<Vec<usize>>> {
+        use Operator::*;
+
+        let fail = |reason: String| -> anyhow::Result<Option<Vec<usize>>> {
+            Err(mismatch(&op.name, &op.placement, &reason))
+        };
+        let remove = |x: &[usize], axis: usize| -> anyhow::Result<Option<Vec<usize>>> {
+            match remove_axis(x, axis) {
+                Some(x) => Ok(Some(x)),
+                None => fail(format!("axis {} out of bounds for shape {:?}", axis, x)),
+            }
+        };
+
+        match &op.kind {
+            Constant(c) => Ok(constant_shape(&c.value)),
+            Input(i) => Ok(input_shapes.get(&i.arg_name).cloned()),
+            Load(_) => Ok(input_shapes.get(&op.name).cloned()),
+            Receive(r) => Ok(self
+                .sent
+                .get(&r.rendezvous_key)
+                .and_then(|sent| self.tensors.get(sent))
+                .cloned()),
+
+            Ones(_) | Zeros(_) | Fill(_) | Sample(_) | SampleSeeded(_) => {
+                Ok(self.value(op, 0).cloned())
+            }
+            Broadcast(_) => Ok(self.value(op, 1).cloned()),
+            Reshape(_) => {
+                let shape = known!(self.value(op, 1));
+                if let Some(x) = self.tensor(op, 0) {
+                    if x.iter().product::<usize>() != shape.iter().product::<usize>() {
+                        return fail(format!("cannot reshape {:?} into {:?}", x, shape));
+                    }
+                }
+                Ok(Some(shape.clone()))
+            }
+
+            Abs(_)
+            | Neg(_)
+            | Identity(_)
+            | Cast(_)
+            | Relu(_)
+            | Sigmoid(_)
+            | Exp(_)
+            | Log(_)
+            | Log2(_)
+            | Sqrt(_)
+            | Pow2(_)
+            | Sign(_)
+            | Shl(_)
+            | Shr(_)
+            | ShlDim(_)
+            | BitExtract(_)
+            | RingInject(_)
+            | RingFixedpointEncode(_)
+            | RingFixedpointDecode(_)
+            | FixedpointEncode(_)
+            | FixedpointDecode(_)
+            | Msb(_)
+            | EqualZero(_)
+            | TruncPr(_)
+            | Inverse(_)
+            | Softmax(_)
+            | Share(_)
+            | Reveal(_)
+            | Mirror(_)
+            | Demirror(_)
+            | AdtToRep(_)
+            | RepToAdt(_)
+            | Output(_) => Ok(self.tensor(op, 0).cloned()),
+
+            Add(_) | Sub(_) | Mul(_) | Div(_) | And(_) | Or(_) | Xor(_) | Less(_) | Greater(_)
+            | Equal(_) | Maximum(_) | AddN(_) | Mux(_) | Select(_) => {
+                let mut result: Vec<usize> = vec![];
+                for input in op.inputs.iter() {
+                    let x = known!(self.tensors.get(input));
+                    result = match broadcast(&result, x) {
+                        Some(result) => result,
+                        None => return fail(format!("cannot broadcast {:?} with {:?}", result, x)),
+                    };
+                }
+                Ok(Some(result))
+            }
+
+            Dot(_) => {
+                let x = known!(self.tensor(op, 0));
+                let y = known!(self.tensor(op, 1));
+                match dot(x, y) {
+                    Some(result) => Ok(Some(result)),
+                    None => fail(format!("cannot take dot product of {:?} and {:?}", x, y)),
+                }
+            }
+            Transpose(_) => Ok(Some(
+                known!(self.tensor(op, 0)).iter().rev().cloned().collect(),
+            )),
+            ExpandDims(e) => {
+                let mut x = known!(self.tensor(op, 0)).clone();
+                let mut axis = e.axis.clone();
+                axis.sort_unstable();
+                for a in axis {
+                    if a > x.len() {
+                        return fail(format!("axis {} out of bounds for shape {:?}", a, x));
+                    }
+                    x.insert(a, 1);
+                }
+                Ok(Some(x))
+            }
+            Squeeze(s) => {
+                let x = known!(self.tensor(op, 0));
+                match s.axis {
+                    Some(axis) if x.get(axis) != Some(&1) => {
+                        fail(format!("cannot squeeze axis {} of shape {:?}", axis, x))
+                    }
+                    Some(axis) => remove(x, axis),
+                    None => Ok(Some(x.iter().filter(|d| **d != 1).cloned().collect())),
+                }
+            }
+            AtLeast2D(a) => {
+                let x = known!(self.tensor(op, 0));
+                match x.as_slice() {
+                    [] => Ok(Some(vec![1, 1])),
+                    [n] if a.to_column_vector => Ok(Some(vec![*n, 1])),
+                    [n] => Ok(Some(vec![1, *n])),
+                    x => Ok(Some(x.to_vec())),
+                }
+            }
+            Sum(s) => match s.axis {
+                Some(axis) => remove(known!(self.tensor(op, 0)), axis),
+                None => Ok(Some(vec![])),
+            },
+            Mean(m) => match m.axis {
+                Some(axis) => remove(known!(self.tensor(op, 0)), axis as usize),
+                None => Ok(Some(vec![])),
+            },
+            RingFixedpointMean(m) => match m.axis {
+                Some(axis) => remove(known!(self.tensor(op, 0)), axis as usize),
+                None => Ok(Some(vec![])),
+            },
+            Argmax(a) => remove(known!(self.tensor(op, 0)), a.axis.unwrap_or(0)),
+            RingFixedpointArgmax(a) => remove(known!(self.tensor(op, 0)), a.axis.unwrap_or(0)),
+            IndexAxis(i) => {
+                let x = known!(self.tensor(op, 0));
+                match x.get(i.axis) {
+                    Some(len) if i.index >= *len => fail(format!(
+                        "index {} out of bounds for axis {} of shape {:?}",
+                        i.index, i.axis, x
+                    )),
+                    _ => remove(x, i.axis),
+                }
+            }
+            Index(_) | BitCompose(_) => remove(known!(self.tensor(op, 0)), 0),
+            BitDecompose(b) => {
+                let bits = match b.sig.arg(0)? {
+                    Ty::HostRing64Tensor => 64,
+                    Ty::HostRing128Tensor => 128,
+                    _ => return Ok(None),
+                };
+                let mut x = known!(self.tensor(op, 0)).clone();
+                x.insert(0, bits);
+                Ok(Some(x))
+            }
+            Concat(c) => {
+                let axis = c.axis as usize;
+                let mut result = known!(self.tensor(op, 0)).clone();
+                if axis >= result.len() {
+                    return fail(format!(
+                        "axis {} out of bounds for shape {:?}",
+                        axis, result
+                    ));
+                }
+                result[axis] = 0;
+                for input in op.inputs.iter() {
+                    let x = known!(self.tensors.get(input));
+                    if x.len() != result.len()
+                        || x.iter()
+                            .zip(result.iter())
+                            .enumerate()
+                            .any(|(i, (a, b))| i != axis && a != b)
+                    {
+                        return fail(format!("cannot concatenate {:?} along axis {}", x, axis));
+                    }
+                    result[axis] += x[axis];
+                }
+                Ok(Some(result))
+            }
+            Slice(s) => {
+                let x = known!(self.tensor(op, 0));
+                Ok(x.iter()
+                    .enumerate()
+                    .map(|(i, len)| match s.slice.0.get(i) {
+                        Some(elem) => slice_range(*len, elem).map(|(start, end)| {
+                            let step = elem.step.unwrap_or(1) as usize;
+                            (end - start + step - 1) / step
+                        }),
+                        None => Some(*len),
+                    })
+                    .collect())
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn constant_shape(c: &Constant) -> Option<Vec<usize>> {
+    match c {
+        Constant::HostBitTensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostRing64Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostRing128Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostFloat32Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostFloat64Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostInt8Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostInt16Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostInt32Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostInt64Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostUint8Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostUint16Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostUint32Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::HostUint64Tensor(x) => Some(x.0.shape().to_vec()),
+        Constant::Bit(_)
+        | Constant::Float32(_)
+        | Constant::Float64(_)
+        | Constant::Ring64(_)
+        | Constant::Ring128(_)
+        | Constant::Fixed(_) => Some(vec![]),
+        _ => None,
+    }
+}
+
+fn broadcast(x: &[usize], y: &[usize]) -> Option<Vec<usize>> {
+    let len = x.len().max(y.len());
+    let dim = |s: &[usize], i: usize| -> usize {
+        if i + s.len() < len {
+            1
+        } else {
+            s[i + s.len() - len]
+        }
+    };
+    (0..len)
+        .map(|i| match (dim(x, i), dim(y, i)) {
+            (a, b) if a == b => Some(a),
+            (1, b) => Some(b),
+            (a, 1) => Some(a),
+            _ => None,
+        })
+        .collect()
+}
+
+fn dot(x: &[usize], y: &[usize]) -> Option<Vec<usize>> {
+    match (x, y) {
+        ([n], [m]) if n == m => Some(vec![]),
+        ([n, k], [m]) if k == m => Some(vec![*n]),
+        ([k], [m, n]) if k == m => Some(vec![*n]),
+        ([n, k], [m, l]) if k == m => Some(vec![*n, *l]),
+        _ => None,
+    }
+}
+
+fn remove_axis(x: &[usize], axis: usize) -> Option<Vec<usize>> {
+    if axis >= x.len() {
+        return None;
+    }
+    let mut x = x.to_vec();
+    x.remove(axis);
+    Some(x)
+}
+
+/// Returns the range of indices covered by a slice of an axis of the given length.
+fn slice_range(len: usize, elem: &SliceInfoElem) -> Option<(usize, usize)> {
+    if elem.step.unwrap_or(1) <= 0 {
+        return None;
+    }
+    let start = resolve_index(elem.start, len);
+    let end = elem.end.map(|end| resolve_index(end, len)).unwrap_or(len);
+    Some((start.min(end), end))
+}
+
+fn resolve_index(index: isize, len: usize) -> usize {
+    if index < 0 {
+        len.saturating_sub(index.unsigned_abs())
+    } else {
+        (index as usize).min(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_infer_shapes() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Input{arg_name = "x"}: () -> Tensor<Float64> @Host(alice)
+        w = Constant{value = HostFloat64Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> Tensor<Float64> @Host(alice)
+        dot = Dot: (Tensor<Float64>, Tensor<Float64>) -> Tensor<Float64> (x, w) @Host(alice)
+        shape = Shape: (Tensor<Float64>) -> Shape<Host> (dot) @Host(alice)
+        ones = Ones: (Shape<Host>) -> Tensor<Float64> (shape) @Host(alice)
+        add = Add: (Tensor<Float64>, Tensor<Float64>) -> Tensor<Float64> (dot, ones) @Host(alice)
+        sum = Sum{axis = 0}: (Tensor<Float64>) -> Tensor<Float64> (add) @Host(alice)
+        z = Output{tag = "z"}: (Tensor<Float64>) -> Tensor<Float64> (sum) @Host(alice)"#;
+        let comp: Computation = source.try_into()?;
+
+        let input_shapes = InputShapes::from([("x".to_string(), vec![5, 2])]);
+        let table = infer_shapes(&comp, &input_shapes)?;
+        assert_eq!(table.tensors["dot"], vec![5, 2]);
+        assert_eq!(table.values["shape"], vec![5, 2]);
+        assert_eq!(table.tensors["ones"], vec![5, 2]);
+        assert_eq!(table.tensors["z"], vec![2]);
+
+        // Without input shapes only the constant is known
+        let table = infer_shapes(&comp, &InputShapes::new())?;
+        assert_eq!(table.tensors["w"], vec![2, 2]);
+        assert!(!table.tensors.contains_key("dot"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shape_mismatch() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Input{arg_name = "x"}: () -> Tensor<Float64> @Host(alice)
+        w = Constant{value = HostFloat64Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> Tensor<Float64> @Host(alice)
+        dot = Dot: (Tensor<Float64>, Tensor<Float64>) -> Tensor<Float64> (x, w) @Host(alice)
+        z = Output{tag = "z"}: (Tensor<Float64>) -> Tensor<Float64> (dot) @Host(alice)"#;
+        let comp: Computation = source.try_into()?;
+
+        let input_shapes = InputShapes::from([("x".to_string(), vec![5, 3])]);
+        let err = infer_shapes(&comp, &input_shapes).unwrap_err();
+        assert!(err.to_string().contains("'dot' on @Host(alice)"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_upmost_index() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Input{arg_name = "x"}: () -> Tensor<Fixed128(24, 40)> @Replicated(alice, bob, carole)
+        argmax = Argmax{axis = 1}: (Tensor<Fixed128(24, 40)>) -> Tensor<Unknown> (x) @Replicated(alice, bob, carole)
+        softmax = Softmax{axis = 1, upmost_index = 2}: (Tensor<Fixed128(24, 40)>) -> Tensor<Fixed128(24, 40)> (x) @Replicated(alice, bob, carole)
+        argmax0 = Argmax{}: (Tensor<Fixed128(24, 40)>) -> Tensor<Unknown> (x) @Replicated(alice, bob, carole)"#;
+        let comp: Computation = source.try_into()?;
+
+        let input_shapes = InputShapes::from([("x".to_string(), vec![10, 3])]);
+        let comp = shape_inference(comp, &input_shapes)?;
+        match &comp.operations[1].kind {
+            Operator::Argmax(op) => assert_eq!(op.upmost_index, Some(3)),
+            _ => panic!("expected an argmax operation"),
+        }
+        match &comp.operations[2].kind {
+            Operator::Softmax(op) => assert_eq!(op.upmost_index, Some(2)),
+            _ => panic!("expected a softmax operation"),
+        }
+        match &comp.operations[3].kind {
+            Operator::Argmax(op) => assert_eq!(op.upmost_index, Some(10)),
+            _ => panic!("expected an argmax operation"),
+        }
+        Ok(())
+    }
+}
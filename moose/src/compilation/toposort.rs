@@ -1,5 +1,6 @@
 use crate::computation::Computation;
 use crate::error::Error;
+use std::collections::HashSet;
 
 pub fn toposort(mut comp: Computation) -> anyhow::Result<Computation> {
     let graph = comp.as_graph();
@@ -37,3 +38,15 @@ pub fn toposort(mut comp: Computation) -> anyhow::Result<Computation> {
     }
     Ok(comp)
 }
+
+/// Returns true if every operation appears after all of its inputs.
+pub(crate) fn is_toposorted(comp: &Computation) -> bool {
+    let mut seen: HashSet<&String> = HashSet::with_capacity(comp.operations.len());
+    for op in comp.operations.iter() {
+        if !op.inputs.iter().all(|input| seen.contains(input)) {
+            return false;
+        }
+        seen.insert(&op.name);
+    }
+    true
+}
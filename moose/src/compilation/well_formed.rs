@@ -1,123 +1,387 @@
-use crate::computation::{Computation, Operator};
+use crate::computation::{Computation, Constant, Operation, Operator, Placement, Role, Ty};
 use crate::execution::SymbolicSession;
 use crate::kernels::DispatchKernel;
+use crate::logical::{TensorDType, TensorShape};
+use crate::storage::filesystem::csv::CsvOptions;
+use crate::storage::filesystem::numpy::NpzOptions;
+use crate::storage::query::LoadQuery;
+use crate::textual::ToTextual;
 use crate::Error;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
-/// Perform basic well-formed check of computation without modification.
+/// Perform well-formed check of computation without modification.
+///
+/// This checks that all inputs are defined and appear in topological order, that the
+/// signature of every operation agrees with the return types of its inputs, that only roles
+/// used by placements are communicated with, that constant load queries are valid, and that a
+/// kernel exists for every operation.
 ///
 /// Note that this check is not completely sound wrt to runtime errors:
 /// - some unsupported operator instantiations are currently only checked at runtime
 /// - some potential ndarray errors cannot currently be caught statically
 /// - computations may only be partially specified, for instance around shapes
 pub fn well_formed(comp: Computation) -> anyhow::Result<Computation> {
+    let defined: HashMap<&String, &Operation> =
+        comp.operations.iter().map(|op| (&op.name, op)).collect();
+    let roles: HashSet<&Role> = comp
+        .operations
+        .iter()
+        .flat_map(|op| placement_roles(&op.placement))
+        .collect();
     let mut seen_values: HashSet<&String> = HashSet::with_capacity(comp.operations.len());
 
     for op in &comp.operations {
-        // Make sure computation is in topological order
         for input_op_name in &op.inputs {
+            // Make sure all inputs refer to an operation
+            if !defined.contains_key(input_op_name) {
+                return Err(ill_formed(
+                    op,
+                    format!("input '{}' is not defined", input_op_name),
+                ));
+            }
+            // Make sure computation is in topological order
             if !seen_values.contains(input_op_name) {
                 return Err(crate::Error::MalformedEnvironment(input_op_name.to_string()).into());
             }
         }
         seen_values.insert(&op.name);
 
-        // Make sure computation only contains valid operator instantiations
-        // by attempting to compile (symbolic) kernels for them
-        use Operator::*;
-        let plc = &op.placement;
-        let compile_error: Option<Error> = match &op.kind {
-            // TODO(Morten) use DispatchKernel::compile for these as well
-            Load(_) | Save(_) | Send(_) | Receive(_) => None,
-
-            Abs(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Shape(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Broadcast(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            PrfKeyGen(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Xor(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            And(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Or(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            BitExtract(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Shl(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            ShlDim(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Shr(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sample(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            SampleSeeded(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            RingFixedpointArgmax(op) => {
-                DispatchKernel::<SymbolicSession, _>::compile(op, plc).err()
-            }
-            RingFixedpointMean(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            RingFixedpointEncode(op) => {
-                DispatchKernel::<SymbolicSession, _>::compile(op, plc).err()
-            }
-            RingFixedpointDecode(op) => {
-                DispatchKernel::<SymbolicSession, _>::compile(op, plc).err()
-            }
-            RingInject(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Fill(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Share(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Reveal(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            TruncPr(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Msb(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            RepToAdt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            BitDecompose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            BitCompose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            AdtToRep(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            DeriveSeed(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Constant(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Input(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Output(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            AtLeast2D(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            FixedpointEncode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            FixedpointDecode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sign(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Transpose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Squeeze(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Identity(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Cast(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Reshape(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Slice(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Ones(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            ExpandDims(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Concat(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Dot(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Inverse(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Add(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sub(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Mul(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Mean(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sum(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Div(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            AddN(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Exp(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Pow2(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Neg(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Log(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Log2(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Equal(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            EqualZero(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Mux(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Less(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Greater(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            IndexAxis(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Index(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Select(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sigmoid(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Maximum(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Softmax(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Argmax(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Demirror(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Mirror(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Decrypt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Sqrt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Diag(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Zeros(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-            Relu(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
-        };
-        if let Some(e) = compile_error {
-            return Err(e.into());
+        check_signature(op, &defined)?;
+        check_roles(op, &roles)?;
+        check_load_query(op, &defined)?;
+
+        if let Some(e) = compile_error(op) {
+            return Err(ill_formed(
+                op,
+                format!(
+                    "no kernel for signature {}: {}",
+                    op.kind.sig().to_textual(),
+                    e
+                ),
+            ));
         }
     }
 
     Ok(comp)
 }
+
+fn ill_formed(op: &Operation, reason: String) -> anyhow::Error {
+    Error::Compilation(format!(
+        "Operation '{}' on {}: {}",
+        op.name,
+        op.placement.to_textual(),
+        reason
+    ))
+    .into()
+}
+
+/// Check that the signature of an operation agrees with the return types of its inputs.
+fn check_signature(op: &Operation, defined: &HashMap<&String, &Operation>) -> anyhow::Result<()> {
+    let sig = op.kind.sig();
+    if let Some(arity) = sig.arity() {
+        if arity != op.inputs.len() {
+            return Err(ill_formed(
+                op,
+                format!(
+                    "expected {} input(s) according to signature {}, found {}",
+                    arity,
+                    sig.to_textual(),
+                    op.inputs.len()
+                ),
+            ));
+        }
+    }
+
+    for (i, input_op_name) in op.inputs.iter().enumerate() {
+        let expected = sig.arg(i)?;
+        let found = defined[input_op_name].kind.sig().ret();
+        if !is_compatible(expected, found) {
+            return Err(ill_formed(
+                op,
+                format!(
+                    "expected argument {} ('{}') of type {}, found {}",
+                    i,
+                    input_op_name,
+                    expected.to_textual(),
+                    found.to_textual()
+                ),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Types are compatible if they are equal or if either is (partially) unknown.
+fn is_compatible(expected: Ty, found: Ty) -> bool {
+    match (expected, found) {
+        (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+        (Ty::Tensor(TensorDType::Unknown), Ty::Tensor(_))
+        | (Ty::Tensor(_), Ty::Tensor(TensorDType::Unknown)) => true,
+        (Ty::Shape(TensorShape::Unknown), Ty::Shape(_))
+        | (Ty::Shape(_), Ty::Shape(TensorShape::Unknown)) => true,
+        (expected, found) => expected == found,
+    }
+}
+
+/// Check that networking operations only refer to roles used by placements.
+fn check_roles(op: &Operation, roles: &HashSet<&Role>) -> anyhow::Result<()> {
+    let role = match &op.kind {
+        Operator::Send(send_op) => &send_op.receiver,
+        Operator::Receive(receive_op) => &receive_op.sender,
+        _ => return Ok(()),
+    };
+    if !roles.contains(role) {
+        return Err(ill_formed(op, format!("unknown role '{}'", role)));
+    }
+    Ok(())
+}
+
+/// Check that the query of a load operation is valid when it is given as a constant.
+///
+/// If the key is also a constant then the options specific to the format of the file, as
+/// given by its extension, are checked as well.
+fn check_load_query(op: &Operation, defined: &HashMap<&String, &Operation>) -> anyhow::Result<()> {
+    if !matches!(op.kind, Operator::Load(_)) {
+        return Ok(());
+    }
+    let constant_string = |input: Option<&String>| match input.map(|name| &defined[name].kind) {
+        Some(Operator::Constant(constant_op)) => match &constant_op.value {
+            Constant::String(s) => Some(s.as_str()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let query = match constant_string(op.inputs.get(1)) {
+        Some(query) => query,
+        None => return Ok(()),
+    };
+    if let Err(e) = LoadQuery::parse(query) {
+        return Err(ill_formed(op, format!("invalid query: {}", e)));
+    }
+    let extension = constant_string(op.inputs.first())
+        .and_then(|key| Path::new(key).extension())
+        .and_then(|extension| extension.to_str());
+    let options = match extension {
+        Some("csv") => CsvOptions::from_query(query).map(|_| ()),
+        Some("npz") => NpzOptions::from_query(query).map(|_| ()),
+        _ => Ok(()),
+    };
+    options.map_err(|e| ill_formed(op, format!("invalid query: {}", e)))
+}
+
+fn placement_roles(plc: &Placement) -> &[Role] {
+    match plc {
+        Placement::Host(plc) => std::slice::from_ref(&plc.owner),
+        Placement::Replicated(plc) => &plc.owners,
+        Placement::Additive(plc) => &plc.owners,
+        Placement::Mirrored3(plc) => &plc.owners,
+    }
+}
+
+/// Make sure computation only contains valid operator instantiations
+/// by attempting to compile (symbolic) kernels for them.
+fn compile_error(op: &Operation) -> Option<Error> {
+    use Operator::*;
+    let plc = &op.placement;
+    match &op.kind {
+        // TODO(Morten) use DispatchKernel::compile for these as well
+        Load(_) | Save(_) | Send(_) | Receive(_) => None,
+
+        Abs(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Shape(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Broadcast(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        PrfKeyGen(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Xor(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        And(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Or(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        BitExtract(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Shl(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        ShlDim(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Shr(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sample(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        SampleSeeded(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RingFixedpointArgmax(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RingFixedpointMean(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RingFixedpointEncode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RingFixedpointDecode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RingInject(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Fill(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Share(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Reveal(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        TruncPr(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Msb(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        RepToAdt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        BitDecompose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        BitCompose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        AdtToRep(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        DeriveSeed(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Constant(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Input(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Output(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        AtLeast2D(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        FixedpointEncode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        FixedpointDecode(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sign(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Transpose(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Squeeze(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Identity(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        StorageKey(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Cast(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Reshape(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Slice(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Ones(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        ExpandDims(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Concat(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Dot(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Inverse(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Add(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sub(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Mul(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Mean(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sum(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Div(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        AddN(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Exp(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Pow2(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Neg(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Log(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Log2(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Equal(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        EqualZero(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Mux(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Less(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Greater(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        IndexAxis(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Index(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Select(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sigmoid(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Maximum(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Softmax(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Argmax(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Demirror(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Mirror(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Decrypt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Sqrt(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Diag(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Zeros(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+        Relu(op) => DispatchKernel::<SymbolicSession, _>::compile(op, plc).err(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    fn check(source: &str) -> anyhow::Result<Computation> {
+        well_formed(source.try_into()?)
+    }
+
+    #[test]
+    fn test_well_formed() -> std::result::Result<(), anyhow::Error> {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        y = Input{arg_name = "y"}: () -> HostFloat32Tensor @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, y) @Host(alice)
+        z = Output{tag = "z"}: (HostFloat32Tensor) -> HostFloat32Tensor (add) @Host(alice)"#;
+        check(source)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dangling_input() {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x, y) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Compilation error: Operation 'add' on @Host(alice): input 'y' is not defined"
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch() {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        neg = Neg: (HostFloat64Tensor) -> HostFloat64Tensor (x) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Compilation error: Operation 'neg' on @Host(alice): expected argument 0 ('x') of type HostFloat64Tensor, found HostFloat32Tensor"
+        );
+    }
+
+    #[test]
+    fn test_arity_mismatch() {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        add = Add: (HostFloat32Tensor, HostFloat32Tensor) -> HostFloat32Tensor (x) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert!(err.contains("expected 2 input(s)"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_role() {
+        let source = r#"
+        x = Constant{value=HostFloat32Tensor([[1.0, 2.0], [3.0, 4.0]])}: () -> HostFloat32Tensor @Host(alice)
+        send = Send{rendezvous_key = 30313233343536373839616263646566, receiver = "carole"}: (HostFloat32Tensor) -> HostUnit (x) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Compilation error: Operation 'send' on @Host(alice): unknown role 'carole'"
+        );
+    }
+
+    #[test]
+    fn test_invalid_load_query() {
+        let source = r#"
+        key = Constant{value=HostString("data.csv")}: () -> HostString @Host(alice)
+        query = Constant{value=HostString("{\"rows\": {\"start\": 10, \"end\": 5}}")}: () -> HostString @Host(alice)
+        x = Load: (HostString, HostString) -> HostFloat64Tensor (key, query) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Compilation error: Operation 'x' on @Host(alice): invalid query:"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_invalid_format_options() {
+        let source = |key: &str| {
+            format!(
+                r#"
+        key = Constant{{value=HostString("{}")}}: () -> HostString @Host(alice)
+        query = Constant{{value=HostString("{{\"missing\": \"ignore\"}}")}}: () -> HostString @Host(alice)
+        x = Load: (HostString, HostString) -> HostFloat64Tensor (key, query) @Host(alice)"#,
+                key
+            )
+        };
+        let err = check(&source("data.csv")).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Compilation error: Operation 'x' on @Host(alice): invalid query:"),
+            "{}",
+            err
+        );
+        // the options are only checked for the format of the file
+        assert!(check(&source("data.npy")).is_ok());
+    }
+
+    #[test]
+    fn test_missing_kernel() {
+        let source = r#"
+        x = Constant{value=HostString("foo")}: () -> HostString @Host(alice)
+        add = Add: (HostString, HostString) -> HostString (x, x) @Host(alice)"#;
+        let err = check(source).unwrap_err().to_string();
+        assert!(
+            err.starts_with(
+                "Compilation error: Operation 'add' on @Host(alice): no kernel for signature (HostString, HostString) -> HostString"
+            ),
+            "{}",
+            err
+        );
+    }
+}
@@ -872,6 +872,7 @@ operators![
     Slice,
     Sqrt,
     Squeeze,
+    StorageKey,
     Sub,
     Sum,
     Transpose,
@@ -977,6 +978,14 @@ pub struct SaveOp {
     pub sig: Signature,
 }
 
+#[derive(
+    Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, ToTextual, FromTextual,
+)]
+pub struct StorageKeyOp {
+    pub sig: Signature,
+    pub suffix: String,
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug, ShortName, ToTextual, FromTextual)]
 pub struct ConstantOp {
     pub sig: Signature,
@@ -1350,16 +1359,13 @@ pub struct RingFixedpointDecodeOp {
     pub scaling_exp: u32,
 }
 
-#[derive(
-    Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, ToTextual, FromTextual,
-)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, FromTextual)]
 pub struct RingFixedpointArgmaxOp {
     pub sig: Signature,
-    // axis can be optional (in which case we need to do an argmax over axis 0)
-    // TODO(Dragos) once we have shape inference we can make axis optional
-    // since we can automatically get the upmost index arg
-    pub axis: usize,
-    pub upmost_index: usize,
+    // argmax is taken over axis 0 if no axis is given
+    pub axis: Option<usize>,
+    // filled in by shape inference if not given
+    pub upmost_index: Option<usize>,
 }
 
 #[derive(
@@ -1503,26 +1509,22 @@ pub struct MaximumOp {
     pub sig: Signature,
 }
 
-#[derive(
-    Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, ToTextual, FromTextual,
-)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, FromTextual)]
 pub struct SoftmaxOp {
     pub sig: Signature,
     // axis can be optional (in which case we need to do a softmax over every entry)
     pub axis: usize,
-    pub upmost_index: usize,
+    // filled in by shape inference if not given
+    pub upmost_index: Option<usize>,
 }
 
-#[derive(
-    Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, ToTextual, FromTextual,
-)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Debug, ShortName, FromTextual)]
 pub struct ArgmaxOp {
     pub sig: Signature,
-    // axis can be optional (in which case we need to do an argmax over axis 0)
-    // TODO(Dragos) once we have shape inference we can make axis optional
-    // since we can automatically get the upmost index arg
-    pub axis: usize,
-    pub upmost_index: usize,
+    // argmax is taken over axis 0 if no axis is given
+    pub axis: Option<usize>,
+    // filled in by shape inference if not given
+    pub upmost_index: Option<usize>,
 }
 
 #[derive(
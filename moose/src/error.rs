@@ -54,8 +54,37 @@ pub enum Error {
     #[error("Session {0} already exists for this executor")]
     SessionAlreadyExists(String),
 
+    #[error("Failed to launch session: {0}")]
+    LaunchFailed(String),
+
+    #[error("Session {0} was aborted")]
+    SessionAborted(String),
+
+    #[error("Timed out receiving '{rendezvous_key}' from {sender}")]
+    Timeout {
+        rendezvous_key: String,
+        sender: String,
+    },
+
+    #[error("Operation '{operation}' on {placement} failed: {error}")]
+    OperationFailed {
+        operation: String,
+        placement: String,
+        error: Box<Error>,
+    },
+
     #[error("Failed to serialize computation: {0}")]
     SerializationError(String),
 }
 
+impl Error {
+    /// The error without the operations it was reported through.
+    pub fn into_root_cause(self) -> Error {
+        match self {
+            Error::OperationFailed { error, .. } => error.into_root_cause(),
+            e => e,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
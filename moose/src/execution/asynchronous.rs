@@ -3,20 +3,26 @@
 use super::{RoleAssignment, RuntimeSession, Session, SetupGeneration};
 use crate::computation::*;
 use crate::error::{Error, Result};
+use crate::execution::profiling::{OperationProfile, Profiler};
 use crate::execution::{Identity, Operands};
 use crate::host::{HostPrfKey, HostString};
 use crate::kernels::{DispatchKernel, Kernel};
-use crate::networking::{local::LocalAsyncNetworking, AsyncNetworking};
+use crate::networking::{codec::fill_in_owner, local::LocalAsyncNetworking, AsyncNetworking};
 use crate::replicated::{RepSetup, ReplicatedPlacement};
 use crate::storage::{local::LocalAsyncStorage, AsyncStorage};
+use crate::textual::ToTextual;
 use futures::future::{Map, Shared};
 use futures::stream::FuturesUnordered;
-use futures::StreamExt;
+use futures::{Future, StreamExt};
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::runtime::Runtime;
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, Notify};
+use tokio::time::Instant;
 
 pub(crate) type AsyncTask = tokio::task::JoinHandle<Result<()>>;
 
@@ -24,18 +30,133 @@ pub type AsyncNetworkingImpl = Arc<dyn AsyncNetworking + Send + Sync>;
 
 pub type AsyncStorageImpl = Arc<dyn AsyncStorage + Send + Sync>;
 
+/// Limits on how long a session waits for values from other parties.
+///
+/// Receiving fails with `Error::Timeout` once either limit is exceeded, so that a
+/// missing party makes the session fail instead of hanging forever.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTimeouts {
+    /// Maximum time to wait for a value while the session makes no progress, i.e. since
+    /// the session started, a task of the session finished, or a value was received.
+    pub receive: Option<Duration>,
+    /// Maximum time since the start of the session after which no value is waited for.
+    pub session: Option<Duration>,
+}
+
 pub struct AsyncSessionHandle {
+    session_id: SessionId,
     tasks: FuturesUnordered<AsyncTask>,
+    abort_signal: Arc<Notify>,
+    progress: AsyncSessionProgress,
+    profiler: Option<Profiler>,
+}
+
+/// Progress of a session, as observed by `AsyncSessionHandle::join_on_first_error`.
+#[derive(Clone, Debug)]
+pub struct AsyncSessionProgress {
+    finished_tasks: Arc<AtomicUsize>,
+    total_tasks: usize,
+    last_progress: Arc<parking_lot::Mutex<Instant>>,
+}
+
+impl Default for AsyncSessionProgress {
+    fn default() -> Self {
+        AsyncSessionProgress {
+            finished_tasks: Default::default(),
+            total_tasks: 0,
+            last_progress: Arc::new(parking_lot::Mutex::new(Instant::now())),
+        }
+    }
+}
+
+impl AsyncSessionProgress {
+    /// Number of tasks (roughly one per operation) that have finished successfully.
+    pub fn finished_tasks(&self) -> usize {
+        self.finished_tasks.load(Ordering::Relaxed)
+    }
+
+    /// Time at which the session started, a task finished, or a value was received, whichever
+    /// was last.
+    pub fn last_progress(&self) -> Instant {
+        *self.last_progress.lock()
+    }
+
+    fn record_progress(&self) {
+        *self.last_progress.lock() = Instant::now();
+    }
+
+    /// Number of tasks spawned by the session.
+    pub fn total_tasks(&self) -> usize {
+        self.total_tasks
+    }
+}
+
+/// Handle for aborting a session from outside of the task joining on it.
+///
+/// Obtained via `AsyncSessionHandle::abort_handle`; aborting causes a pending
+/// (or future) call to `AsyncSessionHandle::join_on_first_error` to cancel
+/// all remaining tasks of the session and return `Error::SessionAborted`.
+#[derive(Clone)]
+pub struct AsyncSessionAbortHandle {
+    abort_signal: Arc<Notify>,
+}
+
+impl AsyncSessionAbortHandle {
+    pub fn abort(&self) {
+        // `notify_one` stores a permit if no one is currently waiting,
+        // so aborting before joining is not lost
+        self.abort_signal.notify_one();
+    }
 }
 
 impl AsyncSessionHandle {
-    pub async fn join_on_first_error(mut self) -> anyhow::Result<()> {
+    pub fn abort_handle(&self) -> AsyncSessionAbortHandle {
+        AsyncSessionAbortHandle {
+            abort_signal: Arc::clone(&self.abort_signal),
+        }
+    }
+
+    pub fn progress(&self) -> AsyncSessionProgress {
+        self.progress.clone()
+    }
+
+    /// Profiler of the session, if profiling was enabled.
+    ///
+    /// The profiler remains usable after joining on the handle.
+    pub fn profiler(&self) -> Option<Profiler> {
+        self.profiler.clone()
+    }
+
+    pub async fn join_on_first_error(self) -> anyhow::Result<()> {
         use crate::error::Error::{OperandUnavailable, ResultUnused};
 
+        let AsyncSessionHandle {
+            session_id,
+            mut tasks,
+            abort_signal,
+            progress,
+            profiler: _,
+        } = self;
+
         let mut maybe_error = None;
-        while let Some(x) = self.tasks.next().await {
+        loop {
+            let x = tokio::select! {
+                x = tasks.next() => match x {
+                    Some(x) => x,
+                    None => break,
+                },
+                _ = abort_signal.notified() => {
+                    tracing::info!("Aborting session {}", session_id);
+                    maybe_error = Some(Err(anyhow::Error::from(Error::SessionAborted(
+                        session_id.to_string(),
+                    ))));
+                    break;
+                }
+            };
             match x {
                 Ok(Ok(_)) => {
+                    progress.finished_tasks.fetch_add(1, Ordering::Relaxed);
+                    progress.record_progress();
                     continue;
                 }
                 Ok(Err(e)) => {
@@ -63,7 +184,7 @@ impl AsyncSessionHandle {
         }
 
         if let Some(e) = maybe_error {
-            for task in self.tasks.iter_mut() {
+            for task in tasks.iter_mut() {
                 task.abort();
             }
             e
@@ -82,6 +203,72 @@ pub struct AsyncSession {
     pub networking: AsyncNetworkingImpl,
     pub storage: AsyncStorageImpl,
     pub tasks: Arc<Mutex<Option<FuturesUnordered<AsyncTask>>>>,
+    operation: Option<Arc<OperationInfo>>,
+    profiler: Option<Profiler>,
+    receive_timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    progress: AsyncSessionProgress,
+}
+
+/// Operation on behalf of which tasks are spawned, used to annotate errors and profiles.
+#[derive(Debug)]
+struct OperationInfo {
+    name: String,
+    kind: String,
+    placement: String,
+}
+
+/// Measures the time spent by a task in its different phases.
+///
+/// Only operations executed via `AsyncSession::execute_operation` are recorded, and only
+/// when profiling is enabled; otherwise this is a no-op.
+struct TaskTimer {
+    target: Option<(Profiler, Arc<OperationInfo>)>,
+    start: std::time::Instant,
+    operands_ready: std::time::Instant,
+}
+
+impl TaskTimer {
+    fn operands_ready(&mut self) {
+        self.operands_ready = std::time::Instant::now();
+    }
+
+    fn record_bytes_sent(&self, rendezvous_key: &RendezvousKey, value: &Value) {
+        if let Some((profiler, _)) = &self.target {
+            let bytes = bincode::serialized_size(value).unwrap_or_default();
+            profiler.record_bytes_sent(rendezvous_key, bytes);
+        }
+    }
+
+    /// Record the time since the operands were ready as compute time.
+    fn finish(self) {
+        self.record(false)
+    }
+
+    /// Record the time since the operands were ready as network time.
+    fn finish_network(self) {
+        self.record(true)
+    }
+
+    fn record(self, network: bool) {
+        if let Some((profiler, operation)) = self.target {
+            let busy_time = self.operands_ready.elapsed();
+            let (compute_time, network_time) = if network {
+                (Duration::ZERO, busy_time)
+            } else {
+                (busy_time, Duration::ZERO)
+            };
+            profiler.record_operation(OperationProfile {
+                name: operation.name.clone(),
+                kind: operation.kind.clone(),
+                placement: operation.placement.clone(),
+                start: profiler.since_epoch(self.start),
+                wait_time: self.operands_ready.duration_since(self.start),
+                compute_time,
+                network_time,
+            });
+        }
+    }
 }
 
 impl AsyncSession {
@@ -99,9 +286,132 @@ impl AsyncSession {
             networking,
             storage,
             tasks: Arc::new(Mutex::new(Some(Default::default()))),
+            operation: None,
+            profiler: None,
+            receive_timeout: None,
+            deadline: None,
+            progress: AsyncSessionProgress::default(),
         }
     }
 
+    /// Enable profiling of the operations executed in this session.
+    ///
+    /// The profile can be obtained via `AsyncSessionHandle::profiler` once the session has run.
+    pub fn with_profiler(self, profiler: Profiler) -> Self {
+        AsyncSession {
+            profiler: Some(profiler),
+            ..self
+        }
+    }
+
+    /// Bound the time spent waiting for values from other parties.
+    ///
+    /// The session deadline is counted from when this is called, and the receive timeout
+    /// from the last progress of the session. Timeouts too large to be represented as a
+    /// point in time are treated as no timeout.
+    pub fn with_timeouts(self, timeouts: SessionTimeouts) -> Self {
+        AsyncSession {
+            receive_timeout: timeouts.receive,
+            deadline: timeouts
+                .session
+                .and_then(|session| Instant::now().checked_add(session)),
+            ..self
+        }
+    }
+
+    /// Point in time after which a receive should fail unless the session makes progress.
+    fn receive_deadline(
+        receive_timeout: Option<Duration>,
+        deadline: Option<Instant>,
+        last_progress: Instant,
+    ) -> Option<Instant> {
+        let timeout = receive_timeout.and_then(|timeout| last_progress.checked_add(timeout));
+        match (timeout, deadline) {
+            (Some(timeout), Some(deadline)) => Some(timeout.min(deadline)),
+            (timeout, deadline) => timeout.or(deadline),
+        }
+    }
+
+    /// Wait for a value, returning `None` if the receive deadline passes first.
+    ///
+    /// The deadline is moved whenever the session makes progress while waiting.
+    async fn receive_within<F>(
+        receive: F,
+        receive_timeout: Option<Duration>,
+        deadline: Option<Instant>,
+        progress: &AsyncSessionProgress,
+    ) -> Option<Result<Value>>
+    where
+        F: Future<Output = Result<Value>>,
+    {
+        tokio::pin!(receive);
+        loop {
+            match Self::receive_deadline(receive_timeout, deadline, progress.last_progress()) {
+                None => return Some(receive.await),
+                Some(deadline) if deadline <= Instant::now() => return None,
+                Some(deadline) => {
+                    if let Ok(value) = tokio::time::timeout_at(deadline, &mut receive).await {
+                        return Some(value);
+                    }
+                }
+            }
+        }
+    }
+
+    fn start_timer(&self) -> TaskTimer {
+        let now = std::time::Instant::now();
+        TaskTimer {
+            target: self.profiler.clone().zip(self.operation.clone()),
+            start: now,
+            operands_ready: now,
+        }
+    }
+
+    /// Execute an operation, annotating errors with its name and placement.
+    ///
+    /// Same as `Session::execute` except that errors produced by the spawned tasks are
+    /// wrapped in `Error::OperationFailed`, so that the root cause reported by
+    /// `AsyncSessionHandle::join_on_first_error` points to the failing operation.
+    pub fn execute_operation(
+        &self,
+        operation_name: &str,
+        op: &Operator,
+        plc: &Placement,
+        operands: Operands<AsyncValue>,
+    ) -> Result<AsyncValue> {
+        let sess = AsyncSession {
+            operation: Some(Arc::new(OperationInfo {
+                name: operation_name.to_string(),
+                kind: op.short_name().to_string(),
+                placement: plc.to_textual(),
+            })),
+            ..self.clone()
+        };
+        sess.execute(op, plc, operands)
+    }
+
+    /// Spawns a task on behalf of the current operation and adds it to the session.
+    fn spawn_task<F>(&self, task: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + Send + 'static,
+    {
+        let operation = self.operation.clone();
+        let task = tokio::spawn(async move {
+            task.await.map_err(|e| match (e, operation) {
+                // not root causes, and must remain recognizable as such
+                (e @ Error::OperandUnavailable, _) => e,
+                (e @ Error::ResultUnused, _) => e,
+                (e, Some(operation)) => Error::OperationFailed {
+                    operation: operation.name.clone(),
+                    placement: operation.placement.clone(),
+                    error: Box::new(e),
+                },
+                (e, None) => e,
+            })
+        });
+        Self::add_task(&self.tasks, task)
+    }
+
     /// Adds a task into the specified collection of tasks.
     ///
     /// The collection is usually a `&sess.tasks`. This is an associated function instead of a method due to
@@ -143,7 +453,17 @@ impl AsyncSession {
                 "Session has been already converted into a handle".to_string(),
             )
         })?;
-        Ok(AsyncSessionHandle { tasks })
+        let progress = AsyncSessionProgress {
+            total_tasks: tasks.len(),
+            ..self.progress.clone()
+        };
+        Ok(AsyncSessionHandle {
+            session_id: self.session_id.clone(),
+            tasks,
+            abort_signal: Arc::new(Notify::new()),
+            progress,
+            profiler: self.profiler.clone(),
+        })
     }
 }
 
@@ -161,8 +481,9 @@ impl AsyncSession {
             let session_id = self.session_id.clone();
             let expected_ty = op.sig.ret();
 
+            let mut timer = self.start_timer();
             let (sender, receiver) = new_channel();
-            let task = tokio::spawn(async move {
+            self.spawn_task(async move {
                 let mut operands = operands;
 
                 let query: HostString = operands
@@ -178,10 +499,12 @@ impl AsyncSession {
                     .await
                     .map_err(map_receive_error)?
                     .try_into()?;
+                timer.operands_ready();
 
                 let value: Value = storage
                     .load(&key.0, &session_id, Some(expected_ty), &query.0)
                     .await?;
+                timer.finish();
 
                 if value.ty() != expected_ty {
                     return Err(Error::TypeMismatch {
@@ -194,8 +517,7 @@ impl AsyncSession {
 
                 map_send_result(sender.send(value))?;
                 Ok(())
-            });
-            Self::add_task(&self.tasks, task)?;
+            })?;
             Ok(receiver)
         } else {
             Err(Error::UnimplementedOperator(
@@ -212,8 +534,9 @@ impl AsyncSession {
             let storage = Arc::clone(&self.storage);
             let unit = Value::from(HostUnit(plc.clone()));
 
+            let mut timer = self.start_timer();
             let (sender, receiver) = new_channel();
-            let task = tokio::spawn(async move {
+            self.spawn_task(async move {
                 let mut operands = operands;
 
                 let x: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
@@ -224,13 +547,14 @@ impl AsyncSession {
                     .await
                     .map_err(map_receive_error)?
                     .try_into()?;
+                timer.operands_ready();
 
                 storage.save(&key.0, &session_id, &x).await?;
+                timer.finish();
 
                 map_send_result(sender.send(unit))?;
                 Ok(())
-            });
-            Self::add_task(&self.tasks, task)?;
+            })?;
             Ok(receiver)
         } else {
             Err(Error::UnimplementedOperator(
@@ -247,18 +571,29 @@ impl AsyncSession {
     ) -> Result<AsyncValue> {
         assert_eq!(operands.len(), 0);
 
-        if let Placement::Host(_) = plc {
+        if let Placement::Host(plc) = plc {
+            let plc = plc.clone();
             let networking_sender = self.find_role_assignment(&op.sender)?.clone();
             let session_id = self.session_id.clone();
             let rendezvous_key = op.rendezvous_key.clone();
             let networking = Arc::clone(&self.networking);
             let expected_ty = op.sig.ret();
+            let receive_timeout = self.receive_timeout;
+            let deadline = self.deadline;
+            let progress = self.progress.clone();
 
+            let timer = self.start_timer();
             let (sender, receiver) = new_channel();
-            let task = tokio::spawn(async move {
-                let value = networking
-                    .receive(&networking_sender, &rendezvous_key, &session_id)
-                    .await?;
+            self.spawn_task(async move {
+                let receive = networking.receive(&networking_sender, &rendezvous_key, &session_id);
+                let value = Self::receive_within(receive, receive_timeout, deadline, &progress)
+                    .await
+                    .ok_or_else(|| Error::Timeout {
+                        rendezvous_key: rendezvous_key.to_string(),
+                        sender: networking_sender.to_string(),
+                    })??;
+                progress.record_progress();
+                timer.finish_network();
 
                 if value.ty() != expected_ty {
                     return Err(Error::TypeMismatch {
@@ -267,12 +602,12 @@ impl AsyncSession {
                     });
                 }
 
-                // TODO(Morten) check or fill-in placement?
+                // compactly encoded values are sent without the owner of their tensors
+                let value = fill_in_owner(value, &plc);
 
                 map_send_result(sender.send(value))?;
                 Ok(())
-            });
-            Self::add_task(&self.tasks, task)?;
+            })?;
             Ok(receiver)
         } else {
             Err(Error::UnimplementedOperator(
@@ -296,20 +631,23 @@ impl AsyncSession {
             let networking = Arc::clone(&self.networking);
             let unit = Value::from(HostUnit(plc.clone()));
 
+            let mut timer = self.start_timer();
             let (sender, receiver) = new_channel();
-            let task = tokio::spawn(async move {
+            self.spawn_task(async move {
                 let mut operands = operands;
 
                 let value = operands.pop().unwrap().await.map_err(map_receive_error)?;
+                timer.operands_ready();
+                timer.record_bytes_sent(&rendezvous_key, &value);
 
                 networking
                     .send(&value, &networking_receiver, &rendezvous_key, &session_id)
                     .await?;
+                timer.finish_network();
 
                 map_send_result(sender.send(unit))?;
                 Ok(())
-            });
-            Self::add_task(&self.tasks, task)?;
+            })?;
             Ok(receiver)
         } else {
             Err(Error::UnimplementedOperator(
@@ -441,6 +779,7 @@ impl DispatchKernel<AsyncSession, Value> for Operator {
             Softmax(op) => DispatchKernel::compile(op, plc),
             Sqrt(op) => DispatchKernel::compile(op, plc),
             Squeeze(op) => DispatchKernel::compile(op, plc),
+            StorageKey(op) => DispatchKernel::compile(op, plc),
             Sub(op) => DispatchKernel::compile(op, plc),
             Sum(op) => DispatchKernel::compile(op, plc),
             Transpose(op) => DispatchKernel::compile(op, plc),
@@ -475,59 +814,69 @@ impl Session for AsyncSession {
         let (sender, receiver) = new_channel();
         let sess = self.clone();
         let plc = plc.clone();
+        let mut timer = self.start_timer();
 
         let task = match kernel {
             Kernel::Nullary { closure } => {
                 assert_eq!(operands.len(), 0);
-                tokio::spawn(async move {
+                self.spawn_task(async move {
                     let y: Value = closure(&sess, &plc)?;
+                    timer.finish();
                     map_send_result(sender.send(y))?;
                     Ok(())
                 })
             }
             Kernel::Unary { closure } => {
                 assert_eq!(operands.len(), 1);
-                tokio::spawn(async move {
+                self.spawn_task(async move {
                     let mut operands = operands;
                     let x0: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
+                    timer.operands_ready();
                     let y: Value = closure(&sess, &plc, x0)?;
+                    timer.finish();
                     map_send_result(sender.send(y))?;
                     Ok(())
                 })
             }
             Kernel::Binary { closure } => {
                 assert_eq!(operands.len(), 2);
-                tokio::spawn(async move {
+                self.spawn_task(async move {
                     let mut operands = operands;
                     let x1: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
                     let x0: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
+                    timer.operands_ready();
                     let y: Value = closure(&sess, &plc, x0, x1)?;
+                    timer.finish();
                     map_send_result(sender.send(y))?;
                     Ok(())
                 })
             }
             Kernel::Ternary { closure } => {
                 assert_eq!(operands.len(), 3);
-                tokio::spawn(async move {
+                self.spawn_task(async move {
                     let mut operands = operands;
                     let x2: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
                     let x1: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
                     let x0: Value = operands.pop().unwrap().await.map_err(map_receive_error)?;
+                    timer.operands_ready();
                     let y: Value = closure(&sess, &plc, x0, x1, x2)?;
+                    timer.finish();
                     map_send_result(sender.send(y))?;
                     Ok(())
                 })
             }
-            Kernel::Variadic { closure } => tokio::spawn(async move {
+            Kernel::Variadic { closure } => self.spawn_task(async move {
                 let operands = futures::future::join_all(operands).await;
                 let xs: std::result::Result<Operands<Value>, _> = operands.into_iter().collect();
                 let xs = xs.map_err(map_receive_error)?;
+                timer.operands_ready();
                 let y: Value = closure(&sess, &plc, xs)?;
+                timer.finish();
                 map_send_result(sender.send(y))?;
                 Ok(())
             }),
         };
-        Self::add_task(&self.tasks, task)?;
+        task?;
         Ok(receiver)
     }
 }
@@ -638,6 +987,9 @@ pub struct AsyncTestRuntime {
     pub executors: HashMap<Identity, AsyncExecutor>,
     pub runtime_storage: HashMap<Identity, AsyncStorageImpl>,
     pub networking: AsyncNetworkingImpl,
+    pub timeouts: SessionTimeouts,
+    #[cfg(test)]
+    paused_time: bool,
 }
 
 impl AsyncTestRuntime {
@@ -667,18 +1019,52 @@ impl AsyncTestRuntime {
             executors,
             runtime_storage,
             networking,
+            timeouts: SessionTimeouts::default(),
+            #[cfg(test)]
+            paused_time: false,
         }
     }
 
-    pub fn evaluate_computation(
+    /// Use the given networking instead of local networking, e.g. to inject faults.
+    pub fn with_networking(self, networking: AsyncNetworkingImpl) -> Self {
+        AsyncTestRuntime { networking, ..self }
+    }
+
+    /// Bound the time sessions wait for values, e.g. to test parties that never send.
+    pub fn with_timeouts(self, timeouts: SessionTimeouts) -> Self {
+        AsyncTestRuntime { timeouts, ..self }
+    }
+
+    /// Evaluate computations on a single thread with time paused, so that timeouts and
+    /// delays elapse as soon as all tasks are waiting rather than by the wall clock.
+    #[cfg(test)]
+    pub(crate) fn with_paused_time(self) -> Self {
+        AsyncTestRuntime {
+            paused_time: true,
+            ..self
+        }
+    }
+
+    fn new_runtime(&self) -> Runtime {
+        #[cfg(test)]
+        if self.paused_time {
+            return tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .start_paused(true)
+                .build()
+                .unwrap();
+        }
+        Runtime::new().unwrap()
+    }
+
+    /// Starts a session for every identity; must be called within a Tokio runtime.
+    fn start_sessions(
         &mut self,
         computation: &Computation,
         arguments: HashMap<String, Value>,
-    ) -> Result<HashMap<String, Value>> {
+    ) -> Result<(Vec<AsyncSessionHandle>, HashMap<String, AsyncValue>)> {
         let mut session_handles: Vec<AsyncSessionHandle> = Vec::new();
         let mut output_futures: HashMap<String, AsyncValue> = HashMap::new();
-        let rt = Runtime::new().unwrap();
-        let _guard = rt.enter();
 
         // since executors are virtual, just enforce roles = identities
         let role_assignments: HashMap<Role, Identity> = self
@@ -696,7 +1082,8 @@ impl AsyncTestRuntime {
                 role_assignments.clone(),
                 Arc::clone(&self.networking),
                 Arc::clone(&self.runtime_storage[own_identity]),
-            );
+            )
+            .with_timeouts(self.timeouts);
             let outputs = executor
                 .run_computation(computation, &role_assignments, own_identity, &moose_session)
                 .unwrap();
@@ -707,6 +1094,17 @@ impl AsyncTestRuntime {
 
             session_handles.push(moose_session.into_handle()?)
         }
+        Ok((session_handles, output_futures))
+    }
+
+    pub fn evaluate_computation(
+        &mut self,
+        computation: &Computation,
+        arguments: HashMap<String, Value>,
+    ) -> Result<HashMap<String, Value>> {
+        let rt = self.new_runtime();
+        let _guard = rt.enter();
+        let (session_handles, output_futures) = self.start_sessions(computation, arguments)?;
 
         let mut futures: FuturesUnordered<_> = session_handles
             .into_iter()
@@ -731,6 +1129,64 @@ impl AsyncTestRuntime {
         Ok(outputs)
     }
 
+    /// Evaluates a computation that is expected to fail, returning the first error raised.
+    ///
+    /// The root cause of the error is returned, as if without the `Error::OperationFailed`
+    /// added when executing operations through `AsyncSession::execute_operation`.
+    ///
+    /// Fails with `Error::TestRuntime` if evaluation succeeds, or if no party has failed
+    /// within `timeout` so that tests report hanging sessions instead of blocking.
+    pub fn evaluate_computation_to_error(
+        &mut self,
+        computation: &Computation,
+        arguments: HashMap<String, Value>,
+        timeout: Duration,
+    ) -> Result<Error> {
+        let rt = self.new_runtime();
+        let _guard = rt.enter();
+        let (session_handles, _output_futures) = self.start_sessions(computation, arguments)?;
+
+        let mut futures: FuturesUnordered<_> = session_handles
+            .into_iter()
+            .map(|h| h.join_on_first_error())
+            .collect();
+        let first_error = async {
+            while let Some(result) = futures.next().await {
+                if let Err(e) = result {
+                    return Some(e);
+                }
+            }
+            None
+        };
+        match rt.block_on(tokio::time::timeout(timeout, first_error)) {
+            Ok(Some(e)) => e
+                .downcast::<Error>()
+                .map(Error::into_root_cause)
+                .map_err(|e| Error::TestRuntime(format!("unexpected error: {}", e))),
+            Ok(None) => Err(Error::TestRuntime(
+                "evaluation succeeded but was expected to fail".to_string(),
+            )),
+            Err(_) => Err(Error::TestRuntime(format!(
+                "session did not terminate within {:?}",
+                timeout
+            ))),
+        }
+    }
+
+    /// Asserts that evaluating a computation fails with a networking error within `timeout`.
+    pub fn assert_networking_error(
+        &mut self,
+        computation: &Computation,
+        arguments: HashMap<String, Value>,
+        timeout: Duration,
+    ) {
+        match self.evaluate_computation_to_error(computation, arguments, timeout) {
+            Ok(Error::Networking(_)) => (),
+            Ok(e) => panic!("expected a networking error but got: {}", e),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
     pub fn read_value_from_storage(&self, identity: Identity, key: String) -> Result<Value> {
         let rt = Runtime::new().unwrap();
         let _guard = rt.enter();
@@ -2,7 +2,7 @@
 
 use crate::computation::IndexedComputation;
 use crate::computation::Operator;
-use crate::execution::{AsyncNetworkingImpl, AsyncStorageImpl};
+use crate::execution::{AsyncNetworkingImpl, AsyncStorageImpl, Profiler, SessionTimeouts};
 use crate::prelude::*;
 use crate::Error;
 use std::collections::HashMap;
@@ -13,6 +13,8 @@ pub struct ExecutionContext {
     own_identity: Identity,
     networking: AsyncNetworkingImpl,
     storage: AsyncStorageImpl,
+    profiling: bool,
+    timeouts: SessionTimeouts,
 }
 
 #[allow(dead_code)]
@@ -31,24 +33,53 @@ impl ExecutionContext {
             own_identity,
             networking,
             storage,
+            profiling: false,
+            timeouts: SessionTimeouts::default(),
         }
     }
 
-    #[tracing::instrument(skip(self, computation, role_assignments))]
-    pub async fn execute_computation(
+    /// Enable or disable profiling of the sessions executed by this context.
+    ///
+    /// When enabled, profiles are available via `AsyncSessionHandle::profiler`.
+    pub fn with_profiling(self, profiling: bool) -> ExecutionContext {
+        ExecutionContext { profiling, ..self }
+    }
+
+    /// Bound the time sessions executed by this context wait for values from other parties.
+    pub fn with_timeouts(self, timeouts: SessionTimeouts) -> ExecutionContext {
+        ExecutionContext { timeouts, ..self }
+    }
+
+    fn new_session(
         &self,
         session_id: SessionId,
-        computation: &Computation,
         arguments: HashMap<String, Value>,
         role_assignments: HashMap<Role, Identity>,
-    ) -> Result<(AsyncSessionHandle, IndexedOutputEnvironment), Box<dyn std::error::Error>> {
+    ) -> AsyncSession {
         let session = AsyncSession::new(
             session_id,
             arguments,
-            role_assignments.clone(),
+            role_assignments,
             Arc::clone(&self.networking),
             Arc::clone(&self.storage),
-        );
+        )
+        .with_timeouts(self.timeouts);
+        if self.profiling {
+            session.with_profiler(Profiler::new())
+        } else {
+            session
+        }
+    }
+
+    #[tracing::instrument(skip(self, computation, role_assignments))]
+    pub async fn execute_computation(
+        &self,
+        session_id: SessionId,
+        computation: &Computation,
+        arguments: HashMap<String, Value>,
+        role_assignments: HashMap<Role, Identity>,
+    ) -> Result<(AsyncSessionHandle, IndexedOutputEnvironment), Box<dyn std::error::Error>> {
+        let session = self.new_session(session_id, arguments, role_assignments.clone());
 
         let mut outputs: IndexedOutputEnvironment = Vec::default();
 
@@ -79,7 +110,8 @@ impl ExecutionContext {
                     .map(|input_name| env.get(input_name).unwrap().clone())
                     .collect();
 
-                let result = session.execute(&op.kind, &op.placement, operands)?;
+                let result =
+                    session.execute_operation(&op.name, &op.kind, &op.placement, operands)?;
 
                 if matches!(op.kind, Operator::Output(_)) {
                     // If it is an output, we need to make sure we capture it for returning.
@@ -103,14 +135,11 @@ impl ExecutionContext {
         arguments: HashMap<String, Value>,
         role_assignments: HashMap<Role, Identity>,
     ) -> Result<(AsyncSessionHandle, IndexedOutputEnvironment), Box<dyn std::error::Error>> {
-        let session = AsyncSession::new(
-            session_id,
-            arguments,
-            role_assignments.clone(),
-            Arc::clone(&self.networking),
-            Arc::clone(&self.storage),
-        );
+        let session = self.new_session(session_id, arguments, role_assignments.clone());
 
+        // operations are indexed in the same order as in the named computation
+        let operation_names: Vec<&String> =
+            computation.operations.iter().map(|op| &op.name).collect();
         let computation = IndexedComputation::try_from(computation)?;
         let mut outputs: IndexedOutputEnvironment = Vec::default();
         {
@@ -156,7 +185,12 @@ impl ExecutionContext {
                 })?;
                 let is_output = matches!(operator, Operator::Output(_));
 
-                let result = session.execute(operator, placement, operands)?;
+                let result = session.execute_operation(
+                    operation_names[op_index],
+                    operator,
+                    placement,
+                    operands,
+                )?;
 
                 if is_output {
                     // If it is an output, we need to make sure we capture it for returning.
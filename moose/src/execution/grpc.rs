@@ -1,22 +1,28 @@
 use crate::choreography::grpc::gen::choreography_client::ChoreographyClient;
 use crate::choreography::grpc::gen::{
-    AbortComputationRequest, LaunchComputationRequest, RetrieveResultsRequest,
+    AbortComputationRequest, GetSessionStatusRequest, LaunchComputationRequest,
+    ListSessionsRequest, RetrieveResultsRequest,
 };
-use crate::choreography::grpc::ComputationOutputs;
+use crate::choreography::grpc::{ComputationOutputs, SessionStatus};
+use crate::execution::{Profile, SessionTimeouts};
 use crate::prelude::{Computation, Identity, Role, SessionId, Value};
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::time::Duration;
 use tonic::transport::{Channel, ClientTlsConfig, Uri};
 
 pub struct GrpcMooseRuntime {
     role_assignments: HashMap<Role, Identity>,
     channels: HashMap<Role, Channel>,
+    profiling: bool,
+    timeouts: SessionTimeouts,
 }
 
 #[derive(Debug)]
 pub struct GrpcOutputs {
     pub outputs: HashMap<String, Value>,
     pub elapsed_time: Option<HashMap<Role, Duration>>,
+    pub profiles: Option<HashMap<Role, Profile>>,
 }
 
 impl GrpcMooseRuntime {
@@ -40,9 +46,21 @@ impl GrpcMooseRuntime {
         Ok(GrpcMooseRuntime {
             role_assignments,
             channels,
+            profiling: false,
+            timeouts: SessionTimeouts::default(),
         })
     }
 
+    /// Request execution profiles from the workers for computations launched hereafter.
+    pub fn with_profiling(self, profiling: bool) -> GrpcMooseRuntime {
+        GrpcMooseRuntime { profiling, ..self }
+    }
+
+    /// Bound the time workers wait for values in computations launched hereafter.
+    pub fn with_timeouts(self, timeouts: SessionTimeouts) -> GrpcMooseRuntime {
+        GrpcMooseRuntime { timeouts, ..self }
+    }
+
     pub async fn run_computation(
         &self,
         session_id: &SessionId,
@@ -64,6 +82,7 @@ impl GrpcMooseRuntime {
         let computation = bincode::serialize(computation)?;
         let arguments = bincode::serialize(&arguments)?;
         let role_assignment = bincode::serialize(&self.role_assignments)?;
+        let timeouts = bincode::serialize(&self.timeouts)?;
 
         for channel in self.channels.values() {
             let mut client = ChoreographyClient::new(channel.clone());
@@ -75,6 +94,8 @@ impl GrpcMooseRuntime {
                 computation: computation.clone(),
                 arguments: arguments.clone(),
                 role_assignment: role_assignment.clone(),
+                profile: self.profiling,
+                timeouts: timeouts.clone(),
             };
 
             let _response = client.launch_computation(request).await?;
@@ -89,17 +110,30 @@ impl GrpcMooseRuntime {
     ) -> Result<(), Box<dyn std::error::Error>> {
         let session_id = bincode::serialize(&session_id)?;
 
-        for channel in self.channels.values() {
+        // NOTE we attempt to abort on all parties before reporting any errors,
+        // to avoid leaving some of them running because another was unreachable
+        let mut failed_roles = Vec::new();
+        for (role, channel) in self.channels.iter() {
             let mut client = ChoreographyClient::new(channel.clone());
 
             let request = AbortComputationRequest {
                 session_id: session_id.clone(),
             };
 
-            let _response = client.abort_computation(request).await?;
+            if let Err(e) = client.abort_computation(request).await {
+                tracing::error!("Failed to abort computation on {}: {}", role, e);
+                failed_roles.push(role.to_string());
+            }
         }
 
-        Ok(())
+        if failed_roles.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(crate::Error::Networking(format!(
+                "failed to abort computation on {}",
+                failed_roles.join(", ")
+            ))))
+        }
     }
 
     pub async fn retrieve_results(
@@ -110,6 +144,7 @@ impl GrpcMooseRuntime {
 
         let mut combined_outputs = HashMap::new();
         let mut combined_stats = HashMap::new();
+        let mut combined_profiles = HashMap::new();
 
         for (role, channel) in self.channels.iter() {
             let mut client = ChoreographyClient::new(channel.clone());
@@ -123,24 +158,82 @@ impl GrpcMooseRuntime {
             let ComputationOutputs {
                 outputs,
                 elapsed_time,
+                profile,
             } = bincode::deserialize::<ComputationOutputs>(&response.get_ref().values)?;
-            combined_outputs.extend(outputs);
+            // raise the root cause reported by the party
+            combined_outputs.extend(outputs?);
 
             if let Some(time) = elapsed_time {
                 combined_stats.insert(role.clone(), time);
             }
+
+            if let Some(profile) = profile {
+                combined_profiles.insert(role.clone(), profile);
+            }
         }
 
+        let profiles = if combined_profiles.is_empty() {
+            None
+        } else {
+            Some(combined_profiles)
+        };
+
         if combined_stats.is_empty() {
             Ok(GrpcOutputs {
                 outputs: combined_outputs,
                 elapsed_time: None,
+                profiles,
             })
         } else {
             Ok(GrpcOutputs {
                 outputs: combined_outputs,
                 elapsed_time: Some(combined_stats),
+                profiles,
             })
         }
     }
+
+    pub async fn session_status(
+        &self,
+        session_id: &SessionId,
+    ) -> Result<HashMap<Role, SessionStatus>, Box<dyn std::error::Error>> {
+        let session_id = bincode::serialize(&session_id)?;
+
+        let mut statuses = HashMap::with_capacity(self.channels.len());
+        for (role, channel) in self.channels.iter() {
+            let mut client = ChoreographyClient::new(channel.clone());
+
+            let request = GetSessionStatusRequest {
+                session_id: session_id.clone(),
+            };
+
+            let response = client.get_session_status(request).await?;
+            let status = response.into_inner().status.ok_or_else(|| {
+                crate::Error::Unexpected(Some("missing session status".to_string()))
+            })?;
+            statuses.insert(role.clone(), SessionStatus::try_from(status)?);
+        }
+
+        Ok(statuses)
+    }
+
+    pub async fn list_sessions(
+        &self,
+    ) -> Result<HashMap<Role, Vec<SessionStatus>>, Box<dyn std::error::Error>> {
+        let mut sessions = HashMap::with_capacity(self.channels.len());
+        for (role, channel) in self.channels.iter() {
+            let mut client = ChoreographyClient::new(channel.clone());
+
+            let response = client.list_sessions(ListSessionsRequest {}).await?;
+            let role_sessions = response
+                .into_inner()
+                .sessions
+                .into_iter()
+                .map(SessionStatus::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            sessions.insert(role.clone(), role_sessions);
+        }
+
+        Ok(sessions)
+    }
 }
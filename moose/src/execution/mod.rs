@@ -12,6 +12,7 @@ pub mod asynchronous;
 pub mod context;
 pub mod grpc;
 pub(crate) mod kernel_helpers;
+pub mod profiling;
 #[cfg(feature = "compile")]
 pub mod symbolic;
 #[cfg(feature = "sync_execute")]
@@ -20,6 +21,7 @@ pub mod synchronous;
 #[cfg(feature = "async_execute")]
 pub use asynchronous::*;
 pub use context::ExecutionContext;
+pub use profiling::{Profile, Profiler};
 #[cfg(feature = "compile")]
 pub use symbolic::*;
 #[cfg(feature = "sync_execute")]
@@ -91,6 +93,7 @@ mod tests {
     use crate::error::Error;
     use crate::execution::{SyncSession, TestSyncExecutor};
     use crate::host::{HostPlacement, HostSeed, HostTensor, RawSeed, RawShape};
+    use crate::networking::faulty::{Fault, FaultPlan, FaultyNetworking};
     use crate::networking::{local::LocalAsyncNetworking, AsyncNetworking};
     use crate::prelude::*;
     use crate::storage::{
@@ -102,6 +105,7 @@ mod tests {
     use rstest::rstest;
     use std::convert::{TryFrom, TryInto};
     use std::rc::Rc;
+    use std::time::Duration;
     use tokio::runtime::Runtime;
 
     fn _run_computation_test(
@@ -1035,4 +1039,355 @@ mod tests {
             panic!("expected session already exists error")
         }
     }
+
+    #[cfg(feature = "async_execute")]
+    #[test]
+    fn test_abort_session() {
+        // bob never sends so the session would hang without the abort
+        let source = r#"x = Receive {rendezvous_key=30303030303030303030303030303031, sender="bob"} : () -> HostFloat32Tensor () @Host(alice)
+        output = Output{tag = "output_0"}: (HostFloat32Tensor) -> HostFloat32Tensor (x) @Host(alice)"#;
+
+        let networking: Arc<dyn Send + Sync + AsyncNetworking> =
+            Arc::new(LocalAsyncNetworking::default());
+
+        let exec_storage: Arc<dyn Send + Sync + AsyncStorage> =
+            Arc::new(LocalAsyncStorage::default());
+
+        let identity = Identity::from("alice");
+        let role_assignments: HashMap<Role, Identity> = hashmap!(
+            Role::from("alice") => identity.clone(),
+            Role::from("bob") => Identity::from("bob"),
+        );
+
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+
+        let moose_session =
+            _create_async_session(&networking, &exec_storage, role_assignments.clone());
+
+        let computation: Computation = source.try_into().unwrap();
+        let mut executor = AsyncExecutor::default();
+        let outputs = executor
+            .run_computation(&computation, &role_assignments, &identity, &moose_session)
+            .unwrap();
+
+        let handle = moose_session.into_handle().unwrap();
+        handle.abort_handle().abort();
+        let res = rt.block_on(handle.join_on_first_error());
+
+        let expected = Error::SessionAborted(format!("{}", SessionId::try_from("foobar").unwrap()));
+        match res {
+            Err(e) => assert_eq!(e.to_string(), expected.to_string()),
+            Ok(_) => panic!("expected session aborted error"),
+        }
+
+        // the aborted output is never produced
+        let output = outputs.get("output_0").unwrap().clone();
+        assert!(rt.block_on(output).is_err());
+    }
+
+    #[cfg(feature = "async_execute")]
+    #[test]
+    fn test_session_progress() {
+        let source = r#"key = Constant{value=HostPrfKey(00000000000000000000000000000000)}: () -> HostPrfKey @Host(alice)
+        seed = DeriveSeed {sync_key = [1, 2, 3]}: (HostPrfKey) -> HostSeed (key) @Host(alice)
+        output = Output{tag = "output_0"}: (HostSeed) -> HostSeed (seed) @Host(alice)"#;
+
+        let networking: Arc<dyn Send + Sync + AsyncNetworking> =
+            Arc::new(LocalAsyncNetworking::default());
+
+        let exec_storage: Arc<dyn Send + Sync + AsyncStorage> =
+            Arc::new(LocalAsyncStorage::default());
+
+        let identity = Identity::from("alice");
+        let role_assignments: HashMap<Role, Identity> =
+            hashmap!(Role::from("alice") => identity.clone());
+
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+
+        let moose_session =
+            _create_async_session(&networking, &exec_storage, role_assignments.clone());
+
+        let computation: Computation = source.try_into().unwrap();
+        let mut executor = AsyncExecutor::default();
+        let _outputs = executor
+            .run_computation(&computation, &role_assignments, &identity, &moose_session)
+            .unwrap();
+
+        let handle = moose_session.into_handle().unwrap();
+        let progress = handle.progress();
+        assert_eq!(progress.total_tasks(), 3);
+
+        rt.block_on(handle.join_on_first_error()).unwrap();
+        assert_eq!(progress.finished_tasks(), 3);
+    }
+
+    #[cfg(feature = "async_execute")]
+    #[test]
+    fn test_failing_operation_is_root_cause() {
+        let source = r#"key = Constant{value = HostString("missing")}: () -> HostString () @Host(alice)
+        query = Constant{value = HostString("")}: () -> HostString () @Host(alice)
+        x = Load: (HostString, HostString) -> HostFloat64Tensor (key, query) @Host(alice)
+        output = Output{tag = "output_0"}: (HostFloat64Tensor) -> HostFloat64Tensor (x) @Host(alice)"#;
+
+        let networking: Arc<dyn Send + Sync + AsyncNetworking> =
+            Arc::new(LocalAsyncNetworking::default());
+
+        let exec_storage: Arc<dyn Send + Sync + AsyncStorage> =
+            Arc::new(LocalAsyncStorage::default());
+
+        let identity = Identity::from("alice");
+        let role_assignments: HashMap<Role, Identity> =
+            hashmap!(Role::from("alice") => identity.clone());
+
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+
+        let computation: Computation = source.try_into().unwrap();
+        let context = ExecutionContext::new(identity, networking, exec_storage);
+        let (handle, _outputs) = rt
+            .block_on(context.execute_computation(
+                SessionId::try_from("foobar").unwrap(),
+                &computation,
+                hashmap!(),
+                role_assignments,
+            ))
+            .unwrap();
+
+        let res = rt.block_on(handle.join_on_first_error());
+        match res.map_err(|e| e.downcast::<Error>()) {
+            Err(Ok(Error::OperationFailed {
+                operation,
+                placement,
+                error,
+            })) => {
+                assert_eq!(operation, "x");
+                assert_eq!(placement, "@Host(alice)");
+                assert!(matches!(*error, Error::Storage(_)));
+            }
+            other => panic!("expected operation failed error, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_root_cause() {
+        let error = Error::OperationFailed {
+            operation: "x".to_string(),
+            placement: "@Host(alice)".to_string(),
+            error: Box::new(Error::Networking("failed".to_string())),
+        };
+        assert!(matches!(error.into_root_cause(), Error::Networking(_)));
+        let error = Error::Timeout {
+            rendezvous_key: "key".to_string(),
+            sender: "alice".to_string(),
+        };
+        assert!(matches!(error.into_root_cause(), Error::Timeout { .. }));
+    }
+
+    #[cfg(feature = "async_execute")]
+    #[test]
+    fn test_profiling() {
+        let source = r#"x = Constant{value = HostFloat64Tensor([1.0, 2.0])}: () -> HostFloat64Tensor () @Host(alice)
+        y = Add: (HostFloat64Tensor, HostFloat64Tensor) -> HostFloat64Tensor (x, x) @Host(alice)
+        output = Output{tag = "output_0"}: (HostFloat64Tensor) -> HostFloat64Tensor (y) @Host(alice)"#;
+
+        let networking: Arc<dyn Send + Sync + AsyncNetworking> =
+            Arc::new(LocalAsyncNetworking::default());
+
+        let exec_storage: Arc<dyn Send + Sync + AsyncStorage> =
+            Arc::new(LocalAsyncStorage::default());
+
+        let identity = Identity::from("alice");
+        let role_assignments: HashMap<Role, Identity> =
+            hashmap!(Role::from("alice") => identity.clone());
+
+        let rt = Runtime::new().unwrap();
+        let _guard = rt.enter();
+
+        let computation: Computation = source.try_into().unwrap();
+        let context =
+            ExecutionContext::new(identity, networking, exec_storage).with_profiling(true);
+        let (handle, outputs) = rt
+            .block_on(context.execute_computation(
+                SessionId::try_from("foobar").unwrap(),
+                &computation,
+                hashmap!(),
+                role_assignments,
+            ))
+            .unwrap();
+
+        let profiler = handle.profiler().unwrap();
+        rt.block_on(handle.join_on_first_error()).unwrap();
+        for (_, output) in outputs {
+            rt.block_on(output).unwrap();
+        }
+
+        let profile = profiler.profile();
+        let mut names: Vec<&str> = profile
+            .operations
+            .iter()
+            .map(|op| op.name.as_str())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["output", "x", "y"]);
+        let add = profile.operations.iter().find(|op| op.name == "y").unwrap();
+        assert_eq!(add.kind, "Add");
+        assert_eq!(add.placement, "@Host(alice)");
+        assert!(profile.bytes_sent.is_empty());
+    }
+
+    const FAULT_SOURCE: &str = r#"x = Constant{value = HostFloat64Tensor([1.0, 2.0])}: () -> HostFloat64Tensor () @Host(alice)
+    send_x = Send{rendezvous_key = 30313233343536373839616263646566, receiver = "bob"}: (HostFloat64Tensor) -> HostUnit (x) @Host(alice)
+    recv_x = Receive{rendezvous_key = 30313233343536373839616263646566, sender = "alice"}: () -> HostFloat64Tensor () @Host(bob)
+    output = Output{tag = "output_0"}: (HostFloat64Tensor) -> HostFloat64Tensor (recv_x) @Host(bob)"#;
+
+    fn faulty_runtime(plan: FaultPlan) -> AsyncTestRuntime {
+        let storage_mapping: HashMap<String, HashMap<String, Value>> =
+            hashmap!("alice".to_string() => hashmap!(), "bob".to_string() => hashmap!());
+        let networking: AsyncNetworkingImpl =
+            Arc::new(FaultyNetworking::new(LocalAsyncNetworking::default(), plan));
+        AsyncTestRuntime::new(storage_mapping).with_networking(networking)
+    }
+
+    #[test]
+    fn test_injected_send_failure() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let plan = FaultPlan::new().on_receiver(Identity::from("bob"), Fault::Fail);
+        faulty_runtime(plan).assert_networking_error(
+            &computation,
+            hashmap!(),
+            Duration::from_secs(10),
+        );
+    }
+
+    #[test]
+    fn test_injected_corruption() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let plan = FaultPlan::new().on_receiver(Identity::from("bob"), Fault::Corrupt);
+        let error = faulty_runtime(plan)
+            .evaluate_computation_to_error(&computation, hashmap!(), Duration::from_secs(10))
+            .unwrap();
+        assert!(matches!(error, Error::TypeMismatch { .. }));
+    }
+
+    #[test]
+    fn test_injected_delay_and_duplicate() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let rendezvous_key = RendezvousKey::from_bytes(*b"0123456789abcdef");
+        for fault in [Fault::Delay(Duration::from_millis(10)), Fault::Duplicate] {
+            let plan = FaultPlan::new().on_key(rendezvous_key.clone(), fault);
+            let outputs = faulty_runtime(plan)
+                .evaluate_computation(&computation, hashmap!())
+                .unwrap();
+            let r: HostFloat64Tensor = outputs["output_0"].clone().try_into().unwrap();
+            assert_eq!(r.0, array![1.0, 2.0].into_shared().into_dyn());
+        }
+    }
+
+    #[test]
+    fn test_receive_timeout() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let plan = FaultPlan::new().on_receiver(Identity::from("bob"), Fault::Drop);
+        let timeouts = SessionTimeouts {
+            receive: Some(Duration::from_millis(100)),
+            session: None,
+        };
+        let error = faulty_runtime(plan)
+            .with_timeouts(timeouts)
+            .with_paused_time()
+            .evaluate_computation_to_error(&computation, hashmap!(), Duration::from_secs(10))
+            .unwrap();
+        match error {
+            Error::Timeout {
+                rendezvous_key,
+                sender,
+            } => {
+                assert_eq!(rendezvous_key, "30313233343536373839616263646566");
+                assert_eq!(sender, "alice");
+            }
+            e => panic!("expected a timeout but got: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_withheld_value_times_out() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let plan = FaultPlan::new().on_receiver(Identity::from("bob"), Fault::Withhold);
+        let timeouts = SessionTimeouts {
+            receive: Some(Duration::from_millis(100)),
+            session: None,
+        };
+        let error = faulty_runtime(plan)
+            .with_timeouts(timeouts)
+            .with_paused_time()
+            .evaluate_computation_to_error(&computation, hashmap!(), Duration::from_secs(10))
+            .unwrap();
+        assert!(matches!(error, Error::Timeout { .. }));
+    }
+
+    #[test]
+    fn test_unbounded_timeouts() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let timeouts = SessionTimeouts {
+            receive: Some(Duration::MAX),
+            session: Some(Duration::MAX),
+        };
+        let outputs = faulty_runtime(FaultPlan::new())
+            .with_timeouts(timeouts)
+            .evaluate_computation(&computation, hashmap!())
+            .unwrap();
+        let r: HostFloat64Tensor = outputs["output_0"].clone().try_into().unwrap();
+        assert_eq!(r.0, array![1.0, 2.0].into_shared().into_dyn());
+    }
+
+    #[test]
+    fn test_receive_timeout_counts_from_last_progress() {
+        let source = r#"x = Constant{value = HostFloat64Tensor([1.0, 2.0])}: () -> HostFloat64Tensor () @Host(alice)
+        send_x = Send{rendezvous_key = 30313233343536373839616263646566, receiver = "bob"}: (HostFloat64Tensor) -> HostUnit (x) @Host(alice)
+        send_y = Send{rendezvous_key = 66656463626139383736353433323130, receiver = "bob"}: (HostFloat64Tensor) -> HostUnit (x) @Host(alice)
+        recv_x = Receive{rendezvous_key = 30313233343536373839616263646566, sender = "alice"}: () -> HostFloat64Tensor () @Host(bob)
+        recv_y = Receive{rendezvous_key = 66656463626139383736353433323130, sender = "alice"}: () -> HostFloat64Tensor () @Host(bob)
+        z = Add: (HostFloat64Tensor, HostFloat64Tensor) -> HostFloat64Tensor (recv_x, recv_y) @Host(bob)
+        output = Output{tag = "output_0"}: (HostFloat64Tensor) -> HostFloat64Tensor (z) @Host(bob)"#;
+        let computation: Computation = source.try_into().unwrap();
+        // the second value arrives later than the receive timeout after the session started,
+        // but within it after the first value arrived
+        let plan = FaultPlan::new()
+            .on_key(
+                RendezvousKey::from_bytes(*b"0123456789abcdef"),
+                Fault::Delay(Duration::from_millis(300)),
+            )
+            .on_key(
+                RendezvousKey::from_bytes(*b"fedcba9876543210"),
+                Fault::Delay(Duration::from_millis(600)),
+            );
+        let timeouts = SessionTimeouts {
+            receive: Some(Duration::from_millis(500)),
+            session: None,
+        };
+        let outputs = faulty_runtime(plan)
+            .with_timeouts(timeouts)
+            .with_paused_time()
+            .evaluate_computation(&computation, hashmap!())
+            .unwrap();
+        let r: HostFloat64Tensor = outputs["output_0"].clone().try_into().unwrap();
+        assert_eq!(r.0, array![2.0, 4.0].into_shared().into_dyn());
+    }
+
+    #[test]
+    fn test_session_deadline() {
+        let computation: Computation = FAULT_SOURCE.try_into().unwrap();
+        let plan = FaultPlan::new().on_receiver(Identity::from("bob"), Fault::Withhold);
+        let timeouts = SessionTimeouts {
+            receive: Some(Duration::from_secs(60)),
+            session: Some(Duration::from_millis(100)),
+        };
+        let error = faulty_runtime(plan)
+            .with_timeouts(timeouts)
+            .with_paused_time()
+            .evaluate_computation_to_error(&computation, hashmap!(), Duration::from_secs(10))
+            .unwrap();
+        assert!(matches!(error, Error::Timeout { .. }));
+    }
 }
@@ -0,0 +1,243 @@
+//! Profiling of session execution.
+
+use crate::computation::RendezvousKey;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Timings of a single operation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OperationProfile {
+    pub name: String,
+    pub kind: String,
+    pub placement: String,
+    /// Time at which the operation started, relative to the start of profiling.
+    pub start: Duration,
+    /// Time spent waiting for operands.
+    pub wait_time: Duration,
+    /// Time spent computing the kernel, including storage access.
+    pub compute_time: Duration,
+    /// Time spent sending or receiving values.
+    pub network_time: Duration,
+}
+
+/// Profile of a session as seen by a single party.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub operations: Vec<OperationProfile>,
+    /// Number of serialized bytes sent per rendezvous key.
+    pub bytes_sent: HashMap<RendezvousKey, u64>,
+}
+
+/// Aggregated timings of all operations of the same kind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KindSummary {
+    pub kind: String,
+    pub count: usize,
+    pub wait_time: Duration,
+    pub compute_time: Duration,
+    pub network_time: Duration,
+}
+
+impl Profile {
+    /// Timings grouped by operator kind, most expensive first.
+    ///
+    /// Kinds are ordered by the sum of compute and network time; waiting is excluded
+    /// since it is mostly spent on other operations.
+    pub fn by_kind(&self) -> Vec<KindSummary> {
+        let mut summaries: HashMap<&str, KindSummary> = HashMap::new();
+        for op in &self.operations {
+            let summary = summaries
+                .entry(op.kind.as_str())
+                .or_insert_with(|| KindSummary {
+                    kind: op.kind.clone(),
+                    ..Default::default()
+                });
+            summary.count += 1;
+            summary.wait_time += op.wait_time;
+            summary.compute_time += op.compute_time;
+            summary.network_time += op.network_time;
+        }
+        let mut summaries: Vec<KindSummary> = summaries.into_values().collect();
+        summaries.sort_by(|a, b| {
+            (b.compute_time + b.network_time)
+                .cmp(&(a.compute_time + a.network_time))
+                .then_with(|| a.kind.cmp(&b.kind))
+        });
+        summaries
+    }
+
+    /// Total number of bytes sent.
+    pub fn total_bytes_sent(&self) -> u64 {
+        self.bytes_sent.values().sum()
+    }
+
+    /// Exports the profile in the Chrome trace event format.
+    ///
+    /// Each placement is shown as a process, and operations running concurrently on the same
+    /// placement are spread over as many threads as needed to avoid overlaps. Events cover the
+    /// compute and network time of operations, with the waiting time given as an argument.
+    pub fn to_chrome_trace(&self) -> serde_json::Value {
+        let mut operations: Vec<&OperationProfile> = self.operations.iter().collect();
+        operations.sort_by_key(|op| op.start + op.wait_time);
+
+        let mut placements: Vec<&str> = operations.iter().map(|op| op.placement.as_str()).collect();
+        placements.sort_unstable();
+        placements.dedup();
+
+        let mut events: Vec<serde_json::Value> = placements
+            .iter()
+            .enumerate()
+            .map(|(pid, placement)| {
+                serde_json::json!({
+                    "name": "process_name",
+                    "ph": "M",
+                    "pid": pid,
+                    "args": { "name": placement },
+                })
+            })
+            .collect();
+
+        // End time of the last event on each thread, per placement
+        let mut threads: Vec<Vec<Duration>> = vec![Vec::new(); placements.len()];
+        for op in operations {
+            let pid = placements.binary_search(&op.placement.as_str()).unwrap();
+            let start = op.start + op.wait_time;
+            let end = start + op.compute_time + op.network_time;
+            let tid = match threads[pid]
+                .iter()
+                .position(|busy_until| *busy_until <= start)
+            {
+                Some(tid) => tid,
+                None => {
+                    threads[pid].push(Duration::ZERO);
+                    threads[pid].len() - 1
+                }
+            };
+            threads[pid][tid] = end;
+
+            events.push(serde_json::json!({
+                "name": op.name,
+                "cat": op.kind,
+                "ph": "X",
+                "ts": start.as_micros() as u64,
+                "dur": (end - start).as_micros() as u64,
+                "pid": pid,
+                "tid": tid,
+                "args": {
+                    "wait_us": op.wait_time.as_micros() as u64,
+                    "compute_us": op.compute_time.as_micros() as u64,
+                    "network_us": op.network_time.as_micros() as u64,
+                },
+            }));
+        }
+
+        serde_json::json!({ "traceEvents": events })
+    }
+}
+
+/// Collects a profile while a session is executing.
+///
+/// Cloning gives a handle to the same profile.
+#[derive(Clone, Debug)]
+pub struct Profiler {
+    epoch: Instant,
+    profile: Arc<Mutex<Profile>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            epoch: Instant::now(),
+            profile: Default::default(),
+        }
+    }
+
+    /// Returns the profile collected so far.
+    pub fn profile(&self) -> Profile {
+        self.profile.lock().clone()
+    }
+
+    pub(crate) fn since_epoch(&self, instant: Instant) -> Duration {
+        instant.saturating_duration_since(self.epoch)
+    }
+
+    pub(crate) fn record_operation(&self, operation: OperationProfile) {
+        self.profile.lock().operations.push(operation);
+    }
+
+    pub(crate) fn record_bytes_sent(&self, rendezvous_key: &RendezvousKey, bytes: u64) {
+        *self
+            .profile
+            .lock()
+            .bytes_sent
+            .entry(rendezvous_key.clone())
+            .or_default() += bytes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(name: &str, kind: &str, start: u64, compute: u64) -> OperationProfile {
+        OperationProfile {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            placement: "@Host(alice)".to_string(),
+            start: Duration::from_micros(start),
+            wait_time: Duration::ZERO,
+            compute_time: Duration::from_micros(compute),
+            network_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_by_kind() {
+        let profile = Profile {
+            operations: vec![
+                operation("x", "Add", 0, 10),
+                operation("y", "Dot", 0, 30),
+                operation("z", "Add", 10, 5),
+            ],
+            bytes_sent: HashMap::new(),
+        };
+        let summaries = profile.by_kind();
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].kind, "Dot");
+        assert_eq!(summaries[1].kind, "Add");
+        assert_eq!(summaries[1].count, 2);
+        assert_eq!(summaries[1].compute_time, Duration::from_micros(15));
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        let profile = Profile {
+            operations: vec![
+                operation("x", "Add", 0, 10),
+                operation("y", "Dot", 5, 30),
+                operation("z", "Add", 10, 5),
+            ],
+            bytes_sent: HashMap::new(),
+        };
+        let trace = profile.to_chrome_trace();
+        let events = trace["traceEvents"].as_array().unwrap();
+        // one metadata event and one event per operation
+        assert_eq!(events.len(), 4);
+        let tids: Vec<u64> = events[1..]
+            .iter()
+            .map(|event| event["tid"].as_u64().unwrap())
+            .collect();
+        // `y` overlaps with `x`, but `z` starts after `x` has ended
+        assert_eq!(tids, vec![0, 1, 0]);
+        assert_eq!(events[2]["dur"].as_u64(), Some(30));
+    }
+}
@@ -347,6 +347,7 @@ impl DispatchKernel<SymbolicSession, SymbolicValue> for Operator {
             Softmax(op) => DispatchKernel::compile(op, plc),
             Sqrt(op) => DispatchKernel::compile(op, plc),
             Squeeze(op) => DispatchKernel::compile(op, plc),
+            StorageKey(op) => DispatchKernel::compile(op, plc),
             Sub(op) => DispatchKernel::compile(op, plc),
             Sum(op) => DispatchKernel::compile(op, plc),
             Transpose(op) => DispatchKernel::compile(op, plc),
@@ -151,8 +151,8 @@ impl DispatchKernel<SyncSession, Value> for Operator {
     fn compile(&self, plc: &Placement) -> Result<Kernel<SyncSession, Value>> {
         use Operator::*;
         match self {
-            Load(_) => unimplemented!(),
-            Save(_) => unimplemented!(),
+            Load(op) => DispatchKernel::compile(op, plc),
+            Save(op) => DispatchKernel::compile(op, plc),
             Send(op) => DispatchKernel::compile(op, plc),
             Receive(op) => DispatchKernel::compile(op, plc),
 
@@ -226,6 +226,7 @@ impl DispatchKernel<SyncSession, Value> for Operator {
             Softmax(op) => DispatchKernel::compile(op, plc),
             Sqrt(op) => DispatchKernel::compile(op, plc),
             Squeeze(op) => DispatchKernel::compile(op, plc),
+            StorageKey(op) => DispatchKernel::compile(op, plc),
             Sub(op) => DispatchKernel::compile(op, plc),
             Sum(op) => DispatchKernel::compile(op, plc),
             Transpose(op) => DispatchKernel::compile(op, plc),
@@ -237,13 +238,29 @@ impl DispatchKernel<SyncSession, Value> for Operator {
     }
 }
 
+/// Returns true for `Save` operations saving the shares of a replicated or additive value,
+/// which are compiled into saves of host values rather than handed to the storage.
+fn saves_shares(op: &SaveOp) -> bool {
+    matches!(
+        op.sig.arg(1),
+        Ok(Ty::ReplicatedBitTensor
+            | Ty::ReplicatedRing64Tensor
+            | Ty::ReplicatedRing128Tensor
+            | Ty::ReplicatedFixed64Tensor
+            | Ty::ReplicatedFixed128Tensor
+            | Ty::AdditiveBitTensor
+            | Ty::AdditiveRing64Tensor
+            | Ty::AdditiveRing128Tensor)
+    )
+}
+
 impl Session for SyncSession {
     type Value = Value;
 
     fn execute(&self, op: &Operator, plc: &Placement, operands: Operands<Value>) -> Result<Value> {
         let mut operands = operands;
         let kernel: Kernel<SyncSession, _> = match op {
-            Operator::Load(op) => {
+            Operator::Load(op) if matches!(plc, Placement::Host(_)) => {
                 assert_eq!(operands.len(), 2);
                 let query: HostString = operands.pop().unwrap().try_into()?;
                 let key: HostString = operands.pop().unwrap().try_into()?;
@@ -252,7 +269,7 @@ impl Session for SyncSession {
                     .storage
                     .load(&key.0, &self.session_id, Some(op.sig.ret()), &query.0);
             }
-            Operator::Save(_) => {
+            Operator::Save(op) if !saves_shares(op) => {
                 assert_eq!(operands.len(), 2);
                 let value: Value = operands.pop().unwrap();
                 let key: HostString = operands.pop().unwrap().try_into()?;
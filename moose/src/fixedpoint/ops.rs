@@ -2283,7 +2283,7 @@ impl SoftmaxOp {
         sess: &S,
         plc: &ReplicatedPlacement,
         axis: usize,
-        upmost_index: usize,
+        upmost_index: Option<usize>,
         x: FixedTensor<HostFixedT, MirFixedT, RepFixedT>,
     ) -> Result<FixedTensor<HostFixedT, MirFixedT, RepFixedT>>
     where
@@ -2313,8 +2313,8 @@ impl ArgmaxOp {
     >(
         sess: &S,
         plc: &ReplicatedPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: FixedTensor<HostFixedT, MirFixedT, RepFixedT>,
     ) -> Result<AbstractUint64Tensor<HostUintT, RepUintT>>
     where
@@ -2342,8 +2342,8 @@ impl ArgmaxOp {
     >(
         sess: &S,
         plc: &HostPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: FixedTensor<HostFixedT, MirFixedT, RepFixedT>,
     ) -> Result<AbstractUint64Tensor<HostUintT, RepUintT>>
     where
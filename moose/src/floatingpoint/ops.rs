@@ -110,7 +110,7 @@ impl SoftmaxOp {
         sess: &S,
         plc: &HostPlacement,
         axis: usize,
-        upmost_index: usize,
+        upmost_index: Option<usize>,
         x: FloatTensor<HostFloatT, MirroredT>,
     ) -> Result<FloatTensor<HostFloatT, MirroredT>>
     where
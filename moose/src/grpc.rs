@@ -9,22 +9,25 @@ pub(crate) fn extract_sender<T>(request: &tonic::Request<T>) -> Result<Option<St
                 ));
             }
 
-            let (_rem, cert) =
-                x509_parser::parse_x509_certificate(certs[0].as_ref()).map_err(|err| {
-                    format!("failed to parse X509 certificate: {:?}", err.to_string())
-                })?;
+            certificate_common_name(certs[0].as_ref()).map(Some)
+        }
+    }
+}
 
-            let cns: Vec<_> = cert
-                .subject()
-                .iter_common_name()
-                .map(|attr| attr.as_str().map_err(|err| err.to_string()))
-                .collect::<Result<_, _>>()?;
+/// Extract the common name of a DER-encoded X509 certificate.
+pub(crate) fn certificate_common_name(cert: &[u8]) -> Result<String, String> {
+    let (_rem, cert) = x509_parser::parse_x509_certificate(cert)
+        .map_err(|err| format!("failed to parse X509 certificate: {:?}", err.to_string()))?;
 
-            if let Some(cn) = cns.first() {
-                Ok(Some(cn.to_string()))
-            } else {
-                Err("certificate common name was empty".to_string())
-            }
-        }
+    let cns: Vec<_> = cert
+        .subject()
+        .iter_common_name()
+        .map(|attr| attr.as_str().map_err(|err| err.to_string()))
+        .collect::<Result<_, _>>()?;
+
+    if let Some(cn) = cns.first() {
+        Ok(cn.to_string())
+    } else {
+        Err("certificate common name was empty".to_string())
     }
 }
@@ -223,6 +223,32 @@ impl SaveOp {
     }
 }
 
+impl StorageKeyOp {
+    /// Appends the suffix to the key as further path components.
+    ///
+    /// Any extension of the key is moved to the end, e.g. `weights.npy` becomes
+    /// `weights/alice-bob-carole/bob.npy`, since storage may choose the format from it.
+    pub(crate) fn kernel<S: RuntimeSession>(
+        _sess: &S,
+        plc: &HostPlacement,
+        suffix: String,
+        key: HostString,
+    ) -> Result<HostString> {
+        let key = key.0;
+        let extension = std::path::Path::new(&key)
+            .extension()
+            .and_then(|extension| extension.to_str());
+        let derived = match extension {
+            Some(extension) => {
+                let stem = &key[..key.len() - extension.len() - 1];
+                format!("{}/{}.{}", stem, suffix, extension)
+            }
+            None => format!("{}/{}", key, suffix),
+        };
+        Ok(HostString(derived, plc.clone()))
+    }
+}
+
 impl AbsOp {
     pub(crate) fn host_kernel<S: RuntimeSession, T: Signed>(
         _sess: &S,
@@ -1016,7 +1042,7 @@ impl SoftmaxOp {
         _sess: &S,
         plc: &HostPlacement,
         axis: usize,
-        _upmost_index: usize,
+        _upmost_index: Option<usize>,
         x: HostTensor<T>,
     ) -> Result<HostTensor<T>>
     where
@@ -2391,11 +2417,11 @@ impl RingFixedpointArgmaxOp {
     pub(crate) fn host_ring64_kernel<S: RuntimeSession>(
         _sess: &S,
         plc: &HostPlacement,
-        axis: usize,
-        _upmost_index: usize,
+        axis: Option<usize>,
+        _upmost_index: Option<usize>,
         x: HostRing64Tensor,
     ) -> Result<HostRing64Tensor> {
-        let axis = Axis(axis);
+        let axis = Axis(axis.unwrap_or(0));
         let signed_tensor = x.0.mapv(|entry| entry.0 as i64);
 
         let mut current_max = signed_tensor.index_axis(axis, 0).to_owned();
@@ -2422,11 +2448,11 @@ impl RingFixedpointArgmaxOp {
     pub(crate) fn host_ring128_kernel<S: RuntimeSession>(
         _sess: &S,
         plc: &HostPlacement,
-        axis: usize,
-        _upmost_index: usize,
+        axis: Option<usize>,
+        _upmost_index: Option<usize>,
         x: HostRing128Tensor,
     ) -> Result<HostRing64Tensor> {
-        let axis = Axis(axis);
+        let axis = Axis(axis.unwrap_or(0));
         let signed_tensor = x.0.mapv(|entry| entry.0 as i128);
 
         let mut current_max = signed_tensor.index_axis(axis, 0).to_owned();
@@ -2455,8 +2481,8 @@ impl ArgmaxOp {
     pub(crate) fn host_fixed_uint_kernel<S: Session, HostRingT, HostRingT2>(
         sess: &S,
         plc: &HostPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: HostFixedTensor<HostRingT>,
     ) -> Result<m!(HostUint64Tensor)>
     where
@@ -607,11 +607,11 @@ modelled_kernel! {
 }
 
 pub trait PlacementArgmax<S: Session, T, O> {
-    fn argmax(&self, sess: &S, axis: usize, upmost_index: usize, x: &T) -> O;
+    fn argmax(&self, sess: &S, axis: Option<usize>, upmost_index: Option<usize>, x: &T) -> O;
 }
 
 modelled_kernel! {
-    PlacementArgmax::argmax, ArgmaxOp{axis: usize, upmost_index: usize},
+    PlacementArgmax::argmax, ArgmaxOp{axis: Option<usize>, upmost_index: Option<usize>},
     [
         (HostPlacement, (HostFixed64Tensor) -> HostUint64Tensor => [hybrid] Self::host_fixed_uint_kernel),
         (HostPlacement, (HostFixed128Tensor) -> HostUint64Tensor => [hybrid] Self::host_fixed_uint_kernel),
@@ -627,7 +627,7 @@ modelled_kernel! {
 }
 
 modelled_kernel! {
-    PlacementArgmax::argmax, RingFixedpointArgmaxOp{axis: usize, upmost_index: usize},
+    PlacementArgmax::argmax, RingFixedpointArgmaxOp{axis: Option<usize>, upmost_index: Option<usize>},
     [
         (HostPlacement, (HostRing128Tensor) -> HostRing64Tensor => [runtime] Self::host_ring128_kernel),
         (HostPlacement, (HostRing64Tensor) -> HostRing64Tensor => [runtime] Self::host_ring64_kernel),
@@ -148,6 +148,24 @@ modelled_kernel! {
         (HostPlacement, (HostString, HostString) -> Float32Tensor => [hybrid] Self::float_kernel),
         (HostPlacement, (HostString, HostString) -> Float64Tensor => [hybrid] Self::float_kernel),
         (HostPlacement, (HostString, HostString) -> Uint64Tensor => [hybrid] Self::u64_kernel),
+        (ReplicatedPlacement, (HostString, HostString) -> ReplicatedBitTensor => [hybrid] Self::rep_ring_kernel),
+        (ReplicatedPlacement, (HostString, HostString) -> ReplicatedRing64Tensor => [hybrid] Self::rep_ring_kernel),
+        (ReplicatedPlacement, (HostString, HostString) -> ReplicatedRing128Tensor => [hybrid] Self::rep_ring_kernel),
+        // TODO(jason,morten): figure out a good way to get this static type information
+        //  from the Signature (improve Ty impl in values!)
+        (ReplicatedPlacement, (HostString, HostString) -> ReplicatedFixed64Tensor => [hybrid] custom |_op| {
+            Ok(Box::new(move |sess, plc, key, query| {
+                Self::rep_fixed_kernel(sess, plc, 14, 23, key, query)
+            }))
+        }),
+        (ReplicatedPlacement, (HostString, HostString) -> ReplicatedFixed128Tensor => [hybrid] custom |_op| {
+            Ok(Box::new(move |sess, plc, key, query| {
+                Self::rep_fixed_kernel(sess, plc, 24, 40, key, query)
+            }))
+        }),
+        (AdditivePlacement, (HostString, HostString) -> AdditiveBitTensor => [hybrid] Self::adt_kernel),
+        (AdditivePlacement, (HostString, HostString) -> AdditiveRing64Tensor => [hybrid] Self::adt_kernel),
+        (AdditivePlacement, (HostString, HostString) -> AdditiveRing128Tensor => [hybrid] Self::adt_kernel),
         (HostPlacement, (HostString, HostString) -> Tensor => [hybrid] custom |op| {
             use crate::logical::{AbstractTensor, TensorDType};
             match op.sig.ret() {
@@ -204,5 +222,51 @@ modelled_kernel! {
         (HostPlacement, (HostString, Float64Tensor) -> HostUnit => [hybrid] Self::float_kernel),
         (HostPlacement, (HostString, BooleanTensor) -> HostUnit => [hybrid] Self::bool_kernel),
         (HostPlacement, (HostString, Uint64Tensor) -> HostUnit => [hybrid] Self::u64_kernel),
+        // Saving a replicated or additive value on one of its owners saves the shares held by that owner
+        (HostPlacement, (HostString, ReplicatedBitTensor) -> HostUnit => [hybrid] Self::rep_ring_kernel),
+        (HostPlacement, (HostString, ReplicatedRing64Tensor) -> HostUnit => [hybrid] Self::rep_ring_kernel),
+        (HostPlacement, (HostString, ReplicatedRing128Tensor) -> HostUnit => [hybrid] Self::rep_ring_kernel),
+        (HostPlacement, (HostString, ReplicatedFixed64Tensor) -> HostUnit => [hybrid] Self::rep_fixed_kernel),
+        (HostPlacement, (HostString, ReplicatedFixed128Tensor) -> HostUnit => [hybrid] Self::rep_fixed_kernel),
+        (HostPlacement, (HostString, AdditiveBitTensor) -> HostUnit => [hybrid] Self::adt_kernel),
+        (HostPlacement, (HostString, AdditiveRing64Tensor) -> HostUnit => [hybrid] Self::adt_kernel),
+        (HostPlacement, (HostString, AdditiveRing128Tensor) -> HostUnit => [hybrid] Self::adt_kernel),
+    ]
+}
+
+pub trait PlacementStorageKey<S: Session, KeyT, O> {
+    fn storage_key(&self, sess: &S, suffix: String, key: &KeyT) -> O;
+}
+
+modelled_kernel! {
+    PlacementStorageKey::storage_key, StorageKeyOp{suffix: String},
+    [
+        (HostPlacement, (HostString) -> HostString => [runtime] Self::kernel),
     ]
 }
+
+/// Suffix of the key under which `role` stores its shares of a value on the placement `owners`.
+///
+/// Keys derived this way are specific to both the placement and the owner, so that shares
+/// saved for one placement or by one owner are not mistaken for those of another.
+pub(crate) fn share_key_suffix(owners: &[Role], role: &Role) -> String {
+    let owners: Vec<&str> = owners.iter().map(|owner| owner.0.as_str()).collect();
+    format!("{}/{}", owners.join("-"), role.0)
+}
+
+/// Suffix of the key under which the shares of a fixed-point value with the given precision are stored.
+///
+/// Keys derived this way are specific to the precision, so that shares saved with one precision
+/// fail to load rather than being reinterpreted with another.
+pub(crate) fn fixed_key_suffix(integral_precision: u32, fractional_precision: u32) -> String {
+    format!("fixed-{}-{}", integral_precision, fractional_precision)
+}
+
+/// Owners and role encoded in a key derived with `share_key_suffix`, if any.
+pub(crate) fn parse_share_key_suffix(key: &str) -> Option<(Vec<Role>, Role)> {
+    let path = std::path::Path::new(key);
+    let role = path.file_stem()?.to_str()?;
+    let owners = path.parent()?.file_name()?.to_str()?;
+    let owners = owners.split('-').map(Role::from).collect();
+    Some((owners, Role::from(role)))
+}
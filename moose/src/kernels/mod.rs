@@ -201,11 +201,11 @@ modelled_kernel! {
 }
 
 pub trait PlacementSoftmax<S: Session, T, O> {
-    fn softmax(&self, sess: &S, axis: usize, upmost_index: usize, x: &T) -> O;
+    fn softmax(&self, sess: &S, axis: usize, upmost_index: Option<usize>, x: &T) -> O;
 }
 
 modelled_kernel! {
-    PlacementSoftmax::softmax, SoftmaxOp{axis: usize, upmost_index: usize},
+    PlacementSoftmax::softmax, SoftmaxOp{axis: usize, upmost_index: Option<usize>},
     [
         (HostPlacement, (Tensor) -> Tensor => [concrete] Self::logical_host_kernel),
         (HostPlacement, (Float32Tensor) -> Float32Tensor => [concrete] Self::float_host_kernel),
@@ -2797,6 +2797,7 @@ pub mod replicated;
 pub mod storage;
 pub mod textual;
 pub mod types;
+pub mod units;
 
 pub use error::{Error, Result};
 pub use tokio;
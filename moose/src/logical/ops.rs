@@ -3341,7 +3341,7 @@ impl SoftmaxOp {
         sess: &S,
         plc: &ReplicatedPlacement,
         axis: usize,
-        upmost_index: usize,
+        upmost_index: Option<usize>,
         x: AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>,
     ) -> Result<AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>>
     where
@@ -3376,7 +3376,7 @@ impl SoftmaxOp {
         sess: &S,
         plc: &HostPlacement,
         axis: usize,
-        upmost_index: usize,
+        upmost_index: Option<usize>,
         x: AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>,
     ) -> Result<AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>>
     where
@@ -3412,8 +3412,8 @@ impl ArgmaxOp {
     >(
         sess: &S,
         plc: &ReplicatedPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>,
     ) -> Result<AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>>
     where
@@ -3447,8 +3447,8 @@ impl ArgmaxOp {
     >(
         sess: &S,
         plc: &HostPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>,
     ) -> Result<AbstractTensor<Fixed64T, Fixed128T, Float32T, Float64T, BoolT, Uint64T>>
     where
@@ -0,0 +1,616 @@
+//! Encodings of values sent over the network.
+//!
+//! Encoded values start with a header naming the encoding and compression used, so that
+//! receivers can decode values without knowing which codec the sender was configured with.
+//! Senders only use what receivers support, as announced with a `CodecSupport` when parties
+//! connect, and fall back to plain bincode for receivers that announced nothing.
+
+use crate::computation::Value;
+use crate::error::{Error, Result};
+use crate::host::{BitArrayRepr, HostBitTensor, HostFixedTensor, HostPlacement, HostRingTensor};
+use bitvec::prelude::*;
+use ndarray::{ArrayD, Dimension, IxDyn};
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::io::Read;
+use std::num::Wrapping;
+
+const ENCODING_BINCODE: u8 = 0;
+const ENCODING_COMPACT: u8 = 1;
+
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+const COMPRESSION_LZ4: u8 = 2;
+
+/// Largest size of a value after decompression; larger values are rejected when decoding.
+pub const MAX_DECOMPRESSED_SIZE: usize = 1 << 30;
+
+/// Owner of tensors decoded from the compact encoding, which does not send owners.
+///
+/// It is replaced by the placement of the receiving operation with `fill_in_owner`.
+const UNKNOWN_OWNER: &str = "";
+
+/// Compression applied to encoded values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Zstd,
+    Lz4,
+}
+
+/// Encodings and compressions that a party is able to decode.
+///
+/// The default only covers plain bincode without compression, which is what is assumed of
+/// parties that did not announce their support.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodecSupport {
+    pub compact: bool,
+    pub zstd: bool,
+    pub lz4: bool,
+}
+
+impl CodecSupport {
+    /// Everything that this version can decode.
+    pub fn all() -> Self {
+        CodecSupport {
+            compact: true,
+            zstd: true,
+            lz4: true,
+        }
+    }
+
+    /// Comma-separated names of the supported encodings and compressions, e.g. for metadata.
+    pub fn to_header(&self) -> String {
+        let names = [
+            (self.compact, "compact"),
+            (self.zstd, "zstd"),
+            (self.lz4, "lz4"),
+        ];
+        names
+            .iter()
+            .filter(|(supported, _)| *supported)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Parse names produced by `to_header`, ignoring those that are not known here.
+    pub fn from_header(header: &str) -> Self {
+        let names: Vec<&str> = header.split(',').map(str::trim).collect();
+        CodecSupport {
+            compact: names.contains(&"compact"),
+            zstd: names.contains(&"zstd"),
+            lz4: names.contains(&"lz4"),
+        }
+    }
+}
+
+/// Codec used for values sent over the network.
+///
+/// The default codec sends plain bincode of values and adds no compression.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WireCodec {
+    /// Use the compact encoding, which stores the placement of a tensor only once and packs
+    /// bit tensors as well as ring tensors holding only bits.
+    pub compact: bool,
+    /// Compression applied to values whose encoding is at least `compression_threshold` bytes.
+    pub compression: Compression,
+    pub compression_threshold: usize,
+}
+
+impl Default for WireCodec {
+    fn default() -> Self {
+        WireCodec {
+            compact: false,
+            compression: Compression::None,
+            compression_threshold: 4096,
+        }
+    }
+}
+
+impl WireCodec {
+    /// Compact encoding without compression.
+    pub fn compact() -> Self {
+        WireCodec {
+            compact: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_compression(self, compression: Compression, threshold: usize) -> Self {
+        WireCodec {
+            compression,
+            compression_threshold: threshold,
+            ..self
+        }
+    }
+
+    /// Same codec restricted to what a receiver with the given support can decode.
+    pub fn negotiate(&self, support: &CodecSupport) -> WireCodec {
+        let compression = match self.compression {
+            Compression::Zstd if support.zstd => Compression::Zstd,
+            Compression::Lz4 if support.lz4 => Compression::Lz4,
+            _ => Compression::None,
+        };
+        WireCodec {
+            compact: self.compact && support.compact,
+            compression,
+            compression_threshold: self.compression_threshold,
+        }
+    }
+
+    /// Whether values are sent as plain bincode, as by the default codec.
+    pub fn is_plain(&self) -> bool {
+        !self.compact && self.compression == Compression::None
+    }
+
+    pub fn encode(&self, value: &Value) -> Result<Vec<u8>> {
+        let (encoding, payload) = if self.compact {
+            (ENCODING_COMPACT, serialize(&CompactValue::from(value))?)
+        } else {
+            (ENCODING_BINCODE, serialize(value)?)
+        };
+
+        let (compression, payload) = if payload.len() >= self.compression_threshold {
+            let compressed = match self.compression {
+                Compression::None => None,
+                Compression::Zstd => Some((
+                    COMPRESSION_ZSTD,
+                    zstd::encode_all(payload.as_slice(), 0).map_err(|e| {
+                        Error::Networking(format!("failed to compress value: {}", e))
+                    })?,
+                )),
+                Compression::Lz4 => {
+                    Some((COMPRESSION_LZ4, lz4_flex::compress_prepend_size(&payload)))
+                }
+            };
+            match compressed {
+                Some((compression, compressed)) if compressed.len() < payload.len() => {
+                    (compression, compressed)
+                }
+                _ => (COMPRESSION_NONE, payload),
+            }
+        } else {
+            (COMPRESSION_NONE, payload)
+        };
+
+        let mut bytes = Vec::with_capacity(2 + payload.len());
+        bytes.push(encoding);
+        bytes.push(compression);
+        bytes.extend_from_slice(&payload);
+
+        if !self.is_plain() && tracing::enabled!(tracing::Level::DEBUG) {
+            let plain_bytes = bincode::serialized_size(value).unwrap_or_default();
+            tracing::debug!(
+                plain_bytes,
+                encoded_bytes = bytes.len(),
+                saved_bytes = plain_bytes as i64 - bytes.len() as i64,
+                "encoded {} value",
+                value.ty()
+            );
+        }
+        Ok(bytes)
+    }
+
+    /// Decode a value encoded by any codec.
+    ///
+    /// Tensors sent with the compact encoding have no owner until `fill_in_owner` is called.
+    pub fn decode(bytes: &[u8]) -> Result<Value> {
+        let (encoding, compression, payload) = match bytes {
+            [encoding, compression, payload @ ..] => (*encoding, *compression, payload),
+            _ => {
+                return Err(Error::Networking(
+                    "encoded value is missing its header".to_string(),
+                ))
+            }
+        };
+
+        let decompressed;
+        let payload = match compression {
+            COMPRESSION_NONE => payload,
+            COMPRESSION_ZSTD => {
+                decompressed = decompress_zstd(payload, MAX_DECOMPRESSED_SIZE)?;
+                decompressed.as_slice()
+            }
+            COMPRESSION_LZ4 => {
+                decompressed = decompress_lz4(payload, MAX_DECOMPRESSED_SIZE)?;
+                decompressed.as_slice()
+            }
+            other => {
+                return Err(Error::Networking(format!(
+                    "unknown compression {} of encoded value",
+                    other
+                )))
+            }
+        };
+
+        match encoding {
+            ENCODING_BINCODE => deserialize(payload),
+            ENCODING_COMPACT => deserialize::<CompactValue>(payload)?.try_into_value(),
+            other => Err(Error::Networking(format!(
+                "unknown encoding {} of value",
+                other
+            ))),
+        }
+    }
+}
+
+/// Place tensors decoded without an owner on the given host, which received them.
+pub fn fill_in_owner(mut value: Value, plc: &HostPlacement) -> Value {
+    let owner = match &mut value {
+        Value::HostRing64Tensor(x) => Some(&mut x.1),
+        Value::HostRing128Tensor(x) => Some(&mut x.1),
+        Value::HostFixed64Tensor(x) => Some(&mut x.tensor.1),
+        Value::HostFixed128Tensor(x) => Some(&mut x.tensor.1),
+        Value::HostBitTensor(x) => Some(&mut x.1),
+        _ => None,
+    };
+    if let Some(owner) = owner {
+        if owner.owner.0 == UNKNOWN_OWNER {
+            *owner = plc.clone();
+        }
+    }
+    value
+}
+
+fn decompress_zstd(payload: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let decoder = zstd::stream::read::Decoder::new(payload)
+        .map_err(|e| Error::Networking(format!("failed to decompress value: {}", e)))?;
+    let mut decompressed = Vec::new();
+    decoder
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Error::Networking(format!("failed to decompress value: {}", e)))?;
+    if decompressed.len() > max_size {
+        return Err(too_large(max_size));
+    }
+    Ok(decompressed)
+}
+
+fn decompress_lz4(payload: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    // the size prefix comes from the sender, so check it before allocating
+    let size = match payload.get(..4) {
+        Some(prefix) => u32::from_le_bytes(prefix.try_into().unwrap()) as usize,
+        None => {
+            return Err(Error::Networking(
+                "compressed value is missing its size".to_string(),
+            ))
+        }
+    };
+    if size > max_size {
+        return Err(too_large(max_size));
+    }
+    lz4_flex::decompress_size_prepended(payload)
+        .map_err(|e| Error::Networking(format!("failed to decompress value: {}", e)))
+}
+
+fn too_large(max_size: usize) -> Error {
+    Error::Networking(format!(
+        "decompressed value exceeds the maximum size of {} bytes",
+        max_size
+    ))
+}
+
+/// Number of elements of a tensor with the given shape, as sent by a peer.
+fn checked_len(shape: &[usize]) -> Result<usize> {
+    shape
+        .iter()
+        .try_fold(1usize, |len, dim| len.checked_mul(*dim))
+        .ok_or_else(|| Error::Networking(format!("tensor shape {:?} is too large", shape)))
+}
+
+fn serialize<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    bincode::serialize(value)
+        .map_err(|e| Error::Networking(format!("failed to serialize value: {}", e)))
+}
+
+fn deserialize<'de, T: Deserialize<'de>>(bytes: &'de [u8]) -> Result<T> {
+    bincode::deserialize(bytes)
+        .map_err(|e| Error::Networking(format!("failed to deserialize value: {}", e)))
+}
+
+/// Compact form of the values most commonly sent during secure computations.
+///
+/// Owners of tensors are left out, since values are placed on their receiver.
+#[derive(Serialize, Deserialize)]
+enum CompactValue {
+    Ring64(CompactRingTensor<u64>),
+    Ring128(CompactRingTensor<u128>),
+    Fixed64 {
+        tensor: CompactRingTensor<u64>,
+        fractional_precision: u32,
+        integral_precision: u32,
+    },
+    Fixed128 {
+        tensor: CompactRingTensor<u128>,
+        fractional_precision: u32,
+        integral_precision: u32,
+    },
+    Bit {
+        shape: Vec<usize>,
+        bits: Vec<u8>,
+    },
+    /// Values without a compact form.
+    Other(Value),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactRingTensor<T> {
+    shape: Vec<usize>,
+    data: RingData<T>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum RingData<T> {
+    Words(Vec<T>),
+    /// Ring tensors holding only zeros and ones, packed into bytes.
+    Bits(Vec<u8>),
+}
+
+impl<T> From<&HostRingTensor<T>> for CompactRingTensor<T>
+where
+    T: Copy + From<u8> + PartialOrd,
+{
+    fn from(tensor: &HostRingTensor<T>) -> Self {
+        let one = T::from(1);
+        let data = if tensor.0.iter().all(|x| x.0 <= one) {
+            RingData::Bits(pack_bits(tensor.0.iter().map(|x| x.0 == one)))
+        } else {
+            RingData::Words(tensor.0.iter().map(|x| x.0).collect())
+        };
+        CompactRingTensor {
+            shape: tensor.0.shape().to_vec(),
+            data,
+        }
+    }
+}
+
+impl<T> CompactRingTensor<T>
+where
+    T: Clone + From<u8>,
+{
+    fn try_into_tensor(self) -> Result<HostRingTensor<T>> {
+        let len = checked_len(&self.shape)?;
+        let words: Vec<Wrapping<T>> = match self.data {
+            RingData::Words(words) => words.into_iter().map(Wrapping).collect(),
+            RingData::Bits(bits) => unpack_bits(bits, len)?
+                .into_iter()
+                .map(|bit| Wrapping(T::from(bit as u8)))
+                .collect(),
+        };
+        let array = ArrayD::from_shape_vec(IxDyn(&self.shape), words)
+            .map_err(|e| Error::Networking(format!("malformed ring tensor: {}", e)))?;
+        Ok(HostRingTensor(
+            array.into_shared(),
+            HostPlacement::from(UNKNOWN_OWNER),
+        ))
+    }
+}
+
+impl From<&Value> for CompactValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::HostRing64Tensor(x) => CompactValue::Ring64(x.as_ref().into()),
+            Value::HostRing128Tensor(x) => CompactValue::Ring128(x.as_ref().into()),
+            Value::HostFixed64Tensor(x) => CompactValue::Fixed64 {
+                tensor: (&x.tensor).into(),
+                fractional_precision: x.fractional_precision,
+                integral_precision: x.integral_precision,
+            },
+            Value::HostFixed128Tensor(x) => CompactValue::Fixed128 {
+                tensor: (&x.tensor).into(),
+                fractional_precision: x.fractional_precision,
+                integral_precision: x.integral_precision,
+            },
+            Value::HostBitTensor(x) => CompactValue::Bit {
+                shape: x.0.dim.slice().to_vec(),
+                bits: pack_bits(x.0.data.iter().by_vals()),
+            },
+            value => CompactValue::Other(value.clone()),
+        }
+    }
+}
+
+impl CompactValue {
+    fn try_into_value(self) -> Result<Value> {
+        let value = match self {
+            CompactValue::Ring64(tensor) => tensor.try_into_tensor()?.into(),
+            CompactValue::Ring128(tensor) => tensor.try_into_tensor()?.into(),
+            CompactValue::Fixed64 {
+                tensor,
+                fractional_precision,
+                integral_precision,
+            } => HostFixedTensor {
+                tensor: tensor.try_into_tensor()?,
+                fractional_precision,
+                integral_precision,
+            }
+            .into(),
+            CompactValue::Fixed128 {
+                tensor,
+                fractional_precision,
+                integral_precision,
+            } => HostFixedTensor {
+                tensor: tensor.try_into_tensor()?,
+                fractional_precision,
+                integral_precision,
+            }
+            .into(),
+            CompactValue::Bit { shape, bits } => {
+                let len = checked_len(&shape)?;
+                HostBitTensor(
+                    BitArrayRepr::from_raw(unpack_bits(bits, len)?, IxDyn(&shape)),
+                    HostPlacement::from(UNKNOWN_OWNER),
+                )
+                .into()
+            }
+            CompactValue::Other(value) => value,
+        };
+        Ok(value)
+    }
+}
+
+fn pack_bits<I: Iterator<Item = bool>>(bits: I) -> Vec<u8> {
+    bits.collect::<BitVec<u8, Lsb0>>().into_vec()
+}
+
+fn unpack_bits(bytes: Vec<u8>, len: usize) -> Result<BitVec<u8, Lsb0>> {
+    let mut bits = BitVec::<u8, Lsb0>::from_vec(bytes);
+    if bits.len() < len {
+        return Err(Error::Networking(format!(
+            "expected {} bits but only {} were sent",
+            len,
+            bits.len()
+        )));
+    }
+    bits.truncate(len);
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostString;
+    use ndarray::array;
+
+    fn ring64(values: ArrayD<u64>) -> Value {
+        HostRingTensor(
+            values.mapv(Wrapping).into_shared(),
+            HostPlacement::from("alice"),
+        )
+        .into()
+    }
+
+    fn codecs() -> Vec<WireCodec> {
+        vec![
+            WireCodec::default(),
+            WireCodec::compact(),
+            WireCodec::compact().with_compression(Compression::Zstd, 0),
+            WireCodec::compact().with_compression(Compression::Lz4, 0),
+            WireCodec::default().with_compression(Compression::Zstd, 0),
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let bits =
+            BitArrayRepr::from_vec(vec![1, 0, 1, 1, 0, 0], &crate::host::RawShape(vec![2, 3]));
+        let values: Vec<Value> = vec![
+            ring64(array![[1, 2], [3, u64::MAX]].into_dyn()),
+            ring64(array![[1, 0, 1], [0, 0, 1]].into_dyn()),
+            HostRingTensor(
+                array![0u128, 1, 1].mapv(Wrapping).into_dyn().into_shared(),
+                HostPlacement::from("alice"),
+            )
+            .into(),
+            HostBitTensor(bits, HostPlacement::from("alice")).into(),
+            HostString("hello".to_string(), HostPlacement::from("alice")).into(),
+        ];
+        for codec in codecs() {
+            for value in &values {
+                let bytes = codec.encode(value).unwrap();
+                let decoded = WireCodec::decode(&bytes).unwrap();
+                let decoded = fill_in_owner(decoded, &HostPlacement::from("alice"));
+                assert_eq!(&decoded, value, "{:?}", codec);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fill_in_owner() {
+        let value = ring64(array![1, 2].into_dyn());
+        let bob = HostPlacement::from("bob");
+
+        // only the compact encoding leaves out the owner
+        let bytes = WireCodec::default().encode(&value).unwrap();
+        let decoded = fill_in_owner(WireCodec::decode(&bytes).unwrap(), &bob);
+        assert_eq!(decoded, value);
+
+        let bytes = WireCodec::compact().encode(&value).unwrap();
+        match fill_in_owner(WireCodec::decode(&bytes).unwrap(), &bob) {
+            Value::HostRing64Tensor(x) => assert_eq!(x.1, bob),
+            other => panic!("unexpected value {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let codec = WireCodec::compact().with_compression(Compression::Zstd, 0);
+        assert_eq!(
+            codec.negotiate(&CodecSupport::default()),
+            WireCodec::default().with_compression(Compression::None, 0)
+        );
+        assert_eq!(codec.negotiate(&CodecSupport::all()), codec);
+
+        let support = CodecSupport {
+            compact: true,
+            zstd: false,
+            lz4: true,
+        };
+        assert_eq!(
+            codec.negotiate(&support),
+            WireCodec::compact().with_compression(Compression::None, 0)
+        );
+        assert_eq!(CodecSupport::from_header(&support.to_header()), support);
+        assert_eq!(CodecSupport::from_header(""), CodecSupport::default());
+        assert_eq!(
+            CodecSupport::from_header("compact, brotli"),
+            CodecSupport {
+                compact: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_compact_packs_bits() {
+        let value = ring64(ArrayD::from_elem(IxDyn(&[64, 64]), 1));
+        let plain = WireCodec::default().encode(&value).unwrap();
+        let compact = WireCodec::compact().encode(&value).unwrap();
+        // one bit instead of 64 per element
+        assert!(compact.len() * 50 < plain.len());
+    }
+
+    #[test]
+    fn test_reject_decompression_bomb() {
+        let mut bytes = vec![ENCODING_BINCODE, COMPRESSION_LZ4];
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&[0; 16]);
+        assert!(matches!(
+            WireCodec::decode(&bytes),
+            Err(Error::Networking(_))
+        ));
+
+        let zeros = vec![0u8; 1 << 16];
+        let compressed = zstd::encode_all(zeros.as_slice(), 0).unwrap();
+        assert_eq!(decompress_zstd(&compressed, 1 << 16).unwrap(), zeros);
+        assert!(decompress_zstd(&compressed, 1 << 10).is_err());
+        let compressed = lz4_flex::compress_prepend_size(&zeros);
+        assert!(decompress_lz4(&compressed, 1 << 10).is_err());
+    }
+
+    #[test]
+    fn test_reject_overflowing_shape() {
+        let compact = CompactValue::Bit {
+            shape: vec![usize::MAX, 2],
+            bits: vec![],
+        };
+        let mut bytes = vec![ENCODING_COMPACT, COMPRESSION_NONE];
+        bytes.extend(serialize(&compact).unwrap());
+        assert!(matches!(
+            WireCodec::decode(&bytes),
+            Err(Error::Networking(_))
+        ));
+    }
+
+    #[test]
+    fn test_reject_unknown_encoding() {
+        let value = ring64(array![1, 2].into_dyn());
+        let mut bytes = WireCodec::default().encode(&value).unwrap();
+        bytes[0] = 42;
+        assert!(matches!(
+            WireCodec::decode(&bytes),
+            Err(Error::Networking(_))
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-//! Constants for the exponential backoff policy for gRPC
+//! Constants for the exponential backoff policy used when retrying network operations
 use lazy_static::lazy_static;
 use std::time::Duration;
 
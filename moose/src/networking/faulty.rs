@@ -0,0 +1,251 @@
+//! Networking that injects faults for testing how sessions fail.
+
+use super::*;
+use parking_lot::Mutex;
+use std::time::Duration;
+
+/// Fault injected when sending a value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault {
+    /// Report the value as sent without delivering it, as if lost on the way.
+    Drop,
+    /// Deliver the value after the given delay.
+    Delay(Duration),
+    /// Deliver the value twice.
+    Duplicate,
+    /// Deliver garbage of a different type in place of the value.
+    Corrupt,
+    /// Never deliver the value nor complete the send, as if the sender stalled.
+    Withhold,
+    /// Fail the send with a networking error, as if the sender crashed.
+    Fail,
+}
+
+/// Rule of a fault plan, matching values by rendezvous key and receiver.
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    /// Only match values with this rendezvous key; all values if `None`.
+    pub rendezvous_key: Option<RendezvousKey>,
+    /// Only match values sent to this receiver; all receivers if `None`.
+    pub receiver: Option<Identity>,
+    pub fault: Fault,
+    /// Number of values to which the fault is applied; unlimited if `None`.
+    pub times: Option<usize>,
+}
+
+impl FaultRule {
+    fn matches(&self, receiver: &Identity, rendezvous_key: &RendezvousKey) -> bool {
+        self.receiver.as_ref().map_or(true, |r| r == receiver)
+            && self
+                .rendezvous_key
+                .as_ref()
+                .map_or(true, |key| key == rendezvous_key)
+    }
+}
+
+/// Scripted faults to inject, applied by the first rule matching each value.
+#[derive(Clone, Debug, Default)]
+pub struct FaultPlan {
+    pub rules: Vec<FaultRule>,
+}
+
+impl FaultPlan {
+    pub fn new() -> FaultPlan {
+        FaultPlan::default()
+    }
+
+    pub fn rule(mut self, rule: FaultRule) -> FaultPlan {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Inject a fault whenever the value with the given rendezvous key is sent.
+    pub fn on_key(self, rendezvous_key: RendezvousKey, fault: Fault) -> FaultPlan {
+        self.rule(FaultRule {
+            rendezvous_key: Some(rendezvous_key),
+            receiver: None,
+            fault,
+            times: None,
+        })
+    }
+
+    /// Inject a fault whenever a value is sent to the given receiver.
+    pub fn on_receiver(self, receiver: Identity, fault: Fault) -> FaultPlan {
+        self.rule(FaultRule {
+            rendezvous_key: None,
+            receiver: Some(receiver),
+            fault,
+            times: None,
+        })
+    }
+}
+
+/// Networking that injects faults into values sent through another implementation.
+///
+/// Faults are applied on the sending side only; receiving is passed on unchanged.
+pub struct FaultyNetworking<N> {
+    inner: N,
+    plan: FaultPlan,
+    /// Number of times each rule has been applied.
+    applied: Mutex<Vec<usize>>,
+}
+
+impl<N> FaultyNetworking<N> {
+    pub fn new(inner: N, plan: FaultPlan) -> Self {
+        let applied = Mutex::new(vec![0; plan.rules.len()]);
+        FaultyNetworking {
+            inner,
+            plan,
+            applied,
+        }
+    }
+
+    fn fault(&self, receiver: &Identity, rendezvous_key: &RendezvousKey) -> Option<Fault> {
+        let mut applied = self.applied.lock();
+        for (rule, applied) in self.plan.rules.iter().zip(applied.iter_mut()) {
+            if rule.matches(receiver, rendezvous_key)
+                && rule.times.map_or(true, |times| *applied < times)
+            {
+                *applied += 1;
+                return Some(rule.fault.clone());
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl<N> AsyncNetworking for FaultyNetworking<N>
+where
+    N: AsyncNetworking + Send + Sync,
+{
+    async fn send(
+        &self,
+        val: &Value,
+        receiver: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<()> {
+        let fault = match self.fault(receiver, rendezvous_key) {
+            Some(fault) => fault,
+            None => {
+                return self
+                    .inner
+                    .send(val, receiver, rendezvous_key, session_id)
+                    .await
+            }
+        };
+        tracing::debug!("Injecting {:?} when sending '{}'", fault, rendezvous_key);
+        match fault {
+            Fault::Drop => Ok(()),
+            Fault::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                self.inner
+                    .send(val, receiver, rendezvous_key, session_id)
+                    .await
+            }
+            Fault::Duplicate => {
+                self.inner
+                    .send(val, receiver, rendezvous_key, session_id)
+                    .await?;
+                self.inner
+                    .send(val, receiver, rendezvous_key, session_id)
+                    .await
+            }
+            Fault::Corrupt => {
+                let garbage = Value::Bit(Box::new(0xff));
+                self.inner
+                    .send(&garbage, receiver, rendezvous_key, session_id)
+                    .await
+            }
+            Fault::Withhold => futures::future::pending().await,
+            Fault::Fail => Err(Error::Networking(format!(
+                "injected failure when sending '{}' to {}",
+                rendezvous_key, receiver
+            ))),
+        }
+    }
+
+    async fn receive(
+        &self,
+        sender: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<Value> {
+        self.inner.receive(sender, rendezvous_key, session_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostPlacement;
+    use crate::networking::local::LocalAsyncNetworking;
+    use std::convert::TryInto;
+
+    fn unit() -> Value {
+        Value::HostUnit(Box::new(HostUnit(HostPlacement::from("alice"))))
+    }
+
+    #[tokio::test]
+    async fn test_fault_plan() {
+        let bob = Identity::from("bob");
+        let carole = Identity::from("carole");
+        let key = |byte| RendezvousKey::from_bytes([byte; 16]);
+        let plan = FaultPlan::new()
+            .rule(FaultRule {
+                rendezvous_key: Some(key(0)),
+                receiver: None,
+                fault: Fault::Fail,
+                times: Some(1),
+            })
+            .on_key(key(1), Fault::Corrupt)
+            .on_receiver(carole.clone(), Fault::Fail);
+        let net = FaultyNetworking::new(LocalAsyncNetworking::default(), plan);
+        let session_id: SessionId = "12345".try_into().unwrap();
+
+        // the first rule only applies once
+        assert!(net.send(&unit(), &bob, &key(0), &session_id).await.is_err());
+        net.send(&unit(), &bob, &key(0), &session_id).await.unwrap();
+
+        net.send(&unit(), &bob, &key(1), &session_id).await.unwrap();
+        let received = net
+            .receive(&Identity::from("alice"), &key(1), &session_id)
+            .await
+            .unwrap();
+        assert_eq!(received.ty(), Ty::Bit);
+
+        assert!(matches!(
+            net.send(&unit(), &carole, &key(2), &session_id).await,
+            Err(Error::Networking(_))
+        ));
+        net.send(&unit(), &bob, &key(2), &session_id).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_lost_values_never_arrive() {
+        let alice = Identity::from("alice");
+        let bob = Identity::from("bob");
+        let key = |byte| RendezvousKey::from_bytes([byte; 16]);
+        let plan = FaultPlan::new()
+            .on_key(key(0), Fault::Drop)
+            .on_key(key(1), Fault::Withhold);
+        let net = FaultyNetworking::new(LocalAsyncNetworking::default(), plan);
+        let session_id: SessionId = "12345".try_into().unwrap();
+        let timeout = Duration::from_secs(60);
+
+        let (key0, key1) = (key(0), key(1));
+        let value = unit();
+
+        // a dropped value is reported as sent
+        net.send(&value, &bob, &key0, &session_id).await.unwrap();
+        let receive = net.receive(&alice, &key0, &session_id);
+        assert!(tokio::time::timeout(timeout, receive).await.is_err());
+
+        // a withheld value is never reported as sent
+        let send = net.send(&value, &bob, &key1, &session_id);
+        assert!(tokio::time::timeout(timeout, send).await.is_err());
+        let receive = net.receive(&alice, &key1, &session_id);
+        assert!(tokio::time::timeout(timeout, receive).await.is_err());
+    }
+}
@@ -7,29 +7,66 @@ mod gen {
 
 use self::gen::networking_client::NetworkingClient;
 use self::gen::networking_server::{Networking, NetworkingServer};
-use self::gen::{SendValueRequest, SendValueResponse};
+use self::gen::{SendValueRequest, SendValueResponse, SendValuesRequest, SendValuesResponse};
+use crate::networking::codec::{CodecSupport, WireCodec};
 use crate::networking::constants;
+use crate::networking::rendezvous::{BufferLimits, RendezvousStore};
 use crate::networking::AsyncNetworking;
 use crate::prelude::*;
 use crate::{Error, Result};
-use async_cell::sync::AsyncCell;
 use async_trait::async_trait;
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
 use tonic::transport::{Channel, ClientTlsConfig, Uri};
 
+/// Response metadata in which receivers announce the codecs they support.
+const CODECS_METADATA_KEY: &str = "moose-codecs";
+
+/// Configuration for batching of outgoing values.
+///
+/// Values sent to the same receiver are buffered until either the window has passed since
+/// the first of them was sent or their total size reaches the threshold, and are then
+/// shipped together in a single request. Batches may include values from several sessions.
+#[derive(Clone, Debug)]
+pub struct BatchingConfig {
+    /// Maximum time a value is held back waiting for others.
+    pub window: Duration,
+    /// Size in bytes at which a batch is sent without waiting for the window to pass.
+    pub max_bytes: usize,
+}
+
+impl Default for BatchingConfig {
+    fn default() -> Self {
+        BatchingConfig {
+            window: Duration::from_millis(1),
+            max_bytes: 1 << 20,
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct GrpcNetworkingManager {
     stores: Arc<SessionStores>,
     channels: Arc<Channels>,
     tls_client_config: Option<ClientTlsConfig>,
+    batching: Option<BatchingConfig>,
+    batchers: Arc<Batchers>,
+    codec: Option<WireCodec>,
+    peer_codecs: Arc<PeerCodecs>,
 }
 
 impl GrpcNetworkingManager {
+    /// Server receiving values into the stores of this manager.
+    ///
+    /// Values buffered for sessions that have not started here are evicted periodically.
     pub fn new_server(&self) -> NetworkingServer<impl Networking> {
+        self.stores.spawn_eviction();
         NetworkingServer::new(NetworkingImpl {
             stores: Arc::clone(&self.stores),
         })
@@ -40,6 +77,10 @@ impl GrpcNetworkingManager {
             stores: Default::default(),
             channels: Default::default(),
             tls_client_config: None,
+            batching: None,
+            batchers: Default::default(),
+            codec: None,
+            peer_codecs: Default::default(),
         }
     }
 
@@ -48,15 +89,60 @@ impl GrpcNetworkingManager {
             stores: Default::default(),
             channels: Default::default(),
             tls_client_config: Some(client),
+            batching: None,
+            batchers: Default::default(),
+            codec: None,
+            peer_codecs: Default::default(),
+        }
+    }
+
+    /// Send values in batches instead of one request per value.
+    ///
+    /// Receivers must support the batched request, i.e. run a version of Moose that has it.
+    pub fn with_batching(self, batching: BatchingConfig) -> Self {
+        GrpcNetworkingManager {
+            batching: Some(batching),
+            ..self
+        }
+    }
+
+    /// Encode values with the given codec instead of plain bincode.
+    ///
+    /// Each receiver is first probed for the codecs it supports, which it announces in the
+    /// metadata of its responses, and values are only encoded with what it supports. Values
+    /// fall back to plain bincode for receivers that announce nothing, e.g. because they run
+    /// an older version of Moose, or that could not be probed yet.
+    pub fn with_codec(self, codec: WireCodec) -> Self {
+        GrpcNetworkingManager {
+            codec: Some(codec),
+            ..self
         }
     }
 
+    /// Limit the values buffered for sessions that have not started here.
+    ///
+    /// Values beyond the limits are rejected, and are retried by their senders.
+    pub fn with_buffer_limits(self, limits: BufferLimits) -> Self {
+        self.stores.set_limits(limits);
+        self
+    }
+
+    /// Number of cells per session that are either waiting for or holding a value.
+    pub fn outstanding_cells(&self) -> HashMap<SessionId, usize> {
+        self.stores.outstanding_cells()
+    }
+
     pub fn new_session(&self, session_id: SessionId) -> Arc<impl AsyncNetworking> {
+        self.stores.start_session(&session_id);
         Arc::new(GrpcNetworking {
             session_id,
             stores: Arc::clone(&self.stores),
             channels: Arc::clone(&self.channels),
             tls_config: self.tls_client_config.clone(),
+            batching: self.batching.clone(),
+            batchers: Arc::clone(&self.batchers),
+            codec: self.codec.clone(),
+            peer_codecs: Arc::clone(&self.peer_codecs),
         })
     }
 }
@@ -66,6 +152,10 @@ pub struct GrpcNetworking {
     session_id: SessionId,
     stores: Arc<SessionStores>,
     channels: Arc<Channels>,
+    batching: Option<BatchingConfig>,
+    batchers: Arc<Batchers>,
+    codec: Option<WireCodec>,
+    peer_codecs: Arc<PeerCodecs>,
 }
 
 impl GrpcNetworking {
@@ -93,6 +183,202 @@ impl GrpcNetworking {
             .clone(); // cloning channels is cheap per tonic documentation
         Ok(channel)
     }
+
+    /// Codec to encode values sent to the given receiver with, if any.
+    ///
+    /// The codecs supported by the receiver are probed on first use and remembered, unless
+    /// the probe fails, in which case plain bincode is used until a later probe succeeds.
+    async fn negotiated_codec(&self, receiver: &Identity) -> Option<WireCodec> {
+        let codec = self.codec.as_ref()?;
+        let supported = match self.peer_codecs.get(receiver).map(|entry| *entry) {
+            Some(supported) => supported,
+            None => match probe_codecs(self.channel(receiver).ok()?).await {
+                Ok(supported) => {
+                    tracing::debug!("{} supports codecs '{}'", receiver, supported.to_header());
+                    self.peer_codecs.insert(receiver.clone(), supported);
+                    supported
+                }
+                Err(e) => {
+                    tracing::debug!("could not probe codecs of {}: {}", receiver, e);
+                    CodecSupport::default()
+                }
+            },
+        };
+        let codec = codec.negotiate(&supported);
+        if codec.is_plain() {
+            None
+        } else {
+            Some(codec)
+        }
+    }
+
+    /// Serialize a value together with its session and rendezvous key.
+    fn tagged_value(
+        &self,
+        val: &Value,
+        rendezvous_key: &RendezvousKey,
+        codec: Option<&WireCodec>,
+    ) -> Result<Vec<u8>> {
+        let bytes = match codec {
+            None => bincode::serialize(&TaggedValue {
+                session_id: self.session_id.clone(),
+                rendezvous_key: rendezvous_key.clone(),
+                value: val.clone(),
+            }),
+            Some(codec) => bincode::serialize(&EncodedTaggedValue {
+                session_id: self.session_id.clone(),
+                rendezvous_key: rendezvous_key.clone(),
+                value: codec.encode(val)?,
+            }),
+        };
+        bytes.map_err(|e| Error::Networking(e.to_string()))
+    }
+
+    /// Queue of values to be sent in batches to the given receiver.
+    fn batcher(
+        &self,
+        receiver: &Identity,
+        batching: &BatchingConfig,
+    ) -> Result<mpsc::UnboundedSender<PendingValue>> {
+        let batcher = self
+            .batchers
+            .entry(receiver.clone())
+            .or_try_insert_with(|| {
+                tracing::debug!("Creating batcher to '{}'", receiver);
+                let channel = self.channel(receiver)?;
+                let (tx, rx) = mpsc::unbounded_channel();
+                tokio::spawn(batch_loop(channel, batching.clone(), rx));
+                Ok::<_, Error>(tx)
+            })?
+            .clone();
+        Ok(batcher)
+    }
+
+    async fn send_batched(
+        &self,
+        tagged_value: Vec<u8>,
+        encoded: bool,
+        receiver: &Identity,
+        batching: &BatchingConfig,
+    ) -> Result<()> {
+        let (reply, result) = oneshot::channel();
+        self.batcher(receiver, batching)?
+            .send(PendingValue {
+                session_id: self.session_id.clone(),
+                tagged_value,
+                encoded,
+                reply,
+            })
+            .map_err(|_| {
+                Error::Networking(format!("batcher for {} is no longer running", receiver))
+            })?;
+        result
+            .await
+            .map_err(|_| Error::Networking(format!("batcher for {} dropped the value", receiver)))?
+    }
+}
+
+/// Value waiting to be sent in a batch, together with where to report the outcome.
+struct PendingValue {
+    session_id: SessionId,
+    tagged_value: Vec<u8>,
+    encoded: bool,
+    reply: oneshot::Sender<Result<()>>,
+}
+
+/// Collect values into batches and send them, with a request per session in the batch, and
+/// separate requests for values that are encoded and values that are not.
+///
+/// Requests are sent concurrently, so that values of one session that the receiver rejects,
+/// e.g. because its buffer is full, only fail that session and do not hold up others.
+async fn batch_loop(
+    channel: Channel,
+    batching: BatchingConfig,
+    mut rx: mpsc::UnboundedReceiver<PendingValue>,
+) {
+    while let Some(first) = rx.recv().await {
+        let deadline = tokio::time::Instant::now() + batching.window;
+        let mut size = first.tagged_value.len();
+        let mut batch = vec![first];
+        while size < batching.max_bytes {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(pending)) => {
+                    size += pending.tagged_value.len();
+                    batch.push(pending);
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        tracing::debug!("Sending batch of {} values ({} bytes)", batch.len(), size);
+        let mut sessions: HashMap<(SessionId, bool), Vec<PendingValue>> = HashMap::new();
+        for pending in batch {
+            sessions
+                .entry((pending.session_id.clone(), pending.encoded))
+                .or_default()
+                .push(pending);
+        }
+        for ((_, encoded), session_batch) in sessions {
+            let channel = channel.clone();
+            tokio::spawn(async move {
+                let (tagged_values, replies): (Vec<_>, Vec<_>) = session_batch
+                    .into_iter()
+                    .map(|pending| (pending.tagged_value, pending.reply))
+                    .unzip();
+                let res = send_batch(&channel, tagged_values, encoded).await;
+                for reply in replies {
+                    // the sending side may have given up waiting
+                    let _ = reply.send(res.clone());
+                }
+            });
+        }
+    }
+}
+
+/// Ask a receiver for the codecs it supports, with an empty batch of values.
+///
+/// Receivers that do not announce any codecs, or do not know batches, only support plain
+/// bincode.
+async fn probe_codecs(channel: Channel) -> Result<CodecSupport> {
+    let mut client = NetworkingClient::new(channel);
+    let request = SendValuesRequest {
+        tagged_values: vec![],
+        encoded: false,
+    };
+    match client.send_values(request).await {
+        Ok(response) => Ok(response
+            .metadata()
+            .get(CODECS_METADATA_KEY)
+            .and_then(|header| header.to_str().ok())
+            .map_or_else(CodecSupport::default, CodecSupport::from_header)),
+        Err(status) if status.code() == tonic::Code::Unimplemented => Ok(CodecSupport::default()),
+        Err(status) => Err(Error::Networking(status.to_string())),
+    }
+}
+
+async fn send_batch(channel: &Channel, tagged_values: Vec<Vec<u8>>, encoded: bool) -> Result<()> {
+    retry(
+        ExponentialBackoff {
+            max_elapsed_time: *constants::MAX_ELAPSED_TIME,
+            max_interval: *constants::MAX_INTERVAL,
+            multiplier: constants::MULTIPLIER,
+            ..Default::default()
+        },
+        || async {
+            let request = SendValuesRequest {
+                tagged_values: tagged_values.clone(),
+                encoded,
+            };
+            let mut client = NetworkingClient::new(channel.clone());
+            let _response = client
+                .send_values(request)
+                .await
+                .map_err(|e| Error::Networking(e.to_string()))?;
+            Ok(())
+        },
+    )
+    .await
 }
 
 #[async_trait]
@@ -104,6 +390,15 @@ impl AsyncNetworking for GrpcNetworking {
         rendezvous_key: &RendezvousKey,
         _session_id: &SessionId,
     ) -> Result<()> {
+        let codec = self.negotiated_codec(receiver).await;
+        let encoded = codec.is_some();
+        let bytes = self.tagged_value(val, rendezvous_key, codec.as_ref())?;
+        if let Some(batching) = &self.batching {
+            #[cfg(debug_assertions)]
+            tracing::debug!("Queueing '{}' for {}", rendezvous_key, receiver);
+            return self.send_batched(bytes, encoded, receiver, batching).await;
+        }
+
         retry(
             ExponentialBackoff {
                 max_elapsed_time: *constants::MAX_ELAPSED_TIME,
@@ -112,15 +407,9 @@ impl AsyncNetworking for GrpcNetworking {
                 ..Default::default()
             },
             || async {
-                let tagged_value = TaggedValue {
-                    session_id: self.session_id.clone(),
-                    rendezvous_key: rendezvous_key.clone(),
-                    value: val.clone(),
-                };
-                let bytes = bincode::serialize(&tagged_value)
-                    .map_err(|e| Error::Networking(e.to_string()))?;
                 let request = SendValueRequest {
-                    tagged_value: bytes,
+                    tagged_value: bytes.clone(),
+                    encoded,
                 };
                 let channel = self.channel(receiver)?;
                 let mut client = NetworkingClient::new(channel);
@@ -142,13 +431,7 @@ impl AsyncNetworking for GrpcNetworking {
         rendezvous_key: &RendezvousKey,
         _session_id: &SessionId,
     ) -> Result<Value> {
-        let cell = cell(
-            &self.stores,
-            self.session_id.clone(),
-            rendezvous_key.clone(),
-        );
-
-        let (actual_sender, value) = cell.take().await;
+        let (actual_sender, value) = self.stores.take(&self.session_id, rendezvous_key).await;
         match actual_sender {
             Some(actual_sender) => {
                 if *sender != actual_sender {
@@ -173,39 +456,66 @@ impl AsyncNetworking for GrpcNetworking {
 
 impl Drop for GrpcNetworking {
     fn drop(&mut self) {
-        let _ = self.stores.remove(&self.session_id);
+        self.stores.end_session(&self.session_id);
     }
 }
 
 type AuthValue = (Option<Identity>, Value);
 
-type SessionStore = DashMap<RendezvousKey, Arc<AsyncCell<AuthValue>>>;
-type SessionStores = DashMap<SessionId, Arc<SessionStore>>;
+type SessionStores = RendezvousStore<AuthValue>;
 type Channels = DashMap<Identity, Channel>;
+type Batchers = DashMap<Identity, mpsc::UnboundedSender<PendingValue>>;
+type PeerCodecs = DashMap<Identity, CodecSupport>;
 
 #[derive(Default)]
 struct NetworkingImpl {
     pub stores: Arc<SessionStores>,
 }
 
-fn cell(
-    stores: &Arc<SessionStores>,
-    session_id: SessionId,
-    rendezvous_key: RendezvousKey,
-) -> Arc<AsyncCell<AuthValue>> {
-    let session_store = stores
-        .entry(session_id) // TODO(Morten) only use the secure bytes?
-        .or_insert_with(Arc::default)
-        .value()
-        .clone();
+impl NetworkingImpl {
+    fn store_value(
+        &self,
+        sender: Option<Identity>,
+        tagged_value: TaggedValue,
+    ) -> std::result::Result<(), tonic::Status> {
+        self.stores
+            .put(
+                &tagged_value.session_id,
+                tagged_value.rendezvous_key,
+                (sender, tagged_value.value),
+            )
+            .map_err(|e| tonic::Status::new(tonic::Code::ResourceExhausted, e.to_string()))
+    }
+}
 
-    let cell = session_store
-        .entry(rendezvous_key)
-        .or_insert_with(AsyncCell::shared)
-        .value()
-        .clone();
+/// Response announcing the codecs supported here.
+fn response_with_codecs<T>(message: T) -> tonic::Response<T> {
+    let mut response = tonic::Response::new(message);
+    // the names of codecs are always valid metadata
+    let supported = CodecSupport::all().to_header().parse().unwrap();
+    response
+        .metadata_mut()
+        .insert(CODECS_METADATA_KEY, supported);
+    response
+}
 
-    cell
+fn parse_tagged_value(
+    bytes: &[u8],
+    encoded: bool,
+) -> std::result::Result<TaggedValue, tonic::Status> {
+    let parse_error =
+        |_e| tonic::Status::new(tonic::Code::Aborted, "failed to parse value".to_string());
+    if !encoded {
+        return bincode::deserialize::<TaggedValue>(bytes).map_err(parse_error);
+    }
+    let encoded_value = bincode::deserialize::<EncodedTaggedValue>(bytes).map_err(parse_error)?;
+    let value = WireCodec::decode(&encoded_value.value)
+        .map_err(|e| tonic::Status::new(tonic::Code::Aborted, e.to_string()))?;
+    Ok(TaggedValue {
+        session_id: encoded_value.session_id,
+        rendezvous_key: encoded_value.rendezvous_key,
+        value,
+    })
 }
 
 #[async_trait]
@@ -219,19 +529,40 @@ impl Networking for NetworkingImpl {
             .map(Identity::from);
 
         let request = request.into_inner();
-        let tagged_value =
-            bincode::deserialize::<TaggedValue>(&request.tagged_value).map_err(|_e| {
-                tonic::Status::new(tonic::Code::Aborted, "failed to parse value".to_string())
-            })?;
+        let tagged_value = parse_tagged_value(&request.tagged_value, request.encoded)?;
+        self.store_value(sender, tagged_value)?;
 
-        let cell = cell(
-            &self.stores,
-            tagged_value.session_id,
-            tagged_value.rendezvous_key,
-        );
-        cell.set((sender, tagged_value.value));
+        Ok(response_with_codecs(SendValueResponse::default()))
+    }
+
+    async fn send_values(
+        &self,
+        request: tonic::Request<SendValuesRequest>,
+    ) -> std::result::Result<tonic::Response<SendValuesResponse>, tonic::Status> {
+        let sender = crate::grpc::extract_sender(&request)
+            .map_err(|e| tonic::Status::new(tonic::Code::Aborted, e))?
+            .map(Identity::from);
+
+        let request = request.into_inner();
+        // all values are parsed and checked to fit before any is stored, so that a retried
+        // batch is not half stored
+        let values = request
+            .tagged_values
+            .iter()
+            .map(|tagged_value| {
+                let tagged_value = parse_tagged_value(tagged_value, request.encoded)?;
+                Ok((
+                    tagged_value.session_id,
+                    tagged_value.rendezvous_key,
+                    (sender.clone(), tagged_value.value),
+                ))
+            })
+            .collect::<std::result::Result<Vec<_>, tonic::Status>>()?;
+        self.stores
+            .put_all(values)
+            .map_err(|e| tonic::Status::new(tonic::Code::ResourceExhausted, e.to_string()))?;
 
-        Ok(tonic::Response::new(SendValueResponse::default()))
+        Ok(response_with_codecs(SendValuesResponse::default()))
     }
 }
 
@@ -241,3 +572,171 @@ struct TaggedValue {
     rendezvous_key: RendezvousKey,
     value: Value,
 }
+
+/// Same as `TaggedValue` but with the value encoded by a `WireCodec`.
+#[derive(Serialize, Deserialize)]
+struct EncodedTaggedValue {
+    session_id: SessionId,
+    rendezvous_key: RendezvousKey,
+    value: Vec<u8>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostPlacement, HostString};
+    use std::convert::TryFrom;
+
+    fn tagged_value(rendezvous_key: [u8; 16], value: &str) -> Vec<u8> {
+        bincode::serialize(&TaggedValue {
+            session_id: SessionId::try_from("session").unwrap(),
+            rendezvous_key: RendezvousKey::from_bytes(rendezvous_key),
+            value: HostString(value.to_string(), HostPlacement::from("alice")).into(),
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_values_fans_out() {
+        let server = NetworkingImpl::default();
+        let request = tonic::Request::new(SendValuesRequest {
+            tagged_values: vec![tagged_value([0; 16], "x"), tagged_value([1; 16], "y")],
+            encoded: false,
+        });
+        server.send_values(request).await.unwrap();
+
+        let session_id = SessionId::try_from("session").unwrap();
+        for (key, expected) in [([0; 16], "x"), ([1; 16], "y")] {
+            let expected: Value =
+                HostString(expected.to_string(), HostPlacement::from("alice")).into();
+            let (sender, value) = server
+                .stores
+                .take(&session_id, &RendezvousKey::from_bytes(key))
+                .await;
+            assert_eq!(sender, None);
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_values_rejects_malformed_batch() {
+        let server = NetworkingImpl::default();
+        let request = tonic::Request::new(SendValuesRequest {
+            tagged_values: vec![tagged_value([0; 16], "x"), vec![1, 2, 3]],
+            encoded: false,
+        });
+        assert!(server.send_values(request).await.is_err());
+        assert!(server.stores.outstanding_cells().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_values_stores_nothing_if_batch_does_not_fit() {
+        let server = NetworkingImpl::default();
+        server.stores.set_limits(BufferLimits {
+            max_values: 2,
+            ..Default::default()
+        });
+        let session_id = SessionId::try_from("session").unwrap();
+        let request = |keys: &[u8]| {
+            tonic::Request::new(SendValuesRequest {
+                tagged_values: keys
+                    .iter()
+                    .map(|key| tagged_value([*key; 16], "x"))
+                    .collect(),
+                encoded: false,
+            })
+        };
+        server.send_values(request(&[0])).await.unwrap();
+
+        let status = server.send_values(request(&[1, 2])).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+        assert_eq!(server.stores.outstanding_cells()[&session_id], 1);
+
+        // retrying a batch that was already stored succeeds
+        server.send_values(request(&[0, 1])).await.unwrap();
+        server.send_values(request(&[0, 1])).await.unwrap();
+        assert_eq!(server.stores.outstanding_cells()[&session_id], 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_value_decodes_encoded_value() {
+        let server = NetworkingImpl::default();
+        let value: Value = HostString("x".to_string(), HostPlacement::from("alice")).into();
+        let tagged_value = bincode::serialize(&EncodedTaggedValue {
+            session_id: SessionId::try_from("session").unwrap(),
+            rendezvous_key: RendezvousKey::from_bytes([0; 16]),
+            value: WireCodec::compact().encode(&value).unwrap(),
+        })
+        .unwrap();
+        let request = tonic::Request::new(SendValueRequest {
+            tagged_value,
+            encoded: true,
+        });
+        server.send_value(request).await.unwrap();
+
+        let (_, received) = server
+            .stores
+            .take(
+                &SessionId::try_from("session").unwrap(),
+                &RendezvousKey::from_bytes([0; 16]),
+            )
+            .await;
+        assert_eq!(received, value);
+    }
+
+    #[tokio::test]
+    async fn test_responses_announce_codecs() {
+        let server = NetworkingImpl::default();
+        // probes are empty batches
+        let request = tonic::Request::new(SendValuesRequest {
+            tagged_values: vec![],
+            encoded: false,
+        });
+        let response = server.send_values(request).await.unwrap();
+        let header = response.metadata().get(CODECS_METADATA_KEY).unwrap();
+        assert_eq!(
+            CodecSupport::from_header(header.to_str().unwrap()),
+            CodecSupport::all()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reject_values_beyond_buffer_limits() {
+        let manager = GrpcNetworkingManager::without_tls().with_buffer_limits(BufferLimits {
+            max_values: 1,
+            ..Default::default()
+        });
+        let server = NetworkingImpl {
+            stores: Arc::clone(&manager.stores),
+        };
+        let request = tonic::Request::new(SendValuesRequest {
+            tagged_values: vec![tagged_value([0; 16], "x"), tagged_value([1; 16], "y")],
+            encoded: false,
+        });
+        let status = server.send_values(request).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        // values are accepted once the session has started here
+        let session_id = SessionId::try_from("session").unwrap();
+        let networking = manager.new_session(session_id.clone());
+        let request = tonic::Request::new(SendValuesRequest {
+            tagged_values: vec![tagged_value([0; 16], "x"), tagged_value([1; 16], "y")],
+            encoded: false,
+        });
+        server.send_values(request).await.unwrap();
+        assert_eq!(manager.outstanding_cells()[&session_id], 2);
+
+        networking
+            .receive(
+                &Identity::from("alice"),
+                &RendezvousKey::from_bytes([0; 16]),
+                &session_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(manager.outstanding_cells()[&session_id], 1);
+
+        drop(networking);
+        assert!(manager.outstanding_cells().is_empty());
+    }
+}
@@ -5,10 +5,16 @@ use crate::error::{Error, Result};
 use crate::execution::Identity;
 use async_trait::async_trait;
 
+pub mod codec;
 mod constants;
+pub mod faulty;
 pub mod grpc;
 pub mod local;
+pub mod quic;
+pub mod rendezvous;
+pub mod simulated;
 pub mod tcpstream;
+pub mod uds;
 
 /// Requirements for synchronous networking.
 ///
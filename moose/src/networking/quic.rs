@@ -0,0 +1,722 @@
+//! QUIC networking implementation.
+//!
+//! Every party keeps a single connection to each other party, over which every value is
+//! sent on its own stream and acknowledged once stored. Values therefore do not wait on
+//! each other as they do when sharing a single TCP stream, which matters for replicated
+//! protocols with many independent values in flight.
+//!
+//! As with `TcpStreamNetworking`, values are numbered per receiver and epoch, so that a value
+//! resent after its acknowledgement was lost is recognised and not stored again.
+
+use crate::{
+    computation::{RendezvousKey, SessionId, Value},
+    execution::Identity,
+    networking::{
+        codec::{CodecSupport, WireCodec},
+        constants,
+        rendezvous::{BufferLimits, RendezvousStore, SessionNetworking},
+        tcpstream::{self, Delivered, DeliveredType},
+        AsyncNetworking,
+    },
+    Error, Result,
+};
+use async_trait::async_trait;
+use backoff::future::retry;
+use backoff::ExponentialBackoff;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_rustls::rustls::Certificate;
+
+/// Protocol negotiated during the TLS handshake.
+const ALPN_PROTOCOL: &[u8] = b"moose";
+
+/// Protocols offered during the TLS handshake, in order of preference.
+///
+/// The first one also announces the codecs supported here, as `moose+compact,zstd,lz4`. The
+/// plain protocol is kept for peers that do not announce any, to which values are sent as
+/// plain bincode.
+fn alpn_protocols() -> Vec<Vec<u8>> {
+    let with_codecs = [
+        ALPN_PROTOCOL,
+        &b"+"[..],
+        CodecSupport::all().to_header().as_bytes(),
+    ]
+    .concat();
+    vec![with_codecs, ALPN_PROTOCOL.to_vec()]
+}
+
+/// Codecs supported by the peer of a connection, as negotiated during the TLS handshake.
+fn connection_codecs(connection: &quinn::Connection) -> CodecSupport {
+    connection
+        .handshake_data()
+        .and_then(|data| data.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        .and_then(|data| data.protocol)
+        .and_then(|protocol| {
+            protocol
+                .strip_prefix(ALPN_PROTOCOL)
+                .and_then(|rest| rest.strip_prefix(b"+"))
+                .map(|header| CodecSupport::from_header(&String::from_utf8_lossy(header)))
+        })
+        .unwrap_or_default()
+}
+
+/// Maximum number of values a peer may be sending to us concurrently.
+const MAX_CONCURRENT_STREAMS: u32 = 1024;
+
+/// Maximum size of a single message, guarding against peers exhausting our memory.
+const MAX_MESSAGE_SIZE: usize = 1 << 30;
+
+/// Reply to a message that was stored; messages that were not are answered by a reset.
+const ACK: &[u8] = &[1];
+
+/// Incoming values, still encoded, together with their authenticated sender.
+type StoreType = Arc<RendezvousStore<(Identity, Vec<u8>)>>;
+
+/// TLS configuration for `QuicNetworking`.
+///
+/// As for `TcpStreamNetworking`, both ends of a connection must present a certificate
+/// signed by the common CA, and the common name of a peer's certificate is taken as its
+/// identity. QUIC always uses TLS so this configuration is mandatory.
+#[derive(Clone)]
+pub struct QuicTlsConfig {
+    client: quinn::ClientConfig,
+    server: quinn::ServerConfig,
+}
+
+impl QuicTlsConfig {
+    /// Create configuration from a PEM-encoded certificate, its private key, and the CA certificate.
+    pub fn from_pem(cert: &[u8], key: &[u8], ca_cert: &[u8]) -> Result<QuicTlsConfig> {
+        let (mut client, mut server) = tcpstream::rustls_configs(cert, key, ca_cert)?;
+        client.alpn_protocols = alpn_protocols();
+        server.alpn_protocols = alpn_protocols();
+
+        let mut transport = quinn::TransportConfig::default();
+        transport.max_concurrent_bidi_streams(MAX_CONCURRENT_STREAMS.into());
+        let mut server = quinn::ServerConfig::with_crypto(Arc::new(server));
+        server.transport_config(Arc::new(transport));
+
+        Ok(QuicTlsConfig {
+            client: quinn::ClientConfig::new(Arc::new(client)),
+            server,
+        })
+    }
+}
+
+/// Message sent on each stream; the sender is given by the connection.
+#[derive(Serialize, Deserialize, Debug)]
+struct Message {
+    /// Chosen by the sender when it starts up.
+    epoch: u64,
+    /// Number of the value among those sent to the receiver in the epoch, kept when resending.
+    seq: u64,
+    session_id: SessionId,
+    rendezvous_key: RendezvousKey,
+    /// Value encoded with a `WireCodec`.
+    value: Vec<u8>,
+}
+
+/// Extract the identity of a peer from the certificate it presented.
+fn connection_identity(connection: &quinn::Connection) -> Result<Identity> {
+    let certs = connection
+        .peer_identity()
+        .and_then(|certs| certs.downcast::<Vec<Certificate>>().ok());
+    tcpstream::peer_identity(certs.as_deref().map(|certs| certs.as_slice()))
+}
+
+async fn resolve(address: &str) -> Result<SocketAddr> {
+    tokio::net::lookup_host(address)
+        .await
+        .map_err(|e| Error::Networking(format!("could not resolve {}: {}", address, e)))?
+        .next()
+        .ok_or_else(|| Error::Networking(format!("no address found for {}", address)))
+}
+
+/// Read a single message from a stream into the store, acknowledging it once stored.
+///
+/// Messages that were already stored are acknowledged without storing them again, since
+/// their value may have been taken in the meantime.
+async fn handle_stream(
+    (mut reply, stream): (quinn::SendStream, quinn::RecvStream),
+    store: StoreType,
+    delivered: DeliveredType,
+    sender: Identity,
+) -> Result<()> {
+    let raw_message = stream
+        .read_to_end(MAX_MESSAGE_SIZE)
+        .await
+        .map_err(|e| Error::Networking(format!("failed to read message from {}: {}", sender, e)))?;
+    let message: Message = bincode::deserialize(&raw_message).map_err(|e| {
+        Error::Networking(format!(
+            "failed to deserialize message from {}: {}",
+            sender, e
+        ))
+    })?;
+
+    let (epoch, seq) = (message.epoch, message.seq);
+    if !delivered.contains_key(&(sender.clone(), epoch)) {
+        tcpstream::register_epoch(&delivered, &sender, epoch);
+    }
+    let duplicate = delivered
+        .get(&(sender.clone(), epoch))
+        .map_or(false, |entry| entry.contains(seq));
+
+    if duplicate {
+        tracing::debug!("ignoring duplicate message {} from {}", seq, sender);
+    } else {
+        tracing::debug!(
+            "storing key: {:?} from: {}",
+            (&message.session_id, &message.rendezvous_key),
+            sender
+        );
+        if let Err(e) = store.put(
+            &message.session_id,
+            message.rendezvous_key,
+            (sender.clone(), message.value),
+        ) {
+            // only this stream fails, and the sender retries after the reset
+            tracing::debug!("rejected value {} from {}: {}", seq, sender, e);
+            let _ = reply.reset(1u32.into());
+            return Ok(());
+        }
+        delivered
+            .entry((sender.clone(), epoch))
+            .or_insert_with(Delivered::new)
+            .insert(seq);
+    }
+    reply
+        .write_all(ACK)
+        .await
+        .map_err(|e| Error::Networking(format!("failed to acknowledge {}: {}", sender, e)))?;
+    reply
+        .finish()
+        .await
+        .map_err(|e| Error::Networking(format!("failed to acknowledge {}: {}", sender, e)))?;
+    Ok(())
+}
+
+/// Accept streams from an incoming connection until it is closed.
+async fn handle_connection(
+    connecting: quinn::Connecting,
+    store: StoreType,
+    delivered: DeliveredType,
+) -> Result<()> {
+    let connection = connecting
+        .await
+        .map_err(|e| Error::Networking(format!("failed to accept connection: {}", e)))?;
+    let sender = connection_identity(&connection)?;
+    tracing::debug!(
+        "accepted connection from {} as {}",
+        connection.remote_address(),
+        sender
+    );
+    loop {
+        let stream = match connection.accept_bi().await {
+            Ok(stream) => stream,
+            Err(quinn::ConnectionError::ApplicationClosed(_)) => {
+                tracing::debug!("{} closed the connection", sender);
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(Error::Networking(format!(
+                    "connection from {} failed: {}",
+                    sender, e
+                )))
+            }
+        };
+        let store = Arc::clone(&store);
+        let delivered = Arc::clone(&delivered);
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_stream(stream, store, delivered, sender).await {
+                tracing::error!("{}", e);
+            }
+        });
+    }
+}
+
+async fn server(endpoint: quinn::Endpoint, store: StoreType, delivered: DeliveredType) {
+    while let Some(connecting) = endpoint.accept().await {
+        let store = Arc::clone(&store);
+        let delivered = Arc::clone(&delivered);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(connecting, store, delivered).await {
+                tracing::error!("{}", e);
+            }
+        });
+    }
+}
+
+/// Party to which values are sent, connected to on first use.
+struct Peer {
+    identity: Identity,
+    address: String,
+    connection: Mutex<Option<quinn::Connection>>,
+    /// Sequence number of the next value sent to the peer.
+    next_seq: AtomicU64,
+}
+
+impl Peer {
+    /// Current connection to the peer, connecting anew if there is none or it was lost.
+    async fn connection(
+        &self,
+        endpoint: &quinn::Endpoint,
+    ) -> std::result::Result<quinn::Connection, backoff::Error<Error>> {
+        let mut connection = self.connection.lock().await;
+        match &*connection {
+            Some(connection) if connection.close_reason().is_none() => Ok(connection.clone()),
+            _ => {
+                let new_connection = self.connect(endpoint).await?;
+                *connection = Some(new_connection.clone());
+                Ok(new_connection)
+            }
+        }
+    }
+
+    /// Connect to the peer, failing permanently if it presents the wrong certificate.
+    async fn connect(
+        &self,
+        endpoint: &quinn::Endpoint,
+    ) -> std::result::Result<quinn::Connection, backoff::Error<Error>> {
+        let address = resolve(&self.address).await?;
+        // the certificate is checked against the host part of the address, as done by gRPC
+        let server_name = self
+            .address
+            .rsplit_once(':')
+            .map_or(self.address.as_str(), |(host, _)| host);
+        let connection = endpoint
+            .connect(address, server_name)
+            .map_err(|e| {
+                Error::Networking(format!("could not connect to {}: {}", self.address, e))
+            })?
+            .await
+            .map_err(|e| {
+                Error::Networking(format!("could not connect to {}: {}", self.address, e))
+            })?;
+        // a peer presenting the wrong certificate will not fix itself
+        let peer = connection_identity(&connection).map_err(backoff::Error::permanent)?;
+        if peer != self.identity {
+            return Err(backoff::Error::permanent(Error::Networking(format!(
+                "expected {} at {} but certificate identifies {}",
+                self.identity, self.address, peer
+            ))));
+        }
+        tracing::debug!("connected to: {} -> {}", self.identity, self.address);
+        Ok(connection)
+    }
+}
+
+pub struct QuicNetworking {
+    own_name: String,
+    endpoint: quinn::Endpoint,
+    store: StoreType,
+    peers: HashMap<Identity, Peer>,
+    codec: WireCodec,
+    epoch: u64,
+}
+
+impl QuicNetworking {
+    /// Listen on the address of `own_name` in `hosts`, with connections to other hosts
+    /// made when values are first sent to them.
+    pub async fn new(
+        own_name: &str,
+        hosts: HashMap<String, String>,
+        tls_config: QuicTlsConfig,
+    ) -> Result<QuicNetworking> {
+        tracing::debug!("own name: {}", own_name);
+        let own_address = hosts
+            .get(own_name)
+            .ok_or_else(|| Error::Networking("own host name not in hosts map".to_string()))?;
+
+        let mut endpoint = quinn::Endpoint::server(tls_config.server, resolve(own_address).await?)
+            .map_err(|e| {
+                Error::Networking(format!("could not bind to address: {}: {}", own_address, e))
+            })?;
+        endpoint.set_default_client_config(tls_config.client);
+        tracing::debug!("listening on: {}", own_address);
+
+        let store = StoreType::default();
+        let delivered = DeliveredType::default();
+        tokio::spawn(server(endpoint.clone(), Arc::clone(&store), delivered));
+        store.spawn_eviction();
+
+        let peers = hosts
+            .into_iter()
+            .filter(|(placement, _)| placement != own_name)
+            .map(|(placement, address)| {
+                let identity = Identity::from(placement);
+                let peer = Peer {
+                    identity: identity.clone(),
+                    address,
+                    connection: Mutex::new(None),
+                    next_seq: AtomicU64::new(1),
+                };
+                (identity, peer)
+            })
+            .collect();
+
+        Ok(QuicNetworking {
+            own_name: own_name.to_string(),
+            endpoint,
+            store,
+            peers,
+            codec: WireCodec::default(),
+            epoch: rand::random(),
+        })
+    }
+
+    /// Encode values with the given codec instead of plain bincode.
+    ///
+    /// Peers announce the codecs they support when connecting, and values are only encoded
+    /// with what the receiving peer supports. Values are decoded according to the codec the
+    /// sender used, so parties may use different codecs.
+    pub fn with_codec(mut self, codec: WireCodec) -> QuicNetworking {
+        self.codec = codec;
+        self
+    }
+
+    /// Limit the values buffered for sessions that have not started locally.
+    pub fn with_buffer_limits(self, limits: BufferLimits) -> QuicNetworking {
+        self.store.set_limits(limits);
+        self
+    }
+
+    /// Number of cells per session that are either waiting for or holding a value.
+    pub fn outstanding_cells(&self) -> HashMap<SessionId, usize> {
+        self.store.outstanding_cells()
+    }
+
+    /// Networking for a single session, whose values are dropped once it is dropped.
+    pub fn new_session(self: &Arc<Self>, session_id: SessionId) -> Arc<impl AsyncNetworking> {
+        Arc::new(SessionNetworking::new(
+            Arc::clone(self),
+            Arc::clone(&self.store),
+            session_id,
+        ))
+    }
+
+    /// Address on which values are received.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.endpoint
+            .local_addr()
+            .map_err(|e| Error::Networking(format!("could not get local address: {}", e)))
+    }
+
+    /// Send a value on a new stream, completing once the peer has stored it.
+    ///
+    /// The value is encoded for the connection it is sent over, since a peer that reconnects
+    /// may support different codecs.
+    async fn send_message(
+        &self,
+        peer: &Peer,
+        seq: u64,
+        value: &Value,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> std::result::Result<(), backoff::Error<Error>> {
+        let connection = peer.connection(&self.endpoint).await?;
+        let codec = self.codec.negotiate(&connection_codecs(&connection));
+        let message = Message {
+            epoch: self.epoch,
+            seq,
+            session_id: session_id.clone(),
+            rendezvous_key: rendezvous_key.clone(),
+            value: codec.encode(value).map_err(backoff::Error::permanent)?,
+        };
+        let raw_message = bincode::serialize(&message)
+            .map_err(|e| Error::Networking(format!("could not serialize message: {}", e)))
+            .map_err(backoff::Error::permanent)?;
+
+        let (mut stream, reply) = connection.open_bi().await.map_err(|e| {
+            Error::Networking(format!("could not open stream to {}: {}", peer.identity, e))
+        })?;
+        stream.write_all(&raw_message).await.map_err(|e| {
+            Error::Networking(format!("could not write to {}: {}", peer.identity, e))
+        })?;
+        stream.finish().await.map_err(|e| {
+            Error::Networking(format!(
+                "could not finish stream to {}: {}",
+                peer.identity, e
+            ))
+        })?;
+        let ack = reply.read_to_end(ACK.len()).await.map_err(|e| {
+            Error::Networking(format!("{} did not store value: {}", peer.identity, e))
+        })?;
+        if ack != ACK {
+            return Err(backoff::Error::transient(Error::Networking(format!(
+                "unexpected reply from {}",
+                peer.identity
+            ))));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for QuicNetworking {
+    fn drop(&mut self) {
+        self.endpoint.close(0u32.into(), b"done");
+    }
+}
+
+#[async_trait]
+impl AsyncNetworking for QuicNetworking {
+    async fn send(
+        &self,
+        value: &Value,
+        receiver: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<()> {
+        tracing::debug!("sending key: {:?} to: {}", rendezvous_key, receiver);
+        let peer = self.peers.get(receiver).ok_or_else(|| {
+            Error::Networking(format!(
+                "in session {}, no connection to send rendezvous key {} from {} to {}",
+                session_id, rendezvous_key, self.own_name, receiver
+            ))
+        })?;
+        // resent values keep their sequence number, so that the receiving end ignores them
+        // if only the acknowledgement was lost
+        let seq = peer.next_seq.fetch_add(1, Ordering::Relaxed);
+        retry(
+            ExponentialBackoff {
+                max_elapsed_time: *constants::MAX_ELAPSED_TIME,
+                max_interval: *constants::MAX_INTERVAL,
+                multiplier: constants::MULTIPLIER,
+                ..Default::default()
+            },
+            || self.send_message(peer, seq, value, rendezvous_key, session_id),
+        )
+        .await
+    }
+
+    async fn receive(
+        &self,
+        sender: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<Value> {
+        tracing::debug!(
+            "receiving key: {:?} from: {}",
+            (session_id, rendezvous_key),
+            sender
+        );
+        let (actual_sender, value) = self.store.take(session_id, rendezvous_key).await;
+        if &actual_sender != sender {
+            return Err(Error::Networking(format!(
+                "expected rendezvous key {} from {} but it was sent by {}",
+                rendezvous_key, sender, actual_sender
+            )));
+        }
+        WireCodec::decode(&value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostPlacement, HostString};
+    use std::convert::TryFrom;
+
+    struct TestCa {
+        ca: rcgen::Certificate,
+    }
+
+    impl TestCa {
+        fn new() -> TestCa {
+            let mut params = rcgen::CertificateParams::new(vec![]);
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, "ca");
+            TestCa {
+                ca: rcgen::Certificate::from_params(params).unwrap(),
+            }
+        }
+
+        fn tls_config(&self, identity: &str) -> QuicTlsConfig {
+            let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]);
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, identity);
+            let cert = rcgen::Certificate::from_params(params).unwrap();
+            QuicTlsConfig::from_pem(
+                cert.serialize_pem_with_signer(&self.ca).unwrap().as_bytes(),
+                cert.serialize_private_key_pem().as_bytes(),
+                self.ca.serialize_pem().unwrap().as_bytes(),
+            )
+            .unwrap()
+        }
+    }
+
+    /// Start networking for parties listening on ports chosen when binding, each given by its
+    /// name and the identity in its certificate, with every party sending to the others' ports.
+    async fn parties(ca: &TestCa, parties: &[(&str, &str)]) -> Vec<QuicNetworking> {
+        let hosts: HashMap<String, String> = parties
+            .iter()
+            .map(|(name, _)| (name.to_string(), "localhost:0".to_string()))
+            .collect();
+        let mut networkings = Vec::new();
+        for (name, identity) in parties {
+            let networking = QuicNetworking::new(name, hosts.clone(), ca.tls_config(identity))
+                .await
+                .unwrap();
+            networkings.push(networking);
+        }
+        let addresses: HashMap<Identity, String> = parties
+            .iter()
+            .zip(&networkings)
+            .map(|((name, _), networking)| {
+                let port = networking.local_addr().unwrap().port();
+                (Identity::from(*name), format!("localhost:{}", port))
+            })
+            .collect();
+        for networking in networkings.iter_mut() {
+            for (identity, peer) in networking.peers.iter_mut() {
+                peer.address = addresses[identity].clone();
+            }
+        }
+        networkings
+    }
+
+    fn value(s: &str) -> Value {
+        HostString(s.to_string(), HostPlacement::from("alice")).into()
+    }
+
+    #[tokio::test]
+    async fn test_send_receive() {
+        let ca = TestCa::new();
+        let mut parties = parties(&ca, &[("alice", "alice"), ("bob", "bob")]).await;
+        let bob = parties.pop().unwrap();
+        let alice = parties.pop().unwrap();
+        let session_id = SessionId::try_from("session").unwrap();
+
+        // values are independent of each other and may be received in any order
+        for i in 0..10u8 {
+            let key = RendezvousKey::from_bytes([i; 16]);
+            alice
+                .send(
+                    &value(&i.to_string()),
+                    &Identity::from("bob"),
+                    &key,
+                    &session_id,
+                )
+                .await
+                .unwrap();
+        }
+        // the codecs of bob were negotiated when connecting to it
+        let connection = alice.peers[&Identity::from("bob")]
+            .connection
+            .lock()
+            .await
+            .clone()
+            .unwrap();
+        assert_eq!(connection_codecs(&connection), CodecSupport::all());
+        for i in (0..10u8).rev() {
+            let key = RendezvousKey::from_bytes([i; 16]);
+            let received = bob
+                .receive(&Identity::from("alice"), &key, &session_id)
+                .await
+                .unwrap();
+            assert_eq!(received, value(&i.to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_values_when_session_ends() {
+        let ca = TestCa::new();
+        let mut parties = parties(&ca, &[("alice", "alice"), ("bob", "bob")]).await;
+        let bob = Arc::new(parties.pop().unwrap());
+        let alice = parties.pop().unwrap();
+        let session_id = SessionId::try_from("session").unwrap();
+
+        let session = bob.new_session(session_id.clone());
+        // the value is sent but never received
+        alice
+            .send(
+                &value("hello"),
+                &Identity::from("bob"),
+                &RendezvousKey::from_bytes([0; 16]),
+                &session_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(bob.outstanding_cells()[&session_id], 1);
+
+        drop(session);
+        assert!(bob.outstanding_cells().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_resent_value() {
+        let ca = TestCa::new();
+        let mut parties = parties(&ca, &[("alice", "alice"), ("bob", "bob")]).await;
+        let bob = Arc::new(parties.pop().unwrap());
+        let alice = parties.pop().unwrap();
+        let session_id = SessionId::try_from("session").unwrap();
+        let key = RendezvousKey::from_bytes([0; 16]);
+        let peer = &alice.peers[&Identity::from("bob")];
+
+        let session = bob.new_session(session_id.clone());
+        alice
+            .send_message(peer, 1, &value("hello"), &key, &session_id)
+            .await
+            .unwrap();
+        let received = session
+            .receive(&Identity::from("alice"), &key, &session_id)
+            .await
+            .unwrap();
+        assert_eq!(received, value("hello"));
+
+        // resending as if the acknowledgement was lost leaves no value behind
+        alice
+            .send_message(peer, 1, &value("hello"), &key, &session_id)
+            .await
+            .unwrap();
+        assert_eq!(bob.outstanding_cells()[&session_id], 0);
+
+        // nor does it recreate the session once ended
+        drop(session);
+        alice
+            .send_message(peer, 1, &value("hello"), &key, &session_id)
+            .await
+            .unwrap();
+        assert!(bob.outstanding_cells().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reject_unexpected_sender() {
+        let ca = TestCa::new();
+        let mut parties = parties(&ca, &[("bob", "bob"), ("carole", "carole")]).await;
+        let carole = parties.pop().unwrap();
+        let bob = parties.pop().unwrap();
+        let session_id = SessionId::try_from("session").unwrap();
+        let key = RendezvousKey::from_bytes([0; 16]);
+
+        carole
+            .send(&value("hello"), &Identity::from("bob"), &key, &session_id)
+            .await
+            .unwrap();
+        let res = bob
+            .receive(&Identity::from("alice"), &key, &session_id)
+            .await;
+        assert!(matches!(res, Err(Error::Networking(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reject_wrong_certificate() {
+        let ca = TestCa::new();
+        // bob presents a certificate for someone else
+        let mut parties = parties(&ca, &[("alice", "alice"), ("bob", "mallory")]).await;
+        let _bob = parties.pop().unwrap();
+        let alice = parties.pop().unwrap();
+        let peer = &alice.peers[&Identity::from("bob")];
+        let res = peer.connect(&alice.endpoint).await;
+        assert!(matches!(
+            res,
+            Err(backoff::Error::Permanent(Error::Networking(_)))
+        ));
+    }
+}
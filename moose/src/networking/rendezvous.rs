@@ -0,0 +1,592 @@
+//! Storage of received values until they are taken by the receiving operation.
+
+use crate::computation::{RendezvousKey, SessionId, Value};
+use crate::error::{Error, Result};
+use crate::execution::Identity;
+use crate::networking::AsyncNetworking;
+use async_cell::sync::AsyncCell;
+use async_trait::async_trait;
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use tokio::time::{sleep, Duration, Instant};
+
+/// Shortest and longest times between evictions of expired values.
+const MIN_EVICTION_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limits on values buffered for sessions that have not started locally.
+///
+/// Values may arrive before the receiving party has started the session, so they must be
+/// buffered, but sessions that never start would otherwise hold on to them forever.
+#[derive(Clone, Debug)]
+pub struct BufferLimits {
+    /// Maximum number of sessions not started locally for which values are buffered.
+    pub max_sessions: usize,
+    /// Maximum number of values buffered for each session not started locally.
+    pub max_values: usize,
+    /// Maximum number of bytes buffered in total for all sessions not started locally.
+    pub max_bytes: usize,
+    /// Time after which values buffered for a session that has not started are dropped.
+    ///
+    /// Started sessions without outstanding cells are also forgotten after this time.
+    pub ttl: Duration,
+}
+
+/// Values whose size counts against `BufferLimits::max_bytes`.
+pub trait BufferedSize {
+    /// Approximate number of bytes taken by the value.
+    fn buffered_size(&self) -> usize;
+}
+
+impl BufferedSize for Vec<u8> {
+    fn buffered_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl BufferedSize for Value {
+    fn buffered_size(&self) -> usize {
+        bincode::serialized_size(self).map_or(usize::MAX, |size| size as usize)
+    }
+}
+
+/// Values tagged with their sender.
+impl<S, V: BufferedSize> BufferedSize for (S, V) {
+    fn buffered_size(&self) -> usize {
+        self.1.buffered_size()
+    }
+}
+
+impl Default for BufferLimits {
+    fn default() -> Self {
+        BufferLimits {
+            max_sessions: 100,
+            max_values: 100_000,
+            max_bytes: 1 << 30,
+            ttl: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+struct SessionCells<T> {
+    cells: DashMap<RendezvousKey, Arc<AsyncCell<T>>>,
+    /// Whether the session has started locally, i.e. values are expected to be taken.
+    started: AtomicBool,
+    /// Number of bytes of the values stored before the session started.
+    buffered_bytes: AtomicUsize,
+    /// Time of creation, or of the last value taken once started.
+    touched: Mutex<Instant>,
+}
+
+impl<T> SessionCells<T> {
+    fn new(started: bool) -> Self {
+        SessionCells {
+            cells: DashMap::new(),
+            started: AtomicBool::new(started),
+            buffered_bytes: AtomicUsize::new(0),
+            touched: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn is_started(&self) -> bool {
+        self.started.load(Ordering::Relaxed)
+    }
+
+    fn is_expired(&self, ttl: Duration) -> bool {
+        let idle = self.touched.lock().elapsed() > ttl;
+        if self.is_started() {
+            idle && self.cells.is_empty()
+        } else {
+            idle
+        }
+    }
+}
+
+/// Rendezvous cells of all sessions, from which each value is taken exactly once.
+///
+/// Cells are removed as soon as their value has been taken. Values for sessions that have
+/// not started locally are buffered subject to `BufferLimits`, with sessions starting either
+/// explicitly via `start_session` or implicitly when a value is first taken.
+pub struct RendezvousStore<T> {
+    sessions: DashMap<SessionId, Arc<SessionCells<T>>>,
+    limits: RwLock<BufferLimits>,
+    /// Time of the last eviction made to find room for a session not started.
+    last_eviction: Mutex<Option<Instant>>,
+}
+
+impl<T> Default for RendezvousStore<T> {
+    fn default() -> Self {
+        RendezvousStore {
+            sessions: DashMap::new(),
+            limits: RwLock::new(BufferLimits::default()),
+            last_eviction: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> RendezvousStore<T> {
+    pub fn set_limits(&self, limits: BufferLimits) {
+        *self.limits.write() = limits;
+    }
+
+    /// Mark the session as started, so that its values are no longer subject to the limits.
+    pub fn start_session(&self, session_id: &SessionId) {
+        self.session(session_id, true);
+    }
+
+    /// Drop all cells of the session, including values that were never taken.
+    pub fn end_session(&self, session_id: &SessionId) {
+        self.sessions.remove(session_id);
+    }
+
+    /// Wait for a value and remove its cell, starting the session if needed.
+    pub async fn take(&self, session_id: &SessionId, rendezvous_key: &RendezvousKey) -> T {
+        let session = self.session(session_id, true);
+        let cell = session
+            .cells
+            .entry(rendezvous_key.clone())
+            .or_insert_with(AsyncCell::shared)
+            .value()
+            .clone();
+        let value = cell.take().await;
+        session.cells.remove(rendezvous_key);
+        *session.touched.lock() = Instant::now();
+        value
+    }
+
+    /// Number of cells per session that are either waiting for or holding a value.
+    pub fn outstanding_cells(&self) -> HashMap<SessionId, usize> {
+        self.sessions
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().cells.len()))
+            .collect()
+    }
+
+    /// Drop buffered values that have exceeded their time to live, and forget idle sessions.
+    ///
+    /// The cells outstanding afterwards, and the bytes buffered for sessions not started,
+    /// are reported through tracing.
+    pub fn evict_expired(&self) {
+        self.drop_expired();
+
+        let outstanding_cells = self.outstanding_cells();
+        for (session_id, cells) in &outstanding_cells {
+            tracing::debug!("Session {} has {} outstanding cells", session_id, cells);
+        }
+        let (buffered_sessions, buffered_bytes) = self.buffered();
+        tracing::info!(
+            "{} outstanding cells in {} sessions, buffering {} bytes for {} sessions not started",
+            outstanding_cells.values().sum::<usize>(),
+            outstanding_cells.len(),
+            buffered_bytes,
+            buffered_sessions
+        );
+    }
+
+    fn drop_expired(&self) {
+        let ttl = self.limits.read().ttl;
+        self.sessions.retain(|session_id, session| {
+            let expired = session.is_expired(ttl);
+            if expired && !session.cells.is_empty() {
+                tracing::warn!(
+                    "Dropping {} values buffered for session {} that never started",
+                    session.cells.len(),
+                    session_id
+                );
+            }
+            !expired
+        });
+    }
+
+    /// Drop expired values to make room for another session not started, unless this was
+    /// done less than `MIN_EVICTION_INTERVAL` ago.
+    ///
+    /// Values for unknown sessions may arrive at any rate, so that evicting every time the
+    /// limit is reached would have each of them scan all sessions.
+    fn drop_expired_for_room(&self) {
+        {
+            let mut last_eviction = self.last_eviction.lock();
+            match *last_eviction {
+                Some(last) if last.elapsed() < MIN_EVICTION_INTERVAL => return,
+                _ => *last_eviction = Some(Instant::now()),
+            }
+        }
+        self.drop_expired();
+    }
+
+    /// Evict expired values periodically, for as long as the store exists.
+    ///
+    /// Expiry is checked every half time to live, but at least every minute.
+    pub(crate) fn spawn_eviction(self: &Arc<Self>)
+    where
+        T: Send + Sync + 'static,
+    {
+        tokio::spawn(evict_periodically(Arc::downgrade(self)));
+    }
+
+    fn session(&self, session_id: &SessionId, started: bool) -> Arc<SessionCells<T>> {
+        let session = match self.sessions.entry(session_id.clone()) {
+            Entry::Occupied(entry) => Arc::clone(entry.get()),
+            Entry::Vacant(entry) => {
+                let session = Arc::new(SessionCells::new(started));
+                entry.insert(Arc::clone(&session));
+                session
+            }
+        };
+        if started && !session.started.swap(true, Ordering::Relaxed) {
+            *session.touched.lock() = Instant::now();
+        }
+        session
+    }
+
+    /// Number of sessions not started, and the bytes buffered for them in total.
+    fn buffered(&self) -> (usize, usize) {
+        self.sessions
+            .iter()
+            .filter(|entry| !entry.value().is_started())
+            .fold((0, 0), |(sessions, bytes), entry| {
+                let session_bytes = entry.value().buffered_bytes.load(Ordering::Relaxed);
+                (sessions + 1, bytes.saturating_add(session_bytes))
+            })
+    }
+}
+
+impl<T: BufferedSize> RendezvousStore<T> {
+    /// Store a value, failing if the session has not started and its buffer is full.
+    pub fn put(
+        &self,
+        session_id: &SessionId,
+        rendezvous_key: RendezvousKey,
+        value: T,
+    ) -> Result<()> {
+        let session = self.existing_or_buffered_session(session_id)?;
+        if !session.cells.contains_key(&rendezvous_key) && !session.is_started() {
+            let size = value.buffered_size();
+            self.check_room(&session, session_id, 1, size)?;
+            session.buffered_bytes.fetch_add(size, Ordering::Relaxed);
+        }
+        let cell = session
+            .cells
+            .entry(rendezvous_key)
+            .or_insert_with(AsyncCell::shared)
+            .value()
+            .clone();
+        cell.set(value);
+        Ok(())
+    }
+
+    /// Store several values, failing without storing any if one of them does not fit.
+    ///
+    /// Values already stored under the same key take no extra room, so that retrying the
+    /// same values succeeds where the first attempt did.
+    pub fn put_all(&self, values: Vec<(SessionId, RendezvousKey, T)>) -> Result<()> {
+        let mut keys: HashMap<&SessionId, HashMap<&RendezvousKey, &T>> = HashMap::new();
+        for (session_id, rendezvous_key, value) in &values {
+            keys.entry(session_id)
+                .or_default()
+                .insert(rendezvous_key, value);
+        }
+        let mut new_bytes: usize = 0;
+        for (session_id, keys) in keys {
+            let session = self.existing_or_buffered_session(session_id)?;
+            if session.is_started() {
+                continue;
+            }
+            let new_values: Vec<&T> = keys
+                .into_iter()
+                .filter(|(rendezvous_key, _)| !session.cells.contains_key(*rendezvous_key))
+                .map(|(_, value)| value)
+                .collect();
+            new_bytes = new_values
+                .iter()
+                .map(|value| value.buffered_size())
+                .fold(new_bytes, usize::saturating_add);
+            self.check_room(&session, session_id, new_values.len(), new_bytes)?;
+        }
+        for (session_id, rendezvous_key, value) in values {
+            self.put(&session_id, rendezvous_key, value)?;
+        }
+        Ok(())
+    }
+
+    fn existing_or_buffered_session(&self, session_id: &SessionId) -> Result<Arc<SessionCells<T>>> {
+        match self.sessions.get(session_id) {
+            Some(session) => Ok(Arc::clone(session.value())),
+            None => self.new_buffered_session(session_id),
+        }
+    }
+
+    /// Fail if a session that has not started has no room for the given number of new values,
+    /// or if the buffers of all sessions not started have no room for the given bytes.
+    fn check_room(
+        &self,
+        session: &SessionCells<T>,
+        session_id: &SessionId,
+        new_values: usize,
+        new_bytes: usize,
+    ) -> Result<()> {
+        if session.is_started() {
+            return Ok(());
+        }
+        let limits = self.limits.read().clone();
+        if session.cells.len() + new_values > limits.max_values {
+            return Err(Error::Networking(format!(
+                "buffer for session {} cannot hold {} more values, limited to {}",
+                session_id, new_values, limits.max_values
+            )));
+        }
+        let (_, buffered_bytes) = self.buffered();
+        if buffered_bytes.saturating_add(new_bytes) > limits.max_bytes {
+            return Err(Error::Networking(format!(
+                "not buffering {} more bytes for session {}, already buffering {} bytes for sessions not started, limited to {}",
+                new_bytes, session_id, buffered_bytes, limits.max_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    fn new_buffered_session(&self, session_id: &SessionId) -> Result<Arc<SessionCells<T>>> {
+        // sessions are only created here when values arrive, so this bounds the memory used
+        let max_sessions = self.limits.read().max_sessions;
+        let (mut buffered_sessions, _) = self.buffered();
+        if buffered_sessions >= max_sessions {
+            self.drop_expired_for_room();
+            buffered_sessions = self.buffered().0;
+        }
+        if buffered_sessions >= max_sessions {
+            return Err(Error::Networking(format!(
+                "not buffering values for session {}, already buffering for {} sessions not started",
+                session_id, buffered_sessions
+            )));
+        }
+        Ok(self.session(session_id, false))
+    }
+}
+
+async fn evict_periodically<T>(store: Weak<RendezvousStore<T>>) {
+    loop {
+        let interval = match store.upgrade() {
+            Some(store) => {
+                (store.limits.read().ttl / 2).clamp(MIN_EVICTION_INTERVAL, MAX_EVICTION_INTERVAL)
+            }
+            None => return,
+        };
+        sleep(interval).await;
+        match store.upgrade() {
+            Some(store) => store.evict_expired(),
+            None => return,
+        }
+    }
+}
+
+/// Networking of a single session, on top of networking shared by all sessions.
+///
+/// The session is started when this is created and ended when it is dropped, so that values
+/// the session never took, including those of cancelled receives, are not kept forever.
+pub struct SessionNetworking<N, T> {
+    networking: Arc<N>,
+    store: Arc<RendezvousStore<T>>,
+    session_id: SessionId,
+}
+
+impl<N, T> SessionNetworking<N, T> {
+    pub(crate) fn new(
+        networking: Arc<N>,
+        store: Arc<RendezvousStore<T>>,
+        session_id: SessionId,
+    ) -> Self {
+        store.start_session(&session_id);
+        SessionNetworking {
+            networking,
+            store,
+            session_id,
+        }
+    }
+}
+
+impl<N, T> Drop for SessionNetworking<N, T> {
+    fn drop(&mut self) {
+        self.store.end_session(&self.session_id);
+    }
+}
+
+#[async_trait]
+impl<N, T> AsyncNetworking for SessionNetworking<N, T>
+where
+    N: AsyncNetworking + Send + Sync,
+    T: Send + Sync,
+{
+    async fn send(
+        &self,
+        value: &Value,
+        receiver: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<()> {
+        self.networking
+            .send(value, receiver, rendezvous_key, session_id)
+            .await
+    }
+
+    async fn receive(
+        &self,
+        sender: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<Value> {
+        self.networking
+            .receive(sender, rendezvous_key, session_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryFrom;
+
+    fn session(id: &str) -> SessionId {
+        SessionId::try_from(id).unwrap()
+    }
+
+    fn key(byte: u8) -> RendezvousKey {
+        RendezvousKey::from_bytes([byte; 16])
+    }
+
+    impl BufferedSize for i32 {
+        fn buffered_size(&self) -> usize {
+            std::mem::size_of::<i32>()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cells_removed_once_taken() {
+        let store = RendezvousStore::default();
+        store.put(&session("a"), key(0), 1).unwrap();
+        store.put(&session("a"), key(1), 2).unwrap();
+        assert_eq!(store.outstanding_cells()[&session("a")], 2);
+
+        assert_eq!(store.take(&session("a"), &key(0)).await, 1);
+        assert_eq!(store.outstanding_cells()[&session("a")], 1);
+        assert_eq!(store.take(&session("a"), &key(1)).await, 2);
+        assert_eq!(store.outstanding_cells()[&session("a")], 0);
+
+        store.end_session(&session("a"));
+        assert!(store.outstanding_cells().is_empty());
+    }
+
+    #[test]
+    fn test_buffer_limits() {
+        let store = RendezvousStore::default();
+        store.set_limits(BufferLimits {
+            max_sessions: 1,
+            max_values: 2,
+            ttl: Duration::from_secs(60),
+            ..Default::default()
+        });
+
+        store.put(&session("a"), key(0), 0).unwrap();
+        store.put(&session("a"), key(1), 1).unwrap();
+        // resending a value does not count against the limit
+        store.put(&session("a"), key(1), 1).unwrap();
+        assert!(store.put(&session("a"), key(2), 2).is_err());
+        assert!(store.put(&session("b"), key(0), 0).is_err());
+
+        // started sessions are not limited
+        store.start_session(&session("a"));
+        store.put(&session("a"), key(2), 2).unwrap();
+        store.start_session(&session("c"));
+        store.put(&session("c"), key(0), 0).unwrap();
+    }
+
+    #[test]
+    fn test_put_all_or_nothing() {
+        let store = RendezvousStore::default();
+        store.set_limits(BufferLimits {
+            max_values: 2,
+            ..Default::default()
+        });
+        store.put(&session("a"), key(0), 0).unwrap();
+
+        let values = vec![(session("a"), key(1), 1), (session("a"), key(2), 2)];
+        assert!(store.put_all(values).is_err());
+        assert_eq!(store.outstanding_cells()[&session("a")], 1);
+
+        // values already stored take no extra room
+        let values = vec![(session("a"), key(0), 0), (session("a"), key(1), 1)];
+        store.put_all(values.clone()).unwrap();
+        store.put_all(values).unwrap();
+        assert_eq!(store.outstanding_cells()[&session("a")], 2);
+    }
+
+    #[test]
+    fn test_evict_expired() {
+        let store = RendezvousStore::default();
+        store.set_limits(BufferLimits {
+            max_sessions: 1,
+            max_values: 1,
+            ttl: Duration::ZERO,
+            ..Default::default()
+        });
+        store.start_session(&session("a"));
+        store.put(&session("a"), key(0), 0).unwrap();
+        store.put(&session("b"), key(0), 0).unwrap();
+        std::thread::sleep(Duration::from_millis(1));
+
+        // the session not started is evicted to make room for another one
+        store.put(&session("c"), key(0), 0).unwrap();
+        let outstanding = store.outstanding_cells();
+        assert!(!outstanding.contains_key(&session("b")));
+        assert_eq!(outstanding[&session("a")], 1);
+        assert_eq!(outstanding[&session("c")], 1);
+
+        // but not again right away, even though that session has also expired
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(store.put(&session("d"), key(0), 0).is_err());
+    }
+
+    #[test]
+    fn test_byte_limit() {
+        let store = RendezvousStore::default();
+        store.set_limits(BufferLimits {
+            max_bytes: 10,
+            ..Default::default()
+        });
+
+        store.put(&session("a"), key(0), vec![0u8; 6]).unwrap();
+        assert!(store.put(&session("b"), key(0), vec![0; 6]).is_err());
+        // the limit applies to all sessions together
+        let values = vec![
+            (session("b"), key(0), vec![0; 2]),
+            (session("c"), key(0), vec![0; 3]),
+        ];
+        assert!(store.put_all(values).is_err());
+        store.put(&session("b"), key(0), vec![0; 4]).unwrap();
+
+        // values of started sessions no longer count
+        store.start_session(&session("a"));
+        store.put(&session("a"), key(1), vec![0; 6]).unwrap();
+        store.put(&session("c"), key(0), vec![0; 6]).unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_evict_periodically() {
+        let store = Arc::new(RendezvousStore::default());
+        store.set_limits(BufferLimits {
+            ttl: Duration::from_secs(10),
+            ..Default::default()
+        });
+        store.spawn_eviction();
+        store.put(&session("a"), key(0), 0).unwrap();
+
+        sleep(Duration::from_secs(5)).await;
+        assert_eq!(store.outstanding_cells()[&session("a")], 1);
+        // evicted without any other values arriving
+        sleep(Duration::from_secs(20)).await;
+        assert!(store.outstanding_cells().is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+//! Networking that simulates network conditions on top of another implementation.
+
+use super::*;
+use parking_lot::Mutex;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+// tokio's clock so that simulated delays follow paused time in tests
+use tokio::time::Instant;
+
+/// Conditions of a link between two parties.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkConditions {
+    /// One-way delay added to every message.
+    pub latency: Duration,
+    /// Additional delay drawn uniformly between zero and this value for every message.
+    pub jitter: Duration,
+    /// Bandwidth in bits per second; unlimited if `None`.
+    pub bandwidth: Option<u64>,
+    /// Probability that a message is lost and has to be retransmitted.
+    pub drop_rate: f64,
+    /// Time before a lost message is retransmitted.
+    pub retransmission_timeout: Duration,
+    /// Allow messages to overtake each other due to jitter.
+    ///
+    /// Messages on a link are otherwise delivered in the order they were sent, as with TCP.
+    pub reorder: bool,
+}
+
+/// Network conditions for all links.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkConditions {
+    /// Conditions of links not found in `links`.
+    pub default: LinkConditions,
+    /// Conditions of specific links, by sender and receiver.
+    pub links: HashMap<(Identity, Identity), LinkConditions>,
+}
+
+impl NetworkConditions {
+    pub fn link(&self, sender: &Identity, receiver: &Identity) -> &LinkConditions {
+        self.links
+            .get(&(sender.clone(), receiver.clone()))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Traffic on a link during simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkTraffic {
+    pub messages: usize,
+    /// Number of serialized bytes delivered.
+    pub bytes: u64,
+    /// Number of times a message was lost and retransmitted.
+    pub retransmissions: usize,
+    /// Sum of the simulated delays of all messages.
+    pub total_delay: Duration,
+}
+
+/// Summary of a simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimulationReport {
+    /// Time from the creation of the networking until the last message was delivered.
+    pub elapsed: Duration,
+    /// Traffic per (sender, receiver) pair.
+    pub links: HashMap<(Identity, Identity), LinkTraffic>,
+}
+
+impl std::fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Simulated network time: {:.3} ms",
+            self.elapsed.as_secs_f64() * 1000.0
+        )?;
+        let mut links: Vec<_> = self.links.iter().collect();
+        links.sort_by_key(|((sender, receiver), _)| (sender.0.clone(), receiver.0.clone()));
+        for ((sender, receiver), traffic) in links {
+            writeln!(
+                f,
+                "{} -> {}: {} messages, {} bytes, {} retransmissions, {:.3} ms total delay",
+                sender,
+                receiver,
+                traffic.messages,
+                traffic.bytes,
+                traffic.retransmissions,
+                traffic.total_delay.as_secs_f64() * 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A message that has been sent but not yet received.
+struct InFlight {
+    receiver: Identity,
+    sent_at: Instant,
+    bytes: u64,
+}
+
+#[derive(Default)]
+struct LinkState {
+    traffic: LinkTraffic,
+    /// Time at which the link has finished transmitting previous messages.
+    busy_until: Option<Instant>,
+    /// Arrival time of the latest message, used to keep messages in order.
+    last_arrival: Option<Instant>,
+}
+
+#[derive(Default)]
+struct SimulationState {
+    in_flight: HashMap<(SessionId, RendezvousKey), InFlight>,
+    links: HashMap<(Identity, Identity), LinkState>,
+    last_arrival: Option<Instant>,
+}
+
+/// Networking that delays messages according to simulated network conditions.
+///
+/// Values are passed on to the wrapped networking right away, and receivers wait until the
+/// simulated arrival time before returning them. Since senders are only known when values
+/// are received, transmissions over a link are queued in the order in which they are
+/// received rather than sent.
+pub struct SimulatedAsyncNetworking<N> {
+    inner: N,
+    conditions: NetworkConditions,
+    epoch: Instant,
+    state: Mutex<SimulationState>,
+}
+
+impl<N> SimulatedAsyncNetworking<N> {
+    pub fn new(inner: N, conditions: NetworkConditions) -> Self {
+        SimulatedAsyncNetworking {
+            inner,
+            conditions,
+            epoch: Instant::now(),
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the traffic simulated so far.
+    pub fn report(&self) -> SimulationReport {
+        let state = self.state.lock();
+        SimulationReport {
+            elapsed: state
+                .last_arrival
+                .map(|arrival| arrival.saturating_duration_since(self.epoch))
+                .unwrap_or_default(),
+            links: state
+                .links
+                .iter()
+                .map(|(link, link_state)| (link.clone(), link_state.traffic.clone()))
+                .collect(),
+        }
+    }
+
+    /// Computes when a message arrives and records it as delivered.
+    fn arrival(&self, sender: &Identity, message: InFlight) -> Instant {
+        let conditions = self.conditions.link(sender, &message.receiver);
+        let mut rng = rand::thread_rng();
+        let mut state = self.state.lock();
+        let link = state
+            .links
+            .entry((sender.clone(), message.receiver.clone()))
+            .or_default();
+
+        let transmission = match conditions.bandwidth {
+            Some(bandwidth) => {
+                Duration::from_secs_f64(message.bytes as f64 * 8.0 / bandwidth as f64)
+            }
+            None => Duration::ZERO,
+        };
+        let start = match link.busy_until {
+            Some(busy_until) if busy_until > message.sent_at => busy_until,
+            _ => message.sent_at,
+        };
+        link.busy_until = Some(start + transmission);
+
+        let mut arrival = start + transmission + conditions.latency;
+        if conditions.jitter > Duration::ZERO {
+            arrival += conditions.jitter.mul_f64(rng.gen_range(0.0..1.0));
+        }
+        if conditions.drop_rate > 0.0 {
+            while rng.gen_bool(conditions.drop_rate.min(0.99)) {
+                arrival += conditions.retransmission_timeout;
+                link.traffic.retransmissions += 1;
+            }
+        }
+        if !conditions.reorder {
+            if let Some(last_arrival) = link.last_arrival {
+                arrival = arrival.max(last_arrival);
+            }
+            link.last_arrival = Some(arrival);
+        }
+
+        link.traffic.messages += 1;
+        link.traffic.bytes += message.bytes;
+        link.traffic.total_delay += arrival - message.sent_at;
+        state.last_arrival = Some(state.last_arrival.map_or(arrival, |last| last.max(arrival)));
+        arrival
+    }
+}
+
+#[async_trait]
+impl<N> AsyncNetworking for SimulatedAsyncNetworking<N>
+where
+    N: AsyncNetworking + Send + Sync,
+{
+    async fn send(
+        &self,
+        val: &Value,
+        receiver: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<()> {
+        let bytes = bincode::serialized_size(val)
+            .map_err(|e| Error::Networking(format!("failed to serialize value: {}", e)))?;
+        self.state.lock().in_flight.insert(
+            (session_id.clone(), rendezvous_key.clone()),
+            InFlight {
+                receiver: receiver.clone(),
+                sent_at: Instant::now(),
+                bytes,
+            },
+        );
+        self.inner
+            .send(val, receiver, rendezvous_key, session_id)
+            .await
+    }
+
+    async fn receive(
+        &self,
+        sender: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<Value> {
+        let value = self
+            .inner
+            .receive(sender, rendezvous_key, session_id)
+            .await?;
+        let message = self
+            .state
+            .lock()
+            .in_flight
+            .remove(&(session_id.clone(), rendezvous_key.clone()))
+            .ok_or_else(|| {
+                Error::Networking(format!(
+                    "received '{}' in session {} without it being sent",
+                    rendezvous_key, session_id
+                ))
+            })?;
+        let arrival = self.arrival(sender, message);
+        tokio::time::sleep_until(arrival).await;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::HostPlacement;
+    use crate::networking::local::LocalAsyncNetworking;
+    use std::convert::TryInto;
+
+    fn unit() -> Value {
+        Value::HostUnit(Box::new(HostUnit(HostPlacement::from("alice"))))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_latency_and_bandwidth() {
+        let conditions = NetworkConditions {
+            default: LinkConditions {
+                latency: Duration::from_millis(20),
+                ..Default::default()
+            },
+            links: HashMap::from([(
+                (Identity::from("alice"), Identity::from("bob")),
+                LinkConditions {
+                    latency: Duration::from_millis(50),
+                    // one byte takes 10ms to transmit
+                    bandwidth: Some(800),
+                    ..Default::default()
+                },
+            )]),
+        };
+        let net = SimulatedAsyncNetworking::new(LocalAsyncNetworking::default(), conditions);
+        let alice = Identity::from("alice");
+        let bob = Identity::from("bob");
+        let session_id: SessionId = "12345".try_into().unwrap();
+        let rendezvous_key: RendezvousKey = "rdv".try_into().unwrap();
+
+        let bytes = bincode::serialized_size(&unit()).unwrap();
+        let start = Instant::now();
+        net.send(&unit(), &bob, &rendezvous_key, &session_id)
+            .await
+            .unwrap();
+        net.receive(&alice, &rendezvous_key, &session_id)
+            .await
+            .unwrap();
+        // time is paused, so it only advances by the simulated delay
+        let expected = Duration::from_millis(50 + 10 * bytes);
+        assert!(start.elapsed() >= expected);
+        assert!(start.elapsed() < expected + Duration::from_millis(1));
+
+        let report = net.report();
+        let traffic = &report.links[&(alice, bob)];
+        assert_eq!(traffic.messages, 1);
+        assert_eq!(traffic.bytes, bytes);
+        assert!(traffic.total_delay >= expected);
+        assert!(report.elapsed >= expected);
+    }
+
+    #[test]
+    fn test_messages_stay_in_order() {
+        let link = LinkConditions {
+            latency: Duration::from_millis(1),
+            jitter: Duration::from_millis(20),
+            ..Default::default()
+        };
+        let conditions = NetworkConditions {
+            default: link.clone(),
+            links: HashMap::from([(
+                (Identity::from("alice"), Identity::from("carole")),
+                LinkConditions {
+                    reorder: true,
+                    ..link
+                },
+            )]),
+        };
+        let net = SimulatedAsyncNetworking::new(LocalAsyncNetworking::default(), conditions);
+        let alice = Identity::from("alice");
+        let sent_at = Instant::now();
+        let message = |receiver: &str| InFlight {
+            receiver: Identity::from(receiver),
+            sent_at,
+            bytes: 8,
+        };
+
+        let arrivals: Vec<Instant> = (0..20)
+            .map(|_| net.arrival(&alice, message("bob")))
+            .collect();
+        assert!(arrivals.windows(2).all(|w| w[0] <= w[1]));
+
+        let arrivals: Vec<Instant> = (0..20)
+            .map(|_| net.arrival(&alice, message("carole")))
+            .collect();
+        assert!(arrivals
+            .iter()
+            .all(|arrival| *arrival >= sent_at + Duration::from_millis(1)));
+        assert_eq!(
+            net.report().links[&(alice, Identity::from("bob"))].messages,
+            20
+        );
+    }
+}
@@ -3,28 +3,234 @@
 use crate::{
     computation::{RendezvousKey, SessionId, Value},
     execution::Identity,
-    networking::AsyncNetworking,
+    networking::{
+        codec::{CodecSupport, WireCodec},
+        constants,
+        rendezvous::{BufferLimits, RendezvousStore, SessionNetworking},
+        AsyncNetworking,
+    },
     Error, Result,
 };
 use async_trait::async_trait;
+use backoff::backoff::Backoff;
+use backoff::future::retry;
+use backoff::ExponentialBackoff;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::convert::TryInto;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::convert::{TryFrom, TryInto};
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::io::AsyncReadExt;
 use tokio::io::AsyncWriteExt;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream, UnixStream};
 use tokio::sync::mpsc;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tokio_rustls::rustls::{self, Certificate, PrivateKey, RootCertStore, ServerName};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
 
-type StoreType =
-    Arc<dashmap::DashMap<(SessionId, RendezvousKey), Arc<async_cell::sync::AsyncCell<Value>>>>;
+/// Largest frame accepted from a peer; the size is checked before reading the frame.
+const MAX_FRAME_SIZE: u64 = 1 << 30;
+
+/// Largest hello frame accepted, which is read before the sender is known.
+const MAX_HELLO_SIZE: u64 = 4 << 10;
+
+/// Incoming values, still encoded, along with the identity of the connection they came over.
+pub(super) type StoreType = Arc<RendezvousStore<(Identity, Vec<u8>)>>;
+
+/// Encodings that a peer announced it supports, as last heard over a connection to it.
+type PeerCodecs = Arc<RwLock<CodecSupport>>;
+
+/// Sequence numbers stored so far for each sender and epoch.
+pub(super) type DeliveredType = Arc<dashmap::DashMap<(Identity, u64), Delivered>>;
+
+/// Number of epochs per sender for which duplicates are recognised.
+///
+/// A sender starts a new epoch every time it starts up, so older epochs are only needed while
+/// an earlier instance of the sender may still be retransmitting.
+const MAX_EPOCHS_PER_SENDER: usize = 16;
+
+/// Number of values stored ahead of one that has not been stored, before giving up on it.
+///
+/// A rejected value is retried by its sender, while later values may be stored in the
+/// meantime. Once this many have been, the sender is assumed to have given up on it.
+const MAX_STORED_OUT_OF_ORDER: usize = 1 << 16;
+
+/// Sequence numbers stored from a single epoch of a sender.
+#[derive(Debug)]
+pub(super) struct Delivered {
+    /// Every sequence number up to and including this one has been stored.
+    up_to: u64,
+    /// Stored sequence numbers above `up_to`, following values that were rejected.
+    above: BTreeSet<u64>,
+    last_active: Instant,
+}
+
+impl Delivered {
+    pub(super) fn new() -> Self {
+        Delivered {
+            up_to: 0,
+            above: BTreeSet::new(),
+            last_active: Instant::now(),
+        }
+    }
+
+    pub(super) fn contains(&self, seq: u64) -> bool {
+        seq <= self.up_to || self.above.contains(&seq)
+    }
+
+    pub(super) fn insert(&mut self, seq: u64) {
+        if seq > self.up_to {
+            self.above.insert(seq);
+        }
+        if self.above.len() > MAX_STORED_OUT_OF_ORDER {
+            // skip the earliest gap
+            if let Some(first) = self.above.iter().next().copied() {
+                self.up_to = first - 1;
+            }
+        }
+        while self.above.remove(&(self.up_to + 1)) {
+            self.up_to += 1;
+        }
+        self.last_active = Instant::now();
+    }
+}
 
 type SendChannelsType = HashMap<Identity, mpsc::Sender<(SendData, mpsc::Sender<()>)>>;
 pub struct TcpStreamNetworking {
     own_name: String,
-    store: StoreType,                // store incoming data
+    pub(super) store: StoreType,     // store incoming data
     send_channels: SendChannelsType, // send data over each stream
+    peer_codecs: HashMap<Identity, PeerCodecs>,
+    codec: WireCodec,
+}
+
+/// TLS configuration for `TcpStreamNetworking`.
+///
+/// Both ends of a connection must present a certificate signed by the common CA, and the
+/// common name of a peer's certificate is taken as its identity.
+#[derive(Clone)]
+pub struct TcpStreamTlsConfig {
+    client: Arc<rustls::ClientConfig>,
+    server: Arc<rustls::ServerConfig>,
+}
+
+impl TcpStreamTlsConfig {
+    /// Create configuration from a PEM-encoded certificate, its private key, and the CA certificate.
+    pub fn from_pem(cert: &[u8], key: &[u8], ca_cert: &[u8]) -> Result<TcpStreamTlsConfig> {
+        let (client, server) = rustls_configs(cert, key, ca_cert)?;
+        Ok(TcpStreamTlsConfig {
+            client: Arc::new(client),
+            server: Arc::new(server),
+        })
+    }
+}
+
+/// Build client and server configurations that both require a certificate signed by the CA.
+pub(super) fn rustls_configs(
+    cert: &[u8],
+    key: &[u8],
+    ca_cert: &[u8],
+) -> Result<(rustls::ClientConfig, rustls::ServerConfig)> {
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut &*cert)
+        .map_err(|e| Error::Networking(format!("failed to parse certificate: {}", e)))?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    let key = parse_private_key(key)?;
+
+    let mut roots = RootCertStore::empty();
+    let ca_certs = rustls_pemfile::certs(&mut &*ca_cert)
+        .map_err(|e| Error::Networking(format!("failed to parse CA certificate: {}", e)))?;
+    for ca_cert in ca_certs {
+        roots
+            .add(&Certificate(ca_cert))
+            .map_err(|e| Error::Networking(format!("invalid CA certificate: {}", e)))?;
+    }
+
+    let server = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(rustls::server::AllowAnyAuthenticatedClient::new(
+            roots.clone(),
+        ))
+        .with_single_cert(certs.clone(), key.clone())
+        .map_err(|e| Error::Networking(format!("invalid server TLS configuration: {}", e)))?;
+
+    let client = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_single_cert(certs, key)
+        .map_err(|e| Error::Networking(format!("invalid client TLS configuration: {}", e)))?;
+
+    Ok((client, server))
+}
+
+fn parse_private_key(key: &[u8]) -> Result<PrivateKey> {
+    let mut reader = key;
+    loop {
+        match rustls_pemfile::read_one(&mut reader)
+            .map_err(|e| Error::Networking(format!("failed to parse private key: {}", e)))?
+        {
+            Some(rustls_pemfile::Item::PKCS8Key(key))
+            | Some(rustls_pemfile::Item::RSAKey(key))
+            | Some(rustls_pemfile::Item::ECKey(key)) => return Ok(PrivateKey(key)),
+            Some(_) => continue,
+            None => {
+                return Err(Error::Networking(
+                    "no private key found in PEM data".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// Extract the identity of a peer from the certificate it presented.
+pub(super) fn peer_identity(certs: Option<&[Certificate]>) -> Result<Identity> {
+    match certs {
+        Some([cert, ..]) => crate::grpc::certificate_common_name(&cert.0)
+            .map(Identity::from)
+            .map_err(Error::Networking),
+        _ => Err(Error::Networking(
+            "peer did not present a certificate".to_string(),
+        )),
+    }
+}
+
+async fn accept_tls(
+    acceptor: &TlsAcceptor,
+    stream: TcpStream,
+) -> Result<(tokio_rustls::server::TlsStream<TcpStream>, Identity)> {
+    let stream = acceptor
+        .accept(stream)
+        .await
+        .map_err(|e| Error::Networking(format!("TLS handshake failed: {}", e)))?;
+    let peer = peer_identity(stream.get_ref().1.peer_certificates())?;
+    Ok((stream, peer))
+}
+
+async fn connect_tls(
+    connector: &TlsConnector,
+    stream: TcpStream,
+    address: &str,
+    expected_peer: &Identity,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    // the certificate is checked against the host part of the address, as done by gRPC
+    let host = address.rsplit_once(':').map_or(address, |(host, _)| host);
+    let server_name = ServerName::try_from(host)
+        .map_err(|e| Error::Networking(format!("invalid server name {}: {}", host, e)))?;
+    let stream = connector
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| Error::Networking(format!("TLS handshake with {} failed: {}", address, e)))?;
+    let peer = peer_identity(stream.get_ref().1.peer_certificates())?;
+    if &peer != expected_peer {
+        return Err(Error::Networking(format!(
+            "expected {} at {} but certificate identifies {}",
+            expected_peer, address, peer
+        )));
+    }
+    Ok(stream)
 }
 
 fn u64_to_little_endian(n: u64, buf: &mut [u8; 8]) {
@@ -43,68 +249,48 @@ fn little_endian_to_u64(buf: &[u8; 8]) -> u64 {
     n
 }
 
-async fn handle_connection(mut stream: TcpStream, store: StoreType) -> Result<()> {
-    loop {
-        // read moose data
-        let mut buf: [u8; 8] = [0; 8];
-        tracing::debug!("reading size buf");
-        let size = match stream.read_exact(&mut buf).await {
-            Ok(_) => little_endian_to_u64(&buf),
-            Err(_) => {
-                tracing::debug!("client hung up");
-                return Ok(()); // when client hangs up
-            }
-        };
-        let mut vec: Vec<u8> = vec![0; size as usize];
-
-        tracing::debug!("reading exact: {}", size);
-        stream.read_exact(&mut vec).await.map_err(|e| {
-            Error::Networking(format!("failed to read data from TCP stream: {}", e))
-        })?;
-        let data: SendData = bincode::deserialize(&vec)
-            .map_err(|e| Error::Networking(format!("failed to deserialize moose value: {}", e)))?;
-
-        // put value into store
-        let key = (data.session_id, data.rendezvous_key);
-        tracing::debug!("storing key: {:?}", key);
-        let cell = store
-            .entry(key.clone())
-            .or_insert_with(async_cell::sync::AsyncCell::shared)
-            .value()
-            .clone();
-
-        cell.set(data.value);
-        tracing::debug!("stored key: {:?}", key);
-    }
-}
-
-async fn server(listener: TcpListener, store: StoreType) -> Result<()> {
-    loop {
-        // TODO: retry logic?
-        tracing::debug!("listening");
-        let (stream, addr) = listener
-            .accept()
-            .await
-            .map_err(|e| Error::Networking(format!("failed to accept connection: {}", e)))?;
-        tracing::debug!("accepted connection: {}", addr);
-        let shared_store = Arc::clone(&store);
-        tokio::spawn(async move {
-            handle_connection(stream, shared_store).await.unwrap();
-        });
-    }
+/// Frames exchanged over a connection.
+///
+/// The connecting party starts with a `Hello`, to which the receiving party answers with the
+/// `Codecs` it supports, and then sends `Data` frames, each of which is acknowledged by the
+/// receiving party once the value has been stored, or rejected if it could not be, in which
+/// case the sender retries it later. Sequence numbers are counted per sender and epoch, and
+/// allow frames retransmitted after a reconnect to be recognised as duplicates.
+#[derive(Serialize, Deserialize, Debug)]
+enum Frame {
+    Hello { sender: Identity, epoch: u64 },
+    Data { seq: u64, data: SendData },
+    Ack { seq: u64 },
+    Reject { seq: u64 },
+    Codecs { supported: CodecSupport },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct SendData {
-    value: Value,
+    /// Value encoded with a `WireCodec`.
+    value: Vec<u8>,
+    sender: Identity,
     receiver: Identity,
     rendezvous_key: RendezvousKey,
     session_id: SessionId,
 }
 
-async fn send_value(stream: &mut TcpStream, send_data: &SendData) -> Result<()> {
-    let raw_data: Vec<u8> = bincode::serialize(send_data)
-        .map_err(|e| Error::Networking(format!("could not serialze send_data: {}", e)))?;
+fn serialize_frame(frame: &Frame) -> Result<Vec<u8>> {
+    bincode::serialize(frame)
+        .map_err(|e| Error::Networking(format!("could not serialize frame: {}", e)))
+}
+
+async fn write_frame<S>(stream: &mut S, frame: &Frame) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    write_raw_frame(stream, &serialize_frame(frame)?).await
+}
+
+async fn write_raw_frame<S>(stream: &mut S, raw_data: &[u8]) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
     let data_size = raw_data.len();
     let mut size_data_buf = [0; 8];
     u64_to_little_endian(
@@ -114,12 +300,11 @@ async fn send_value(stream: &mut TcpStream, send_data: &SendData) -> Result<()>
         &mut size_data_buf,
     );
 
-    // TODO: write error, re-establish connection?
     stream.write_all(&size_data_buf).await.map_err(|e| {
         Error::Networking(format!("could not write data size over TCP stream: {}", e))
     })?;
     stream
-        .write_all(&raw_data)
+        .write_all(raw_data)
         .await
         .map_err(|e| Error::Networking(format!("could not write data over TCP stream: {}", e)))?;
     stream
@@ -129,29 +314,464 @@ async fn send_value(stream: &mut TcpStream, send_data: &SendData) -> Result<()>
     Ok(())
 }
 
-async fn send_loop(
-    mut stream: TcpStream,
-    mut rx: mpsc::Receiver<(SendData, mpsc::Sender<()>)>,
-) -> Result<()> {
+/// Read the next frame, returning `None` if the other end closed the connection.
+async fn read_frame<S>(stream: &mut S, max_size: u64) -> Result<Option<Frame>>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut buf: [u8; 8] = [0; 8];
+    match stream.read_exact(&mut buf).await {
+        Ok(_) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => {
+            return Err(Error::Networking(format!(
+                "failed to read data size from TCP stream: {}",
+                e
+            )))
+        }
+    }
+    let size = little_endian_to_u64(&buf);
+    if size > max_size {
+        return Err(Error::Networking(format!(
+            "frame of {} bytes exceeds the maximum size of {} bytes",
+            size, max_size
+        )));
+    }
+
+    // the buffer grows as data arrives, rather than trusting the announced size
+    tracing::debug!("reading exact: {}", size);
+    let mut vec: Vec<u8> = Vec::new();
+    stream
+        .take(size)
+        .read_to_end(&mut vec)
+        .await
+        .map_err(|e| Error::Networking(format!("failed to read data from TCP stream: {}", e)))?;
+    if vec.len() as u64 != size {
+        return Err(Error::Networking(
+            "TCP stream ended in the middle of a frame".to_string(),
+        ));
+    }
+    bincode::deserialize(&vec)
+        .map(Some)
+        .map_err(|e| Error::Networking(format!("failed to deserialize frame: {}", e)))
+}
+
+/// Read values from a connection into the store, acknowledging each of them.
+///
+/// Values that were already stored under the same sequence number are acknowledged but
+/// otherwise ignored, so that retransmissions are idempotent. Values that cannot be stored
+/// are rejected one by one, without closing the connection, and are stored if sent again
+/// later under the same sequence number. If the peer has been
+/// authenticated then a connection claiming to come from any other sender is rejected.
+pub(super) async fn handle_connection<S>(
+    mut stream: S,
+    store: StoreType,
+    delivered: DeliveredType,
+    peer: Option<Identity>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (sender, epoch) = match read_frame(&mut stream, MAX_HELLO_SIZE).await? {
+        Some(Frame::Hello { sender, epoch }) => (sender, epoch),
+        Some(_) => {
+            return Err(Error::Networking(
+                "connection did not start with a hello frame".to_string(),
+            ))
+        }
+        None => return Ok(()),
+    };
+    if let Some(peer) = &peer {
+        if &sender != peer {
+            return Err(Error::Networking(format!(
+                "rejected connection from {} claiming to be {}",
+                peer, sender
+            )));
+        }
+    }
+    register_epoch(&delivered, &sender, epoch);
+    let codecs = Frame::Codecs {
+        supported: CodecSupport::all(),
+    };
+    write_frame(&mut stream, &codecs).await?;
+
     loop {
-        match rx.recv().await {
-            Some((data, finished_send_signal)) => {
-                send_value(&mut stream, &data).await.map_err(|e| {
-                    Error::Networking(format!("could not send value to other worker: {}", e))
-                })?;
-                finished_send_signal.send(()).await.map_err(|e| {
-                    Error::Networking(format!(
-                        "could not transmit the finished sending ack over mpsc channel: {}",
-                        e
-                    ))
-                })?;
+        let (seq, data) = match read_frame(&mut stream, MAX_FRAME_SIZE).await? {
+            Some(Frame::Data { seq, data }) => (seq, data),
+            Some(_) => {
+                return Err(Error::Networking(format!(
+                    "unexpected frame from {}",
+                    sender
+                )))
             }
             None => {
-                stream.shutdown().await.map_err(|e| {
-                    Error::Networking(format!("failed to shutdown TCP stream: {}", e))
-                })?;
+                tracing::debug!("client hung up");
+                return Ok(());
+            }
+        };
+        if data.sender != sender {
+            return Err(Error::Networking(format!(
+                "rejected value from {} claiming to be sent by {}",
+                sender, data.sender
+            )));
+        }
+
+        let duplicate = delivered
+            .get(&(sender.clone(), epoch))
+            .map_or(false, |entry| entry.contains(seq));
+
+        let reply = if !duplicate {
+            tracing::debug!(
+                "storing key: {:?}",
+                (&data.session_id, &data.rendezvous_key)
+            );
+            let value = (sender.clone(), data.value);
+            match store.put(&data.session_id, data.rendezvous_key, value) {
+                Ok(()) => {
+                    delivered
+                        .entry((sender.clone(), epoch))
+                        .or_insert_with(Delivered::new)
+                        .insert(seq);
+                    Frame::Ack { seq }
+                }
+                Err(e) => {
+                    // the value is not marked as delivered, so that it is stored once its
+                    // sender retries it
+                    tracing::debug!("rejected value {} from {}: {}", seq, sender, e);
+                    Frame::Reject { seq }
+                }
+            }
+        } else {
+            tracing::debug!("ignoring duplicate frame {} from {}", seq, sender);
+            Frame::Ack { seq }
+        };
+
+        write_frame(&mut stream, &reply).await?;
+    }
+}
+
+/// Start tracking the epoch of a sender, forgetting its least recently active epochs beyond
+/// `MAX_EPOCHS_PER_SENDER`.
+pub(super) fn register_epoch(delivered: &DeliveredType, sender: &Identity, epoch: u64) {
+    delivered
+        .entry((sender.clone(), epoch))
+        .or_insert_with(Delivered::new)
+        .last_active = Instant::now();
+    let mut epochs: Vec<(u64, Instant)> = delivered
+        .iter()
+        .filter(|entry| &entry.key().0 == sender && entry.key().1 != epoch)
+        .map(|entry| (entry.key().1, entry.value().last_active))
+        .collect();
+    // the new epoch is always kept
+    if epochs.len() >= MAX_EPOCHS_PER_SENDER {
+        epochs.sort_by_key(|(_, last_active)| *last_active);
+        for (old_epoch, _) in &epochs[..=epochs.len() - MAX_EPOCHS_PER_SENDER] {
+            tracing::debug!("forgetting epoch {} of {}", old_epoch, sender);
+            delivered.remove(&(sender.clone(), *old_epoch));
+        }
+    }
+}
+
+async fn server(
+    listener: TcpListener,
+    store: StoreType,
+    delivered: DeliveredType,
+    tls: Option<TlsAcceptor>,
+) {
+    loop {
+        tracing::debug!("listening");
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // errors such as running out of file descriptors are usually temporary
+                tracing::warn!("failed to accept connection: {}", e);
+                sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        tracing::debug!("accepted connection: {}", addr);
+        let shared_store = Arc::clone(&store);
+        let delivered = Arc::clone(&delivered);
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let res = match tls {
+                None => handle_connection(stream, shared_store, delivered, None).await,
+                Some(acceptor) => match accept_tls(&acceptor, stream).await {
+                    Ok((stream, peer)) => {
+                        tracing::debug!("authenticated connection from {} as {}", addr, peer);
+                        handle_connection(stream, shared_store, delivered, Some(peer)).await
+                    }
+                    Err(e) => Err(e),
+                },
+            };
+            if let Err(e) = res {
+                tracing::error!("closed connection from {}: {}", addr, e);
+            }
+        });
+    }
+}
+
+/// Connection to another worker, either plain or over TLS.
+pub(super) trait AsyncStream: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<S: AsyncRead + AsyncWrite + Send + Unpin> AsyncStream for S {}
+
+/// Address at which a worker accepts connections.
+#[derive(Clone, Debug)]
+pub(super) enum PeerAddress {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PeerAddress::Tcp(address) => write!(f, "{}", address),
+            PeerAddress::Unix(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Worker to which values are sent.
+#[derive(Clone)]
+pub(super) struct Peer {
+    pub(super) identity: Identity,
+    pub(super) address: PeerAddress,
+    /// TLS is only used over TCP.
+    pub(super) connector: Option<TlsConnector>,
+}
+
+/// Policy for retrying connections and rejected values.
+fn retry_backoff() -> ExponentialBackoff {
+    ExponentialBackoff {
+        max_elapsed_time: *constants::MAX_ELAPSED_TIME,
+        max_interval: *constants::MAX_INTERVAL,
+        multiplier: constants::MULTIPLIER,
+        ..Default::default()
+    }
+}
+
+impl Peer {
+    /// Connect to the peer, retrying with exponential backoff.
+    pub(super) async fn connect(&self) -> Result<Box<dyn AsyncStream>> {
+        retry(retry_backoff(), || async {
+            let connect_error = |e| {
+                Error::Networking(format!(
+                    "could not connect to {} at {}: {}",
+                    self.identity, self.address, e
+                ))
+            };
+            let address = match &self.address {
+                PeerAddress::Tcp(address) => address,
+                PeerAddress::Unix(path) => {
+                    let stream = UnixStream::connect(path).await.map_err(connect_error)?;
+                    return Ok(Box::new(stream) as Box<dyn AsyncStream>);
+                }
+            };
+            let stream = TcpStream::connect(address).await.map_err(connect_error)?;
+            match &self.connector {
+                None => Ok(Box::new(stream) as Box<dyn AsyncStream>),
+                Some(connector) => {
+                    // a peer presenting the wrong certificate will not fix itself
+                    let stream = connect_tls(connector, stream, address, &self.identity)
+                        .await
+                        .map_err(backoff::Error::permanent)?;
+                    Ok(Box::new(stream) as Box<dyn AsyncStream>)
+                }
+            }
+        })
+        .await
+    }
+}
+
+type FinishedSendSignal = mpsc::Sender<()>;
+
+/// Value that has been written but not yet stored by the peer.
+struct Unacked {
+    seq: u64,
+    /// Serialized data frame.
+    raw_frame: Vec<u8>,
+    finished_send_signal: FinishedSendSignal,
+    /// Backoff for retrying the value, once it has been rejected.
+    backoff: Option<ExponentialBackoff>,
+}
+
+/// Sending state that survives reconnects.
+struct SendState {
+    hello: Frame,
+    next_seq: u64,
+    /// Values that have been written but not yet acknowledged, in the order written.
+    unacked: VecDeque<Unacked>,
+    /// Values rejected by the peer, with the time at which to write them again.
+    rejected: Vec<(Instant, Unacked)>,
+    /// Whether all senders have gone away.
+    closed: bool,
+}
+
+/// Send values to a peer until all senders have gone away.
+///
+/// If the connection fails then a new one is established and all unacknowledged values are
+/// sent again; the receiving end drops those it has already stored. Values the peer could
+/// not store are sent again with exponential backoff, and their sends fail once it runs out.
+/// The codecs that the peer announces on each connection are kept in `codecs`.
+async fn send_loop(
+    own_identity: Identity,
+    peer: Peer,
+    mut stream: Box<dyn AsyncStream>,
+    mut rx: mpsc::Receiver<(SendData, FinishedSendSignal)>,
+    codecs: PeerCodecs,
+) -> Result<()> {
+    let mut state = SendState {
+        hello: Frame::Hello {
+            sender: own_identity,
+            epoch: rand::random(),
+        },
+        next_seq: 1,
+        unacked: VecDeque::new(),
+        rejected: Vec::new(),
+        closed: false,
+    };
+    loop {
+        match run_connection(stream, &mut rx, &mut state, &codecs).await {
+            Ok(()) => return Ok(()),
+            Err(e) => tracing::warn!(
+                "connection to {} failed, reconnecting: {}",
+                peer.identity,
+                e
+            ),
+        }
+        stream = peer.connect().await?;
+        tracing::debug!(
+            "reconnected to {}, resending {} values",
+            peer.identity,
+            state.unacked.len()
+        );
+    }
+}
+
+async fn run_connection(
+    stream: Box<dyn AsyncStream>,
+    rx: &mut mpsc::Receiver<(SendData, FinishedSendSignal)>,
+    state: &mut SendState,
+    codecs: &PeerCodecs,
+) -> Result<()> {
+    let (reader, mut writer) = tokio::io::split(stream);
+    // acks are read in a separate task since reading a frame cannot be cancelled midway
+    let (ack_tx, mut acks) = mpsc::unbounded_channel();
+    let ack_reader = tokio::spawn(read_acks(reader, ack_tx, Arc::clone(codecs)));
+    let res: Result<()> = async {
+        write_frame(&mut writer, &state.hello).await?;
+        for unacked in state.unacked.iter() {
+            write_raw_frame(&mut writer, &unacked.raw_frame).await?;
+        }
+
+        loop {
+            if state.closed && state.unacked.is_empty() && state.rejected.is_empty() {
+                if let Err(e) = writer.shutdown().await {
+                    tracing::debug!("failed to shutdown TCP stream: {}", e);
+                }
                 return Ok(());
             }
+            let next_retry = state.rejected.iter().map(|(at, _)| *at).min();
+            let retry_at = next_retry.unwrap_or_else(Instant::now);
+            tokio::select! {
+                item = rx.recv(), if !state.closed => match item {
+                    Some((data, finished_send_signal)) => {
+                        let seq = state.next_seq;
+                        let raw_frame = match serialize_frame(&Frame::Data { seq, data }) {
+                            Ok(raw_frame) => raw_frame,
+                            Err(e) => {
+                                // dropping the signal fails the send
+                                tracing::error!("{}", e);
+                                continue;
+                            }
+                        };
+                        state.next_seq += 1;
+                        // kept before writing so that it is resent if the write fails
+                        state.unacked.push_back(Unacked {
+                            seq,
+                            raw_frame,
+                            finished_send_signal,
+                            backoff: None,
+                        });
+                        let unacked = state.unacked.back().unwrap();
+                        write_raw_frame(&mut writer, &unacked.raw_frame).await?;
+                    }
+                    None => state.closed = true,
+                },
+                _ = sleep_until(retry_at), if next_retry.is_some() => {
+                    let now = Instant::now();
+                    let (due, waiting): (Vec<_>, Vec<_>) =
+                        state.rejected.drain(..).partition(|(at, _)| *at <= now);
+                    state.rejected = waiting;
+                    for (_, unacked) in due {
+                        tracing::debug!("resending rejected value {}", unacked.seq);
+                        state.unacked.push_back(unacked);
+                        let unacked = state.unacked.back().unwrap();
+                        write_raw_frame(&mut writer, &unacked.raw_frame).await?;
+                    }
+                },
+                ack = acks.recv() => match ack {
+                    Some(Ok((seq, stored))) => {
+                        let position = state.unacked.iter().position(|u| u.seq == seq);
+                        let mut unacked = match position.and_then(|i| state.unacked.remove(i)) {
+                            Some(unacked) => unacked,
+                            None => continue,
+                        };
+                        if stored {
+                            // the sending side may have stopped waiting
+                            let _ = unacked.finished_send_signal.send(()).await;
+                            continue;
+                        }
+                        let backoff = unacked.backoff.get_or_insert_with(retry_backoff);
+                        match backoff.next_backoff() {
+                            Some(delay) => {
+                                state.rejected.push((Instant::now() + delay, unacked));
+                            }
+                            // dropping the signal fails the send
+                            None => tracing::warn!("giving up on rejected value {}", seq),
+                        }
+                    }
+                    Some(Err(e)) => return Err(e),
+                    None => {
+                        return Err(Error::Networking(
+                            "connection closed by peer".to_string(),
+                        ))
+                    }
+                },
+            }
+        }
+    }
+    .await;
+    ack_reader.abort();
+    res
+}
+
+/// Read replies to sent values, each being the sequence number and whether it was stored.
+///
+/// The codecs announced by the peer are recorded as they arrive.
+async fn read_acks<S>(
+    mut reader: S,
+    acks: mpsc::UnboundedSender<Result<(u64, bool)>>,
+    codecs: PeerCodecs,
+) where
+    S: AsyncRead + Unpin,
+{
+    loop {
+        let ack = match read_frame(&mut reader, MAX_FRAME_SIZE).await {
+            Ok(Some(Frame::Ack { seq })) => Ok((seq, true)),
+            Ok(Some(Frame::Reject { seq })) => Ok((seq, false)),
+            Ok(Some(Frame::Codecs { supported })) => {
+                *codecs.write() = supported;
+                continue;
+            }
+            Ok(Some(_)) => Err(Error::Networking("expected an ack frame".to_string())),
+            Ok(None) => return,
+            Err(e) => Err(e),
+        };
+        let failed = ack.is_err();
+        if acks.send(ack).is_err() || failed {
+            return;
         }
     }
 }
@@ -160,6 +780,26 @@ impl TcpStreamNetworking {
     pub async fn new(
         own_name: &str,
         hosts: HashMap<String, String>,
+    ) -> Result<TcpStreamNetworking> {
+        Self::connect(own_name, hosts, None).await
+    }
+
+    /// Same as `new` except that all connections use TLS with mutual authentication.
+    ///
+    /// Incoming connections are bound to the identity found in the peer's certificate,
+    /// and outgoing connections are only made to peers whose certificate matches their name.
+    pub async fn new_with_tls(
+        own_name: &str,
+        hosts: HashMap<String, String>,
+        tls_config: TcpStreamTlsConfig,
+    ) -> Result<TcpStreamNetworking> {
+        Self::connect(own_name, hosts, Some(tls_config)).await
+    }
+
+    async fn connect(
+        own_name: &str,
+        hosts: HashMap<String, String>,
+        tls_config: Option<TcpStreamTlsConfig>,
     ) -> Result<TcpStreamNetworking> {
         tracing::debug!("own name: {}", own_name);
         let store = StoreType::default();
@@ -174,48 +814,97 @@ impl TcpStreamNetworking {
             Error::Networking(format!("could not bind to address: {}: {}", own_address, e))
         })?;
         let shared_store = Arc::clone(&store);
-        tokio::spawn(async move {
-            server(listener, Arc::clone(&shared_store)).await.unwrap();
-        });
+        let acceptor = tls_config
+            .as_ref()
+            .map(|config| TlsAcceptor::from(Arc::clone(&config.server)));
+        tokio::spawn(server(
+            listener,
+            shared_store,
+            DeliveredType::default(),
+            acceptor,
+        ));
+        let connector = tls_config
+            .as_ref()
+            .map(|config| TlsConnector::from(Arc::clone(&config.client)));
 
-        // connect to every other server
-        let mut others: Vec<(String, String)> = hosts
-            .clone()
+        let peers = hosts
             .into_iter()
             .filter(|(placement, _)| *placement != own_name)
+            .map(|(placement, address)| Peer {
+                identity: Identity::from(placement),
+                address: PeerAddress::Tcp(address),
+                connector: connector.clone(),
+            })
             .collect();
-        others.sort();
-        tracing::debug!("others = {:?}", others);
+        Self::connect_peers(own_name, store, peers).await
+    }
+
+    /// Connect to every other worker, with incoming values put into the given store.
+    pub(super) async fn connect_peers(
+        own_name: String,
+        store: StoreType,
+        mut peers: Vec<Peer>,
+    ) -> Result<TcpStreamNetworking> {
+        store.spawn_eviction();
+        peers.sort_by(|a, b| a.identity.0.cmp(&b.identity.0));
         let mut send_channels = HashMap::new();
-        for (placement, address) in others.iter() {
-            tracing::debug!("trying: {} -> {}", placement, address);
-            loop {
-                let stream = match TcpStream::connect(address).await {
-                    Ok(s) => s,
-                    Err(_) => {
-                        sleep(Duration::from_secs(1)).await;
-                        continue;
-                    }
-                };
-                tracing::debug!("connected to: {} -> {}", placement, address);
-                let (tx, rx) = mpsc::channel(100);
-                let identity = Identity::from(placement);
-                send_channels.insert(identity, tx);
-
-                tokio::spawn(async move {
-                    send_loop(stream, rx).await.unwrap();
-                });
-                break;
-            }
+        let mut peer_codecs = HashMap::new();
+        for peer in peers.into_iter() {
+            tracing::debug!("trying: {} -> {}", peer.identity, peer.address);
+            let stream = peer.connect().await?;
+            tracing::debug!("connected to: {} -> {}", peer.identity, peer.address);
+
+            let (tx, rx) = mpsc::channel(100);
+            send_channels.insert(peer.identity.clone(), tx);
+            let codecs = PeerCodecs::default();
+            peer_codecs.insert(peer.identity.clone(), Arc::clone(&codecs));
+            let own_identity = Identity::from(&own_name);
+            tokio::spawn(async move {
+                let identity = peer.identity.clone();
+                if let Err(e) = send_loop(own_identity, peer, stream, rx, codecs).await {
+                    tracing::error!("stopped sending to {}: {}", identity, e);
+                }
+            });
         }
 
-        let store = Arc::clone(&store);
         Ok(TcpStreamNetworking {
             own_name,
             store,
             send_channels,
+            peer_codecs,
+            codec: WireCodec::default(),
         })
     }
+
+    /// Encode values with the given codec instead of plain bincode.
+    ///
+    /// Each peer announces the codecs it supports when a connection to it is made, and values
+    /// are only encoded with what it supports. Values sent before that fall back to plain
+    /// bincode. Values are decoded according to the codec the sender used, so parties may use
+    /// different codecs.
+    pub fn with_codec(self, codec: WireCodec) -> TcpStreamNetworking {
+        TcpStreamNetworking { codec, ..self }
+    }
+
+    /// Limit the values buffered for sessions that have not started locally.
+    pub fn with_buffer_limits(self, limits: BufferLimits) -> TcpStreamNetworking {
+        self.store.set_limits(limits);
+        self
+    }
+
+    /// Number of cells per session that are either waiting for or holding a value.
+    pub fn outstanding_cells(&self) -> HashMap<SessionId, usize> {
+        self.store.outstanding_cells()
+    }
+
+    /// Networking for a single session, whose values are dropped once it is dropped.
+    pub fn new_session(self: &Arc<Self>, session_id: SessionId) -> Arc<impl AsyncNetworking> {
+        Arc::new(SessionNetworking::new(
+            Arc::clone(self),
+            Arc::clone(&self.store),
+            session_id,
+        ))
+    }
 }
 
 #[async_trait]
@@ -235,8 +924,14 @@ impl AsyncNetworking for TcpStreamNetworking {
                 session_id, rendezvous_key, self.own_name, receiver
             ))
         })?;
+        let supported = self
+            .peer_codecs
+            .get(receiver)
+            .map(|codecs| *codecs.read())
+            .unwrap_or_default();
         let send_data = SendData {
-            value: value.clone(),
+            value: self.codec.negotiate(&supported).encode(value)?,
+            sender: Identity::from(&self.own_name),
             receiver: receiver.clone(),
             rendezvous_key: rendezvous_key.clone(),
             session_id: session_id.clone(),
@@ -250,9 +945,10 @@ impl AsyncNetworking for TcpStreamNetworking {
             ))
         })?;
         let ack = send_finished.recv().await.ok_or_else(|| {
-            Error::Networking(
-                "did not receive the finished sending ack from the send loop".to_string(),
-            )
+            Error::Networking(format!(
+                "in session {}, {} did not store rendezvous key {} from {}",
+                session_id, receiver, rendezvous_key, self.own_name
+            ))
         })?;
 
         Ok(ack)
@@ -264,29 +960,24 @@ impl AsyncNetworking for TcpStreamNetworking {
         rendezvous_key: &RendezvousKey,
         session_id: &SessionId,
     ) -> Result<Value> {
-        let key = (session_id.clone(), rendezvous_key.clone());
-        tracing::debug!("receiving key: {:?} from: {}", key, sender);
-
-        let cell = self
-            .store
-            .entry(key.clone())
-            .or_insert_with(async_cell::sync::AsyncCell::shared)
-            .value()
-            .clone();
-
-        tracing::debug!("awaiting receive key: {:?}", key);
-        let value = cell.take().await;
+        let key = (session_id, rendezvous_key);
+        tracing::debug!("awaiting receive key: {:?} from: {}", key, sender);
+        let (actual_sender, value) = self.store.take(session_id, rendezvous_key).await;
         tracing::debug!("got key: {:?}", key);
-        self.store.remove(&key).ok_or_else(|| {
-            Error::Networking(format!("failed to remove key: {:?} from store", key))
-        })?;
-        Ok(value)
+        if &actual_sender != sender {
+            return Err(Error::Networking(format!(
+                "expected rendezvous key {} from {} but it was sent by {}",
+                rendezvous_key, sender, actual_sender
+            )));
+        }
+        WireCodec::decode(&value)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::host::{HostPlacement, HostString};
 
     #[test]
     fn test_u64_to_little_endian() {
@@ -299,4 +990,589 @@ mod test {
         println!("{:x?}", test_int);
         assert_eq!(test_int, x);
     }
+
+    fn send_data(sender: &str) -> SendData {
+        SendData {
+            value: WireCodec::default()
+                .encode(&HostString("hello".to_string(), HostPlacement::from("alice")).into())
+                .unwrap(),
+            sender: Identity::from(sender),
+            receiver: Identity::from("bob"),
+            rendezvous_key: RendezvousKey::from_bytes([0; 16]),
+            session_id: SessionId::try_from("session").unwrap(),
+        }
+    }
+
+    fn hello(sender: &str) -> Frame {
+        Frame::Hello {
+            sender: Identity::from(sender),
+            epoch: 42,
+        }
+    }
+
+    /// Start a connection as the given sender, reading the codecs announced in reply.
+    async fn greet<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, sender: &str) {
+        write_frame(stream, &hello(sender)).await.unwrap();
+        match read_frame(stream, MAX_FRAME_SIZE).await.unwrap() {
+            Some(Frame::Codecs { supported }) => assert_eq!(supported, CodecSupport::all()),
+            frame => panic!("expected codecs but got {:?}", frame),
+        }
+    }
+
+    async fn expect_ack<S: AsyncRead + Unpin>(stream: &mut S, expected_seq: u64) {
+        match read_frame(stream, MAX_FRAME_SIZE).await.unwrap() {
+            Some(Frame::Ack { seq }) => assert_eq!(seq, expected_seq),
+            frame => panic!("expected ack but got {:?}", frame),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_oversized_frame() {
+        let mut buf = [0; 8];
+        u64_to_little_endian(u64::MAX, &mut buf);
+        let res = read_frame(&mut &buf[..], MAX_FRAME_SIZE).await;
+        assert!(matches!(res, Err(Error::Networking(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reject_oversized_hello() {
+        let store = StoreType::default();
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            None,
+        ));
+
+        // the connection is rejected as soon as the size is announced
+        let mut buf = [0; 8];
+        u64_to_little_endian(MAX_HELLO_SIZE + 1, &mut buf);
+        client.write_all(&buf).await.unwrap();
+        let res = connection.await.unwrap();
+        assert!(matches!(res, Err(Error::Networking(_))));
+    }
+
+    #[tokio::test]
+    async fn test_accept_from_authenticated_peer() {
+        let store = StoreType::default();
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            Some(Identity::from("alice")),
+        ));
+
+        let data = send_data("alice");
+        let key = (data.session_id.clone(), data.rendezvous_key.clone());
+        greet(&mut client, "alice").await;
+        write_frame(&mut client, &Frame::Data { seq: 1, data })
+            .await
+            .unwrap();
+        expect_ack(&mut client, 1).await;
+        drop(client);
+
+        connection.await.unwrap().unwrap();
+        assert_eq!(store.outstanding_cells()[&key.0], 1);
+    }
+
+    #[tokio::test]
+    async fn test_reject_spoofed_sender() {
+        let store = StoreType::default();
+        let (mut client, server) = tokio::io::duplex(1024);
+
+        write_frame(&mut client, &hello("carole")).await.unwrap();
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 1,
+                data: send_data("carole"),
+            },
+        )
+        .await
+        .unwrap();
+
+        let res = handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            Some(Identity::from("alice")),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Networking(_))));
+        assert!(store.outstanding_cells().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_retransmitted_value() {
+        let store = StoreType::default();
+        let delivered = DeliveredType::default();
+        let data = send_data("alice");
+        let key = (data.session_id.clone(), data.rendezvous_key.clone());
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            Arc::clone(&delivered),
+            None,
+        ));
+        greet(&mut client, "alice").await;
+        write_frame(&mut client, &Frame::Data { seq: 1, data })
+            .await
+            .unwrap();
+        expect_ack(&mut client, 1).await;
+        drop(client);
+        connection.await.unwrap().unwrap();
+
+        // the value is received, after which the sender resends it over a new connection
+        store.take(&key.0, &key.1).await;
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            Arc::clone(&delivered),
+            None,
+        ));
+        greet(&mut client, "alice").await;
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 1,
+                data: send_data("alice"),
+            },
+        )
+        .await
+        .unwrap();
+        expect_ack(&mut client, 1).await;
+        drop(client);
+        connection.await.unwrap().unwrap();
+
+        assert_eq!(store.outstanding_cells()[&key.0], 0);
+    }
+
+    #[tokio::test]
+    async fn test_keep_reading_after_rejected_value() {
+        let store = StoreType::default();
+        store.set_limits(BufferLimits {
+            max_values: 1,
+            ..Default::default()
+        });
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            None,
+        ));
+        let data = |byte| SendData {
+            rendezvous_key: RendezvousKey::from_bytes([byte; 16]),
+            ..send_data("alice")
+        };
+        let session_id = data(0).session_id;
+
+        greet(&mut client, "alice").await;
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 1,
+                data: data(0),
+            },
+        )
+        .await
+        .unwrap();
+        expect_ack(&mut client, 1).await;
+        // the buffer of the session is full
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 2,
+                data: data(1),
+            },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            read_frame(&mut client, MAX_FRAME_SIZE).await.unwrap(),
+            Some(Frame::Reject { seq: 2 })
+        ));
+        // the value is sent again once the session has started, over the same connection
+        store.start_session(&session_id);
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 2,
+                data: data(1),
+            },
+        )
+        .await
+        .unwrap();
+        expect_ack(&mut client, 2).await;
+        drop(client);
+
+        connection.await.unwrap().unwrap();
+        assert_eq!(store.outstanding_cells()[&session_id], 2);
+    }
+
+    #[tokio::test]
+    async fn test_store_rejected_value_resent_after_reconnect() {
+        let store = StoreType::default();
+        store.set_limits(BufferLimits {
+            max_values: 0,
+            ..Default::default()
+        });
+        let delivered = DeliveredType::default();
+        let rejected = send_data("alice");
+        let stored = SendData {
+            session_id: SessionId::try_from("started").unwrap(),
+            ..send_data("alice")
+        };
+        let session_id = rejected.session_id.clone();
+        store.start_session(&stored.session_id);
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            Arc::clone(&delivered),
+            None,
+        ));
+        greet(&mut client, "alice").await;
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 1,
+                data: rejected,
+            },
+        )
+        .await
+        .unwrap();
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 2,
+                data: stored,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            read_frame(&mut client, MAX_FRAME_SIZE).await.unwrap(),
+            Some(Frame::Reject { seq: 1 })
+        ));
+        expect_ack(&mut client, 2).await;
+        drop(client);
+        connection.await.unwrap().unwrap();
+
+        // the rejected value is resent under the same sequence number after a reconnect, e.g.
+        // when the connection failed before the sender read the replies
+        store.start_session(&session_id);
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            Arc::clone(&delivered),
+            None,
+        ));
+        greet(&mut client, "alice").await;
+        write_frame(
+            &mut client,
+            &Frame::Data {
+                seq: 1,
+                data: send_data("alice"),
+            },
+        )
+        .await
+        .unwrap();
+        expect_ack(&mut client, 1).await;
+        drop(client);
+        connection.await.unwrap().unwrap();
+
+        assert_eq!(store.outstanding_cells()[&session_id], 1);
+    }
+
+    #[test]
+    fn test_skip_values_never_stored() {
+        let mut delivered = Delivered::new();
+        delivered.insert(1);
+        // the value with sequence number 2 is never stored
+        for seq in 3..MAX_STORED_OUT_OF_ORDER as u64 + 3 {
+            delivered.insert(seq);
+        }
+        assert!(!delivered.contains(2));
+        delivered.insert(MAX_STORED_OUT_OF_ORDER as u64 + 3);
+        assert!(delivered.contains(2));
+        assert!(delivered.above.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_rejected_value() {
+        let store = StoreType::default();
+        store.set_limits(BufferLimits {
+            max_values: 0,
+            ..Default::default()
+        });
+        let (client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            None,
+        ));
+        let peer = Peer {
+            identity: Identity::from("bob"),
+            address: PeerAddress::Tcp("localhost:0".to_string()),
+            connector: None,
+        };
+        let (tx, rx) = mpsc::channel(1);
+        let codecs = PeerCodecs::default();
+        let sender = tokio::spawn(send_loop(
+            Identity::from("alice"),
+            peer,
+            Box::new(client),
+            rx,
+            Arc::clone(&codecs),
+        ));
+
+        let data = send_data("alice");
+        let session_id = data.session_id.clone();
+        let (finished_send_signal, mut send_finished) = mpsc::channel(1);
+        tx.send((data, finished_send_signal)).await.unwrap();
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(store.outstanding_cells()[&session_id], 0);
+
+        // the codecs of the receiver are known once it has answered
+        assert_eq!(*codecs.read(), CodecSupport::all());
+
+        // the value is stored once the session has started
+        store.start_session(&session_id);
+        assert!(send_finished.recv().await.is_some());
+        drop(tx);
+
+        sender.await.unwrap().unwrap();
+        connection.await.unwrap().unwrap();
+        assert_eq!(store.outstanding_cells()[&session_id], 1);
+    }
+
+    #[tokio::test]
+    async fn test_reject_value_from_unexpected_sender() {
+        let store = StoreType::default();
+        let networking =
+            TcpStreamNetworking::connect_peers("bob".to_string(), Arc::clone(&store), vec![])
+                .await
+                .unwrap();
+        let (mut client, server) = tokio::io::duplex(1024);
+        let connection = tokio::spawn(handle_connection(
+            server,
+            Arc::clone(&store),
+            DeliveredType::default(),
+            Some(Identity::from("carole")),
+        ));
+
+        let data = send_data("carole");
+        let key = (data.session_id.clone(), data.rendezvous_key.clone());
+        greet(&mut client, "carole").await;
+        write_frame(&mut client, &Frame::Data { seq: 1, data })
+            .await
+            .unwrap();
+        expect_ack(&mut client, 1).await;
+        drop(client);
+        connection.await.unwrap().unwrap();
+
+        let res = networking
+            .receive(&Identity::from("alice"), &key.1, &key.0)
+            .await;
+        assert!(matches!(res, Err(Error::Networking(_))));
+    }
+
+    #[tokio::test]
+    async fn test_drop_values_when_session_ends() {
+        let store = StoreType::default();
+        let networking = Arc::new(
+            TcpStreamNetworking::connect_peers("bob".to_string(), Arc::clone(&store), vec![])
+                .await
+                .unwrap(),
+        );
+        let data = send_data("alice");
+        let session_id = data.session_id.clone();
+
+        let session = networking.new_session(session_id.clone());
+        // a value that is never taken, and a receive that is cancelled
+        store
+            .put(&session_id, data.rendezvous_key, (data.sender, data.value))
+            .unwrap();
+        let sender = Identity::from("alice");
+        let rendezvous_key = RendezvousKey::from_bytes([1; 16]);
+        let receive = session.receive(&sender, &rendezvous_key, &session_id);
+        assert!(tokio::time::timeout(Duration::from_millis(10), receive)
+            .await
+            .is_err());
+        assert_eq!(networking.outstanding_cells()[&session_id], 2);
+
+        drop(session);
+        assert!(networking.outstanding_cells().is_empty());
+    }
+
+    #[test]
+    fn test_forget_old_epochs() {
+        let delivered = DeliveredType::default();
+        let alice = Identity::from("alice");
+        let bob = Identity::from("bob");
+        register_epoch(&delivered, &bob, 0);
+        for epoch in 0..MAX_EPOCHS_PER_SENDER as u64 + 10 {
+            register_epoch(&delivered, &alice, epoch);
+        }
+        // the most recent epoch of alice is kept, and those of other senders are unaffected
+        assert_eq!(delivered.len(), MAX_EPOCHS_PER_SENDER + 1);
+        assert!(delivered.contains_key(&(alice, MAX_EPOCHS_PER_SENDER as u64 + 9)));
+        assert!(delivered.contains_key(&(bob, 0)));
+    }
+
+    #[tokio::test]
+    async fn test_resend_after_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let store = StoreType::default();
+
+        let server_store = Arc::clone(&store);
+        let server = tokio::spawn(async move {
+            // drop the first connection without acknowledging the value
+            let (mut stream, _) = listener.accept().await.unwrap();
+            assert!(matches!(
+                read_frame(&mut stream, MAX_FRAME_SIZE).await.unwrap(),
+                Some(Frame::Hello { .. })
+            ));
+            assert!(matches!(
+                read_frame(&mut stream, MAX_FRAME_SIZE).await.unwrap(),
+                Some(Frame::Data { seq: 1, .. })
+            ));
+            drop(stream);
+
+            let (stream, _) = listener.accept().await.unwrap();
+            handle_connection(stream, server_store, DeliveredType::default(), None).await
+        });
+
+        let peer = Peer {
+            identity: Identity::from("bob"),
+            address: PeerAddress::Tcp(address),
+            connector: None,
+        };
+        let stream = peer.connect().await.unwrap();
+        let (tx, rx) = mpsc::channel(1);
+        let codecs = PeerCodecs::default();
+        let sender = tokio::spawn(send_loop(Identity::from("alice"), peer, stream, rx, codecs));
+
+        let data = send_data("alice");
+        let key = (data.session_id.clone(), data.rendezvous_key.clone());
+        let (finished_send_signal, mut send_finished) = mpsc::channel(1);
+        tx.send((data, finished_send_signal)).await.unwrap();
+        assert!(send_finished.recv().await.is_some());
+        drop(tx);
+
+        sender.await.unwrap().unwrap();
+        server.await.unwrap().unwrap();
+        assert_eq!(store.outstanding_cells()[&key.0], 1);
+    }
+
+    struct TestCa {
+        ca: rcgen::Certificate,
+    }
+
+    impl TestCa {
+        fn new() -> TestCa {
+            let mut params = rcgen::CertificateParams::new(vec![]);
+            params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, "ca");
+            TestCa {
+                ca: rcgen::Certificate::from_params(params).unwrap(),
+            }
+        }
+
+        fn tls_config(&self, identity: &str) -> TcpStreamTlsConfig {
+            let mut params = rcgen::CertificateParams::new(vec!["localhost".to_string()]);
+            params
+                .distinguished_name
+                .push(rcgen::DnType::CommonName, identity);
+            let cert = rcgen::Certificate::from_params(params).unwrap();
+            TcpStreamTlsConfig::from_pem(
+                cert.serialize_pem_with_signer(&self.ca).unwrap().as_bytes(),
+                cert.serialize_private_key_pem().as_bytes(),
+                self.ca.serialize_pem().unwrap().as_bytes(),
+            )
+            .unwrap()
+        }
+    }
+
+    /// Address on localhost with a port that was free when asked for.
+    async fn free_address() -> String {
+        let listener = TcpListener::bind("localhost:0").await.unwrap();
+        format!("localhost:{}", listener.local_addr().unwrap().port())
+    }
+
+    #[tokio::test]
+    async fn test_send_receive_over_tls() {
+        let ca = TestCa::new();
+        let hosts: HashMap<String, String> = vec![
+            ("alice".to_string(), free_address().await),
+            ("bob".to_string(), free_address().await),
+        ]
+        .into_iter()
+        .collect();
+        // each party waits for the other to listen before returning
+        let (alice, bob) = tokio::join!(
+            TcpStreamNetworking::new_with_tls("alice", hosts.clone(), ca.tls_config("alice")),
+            TcpStreamNetworking::new_with_tls("bob", hosts, ca.tls_config("bob")),
+        );
+        let (alice, bob) = (alice.unwrap(), bob.unwrap());
+
+        let value: Value = HostString("hello".to_string(), HostPlacement::from("alice")).into();
+        let key = RendezvousKey::from_bytes([0; 16]);
+        let session_id = SessionId::try_from("session").unwrap();
+        alice
+            .send(&value, &Identity::from("bob"), &key, &session_id)
+            .await
+            .unwrap();
+        let received = bob
+            .receive(&Identity::from("alice"), &key, &session_id)
+            .await
+            .unwrap();
+        assert_eq!(received, value);
+    }
+
+    #[tokio::test]
+    async fn test_reject_certificate_of_other_sender() {
+        let ca = TestCa::new();
+        let address = free_address().await;
+        let hosts: HashMap<String, String> = vec![("bob".to_string(), address.clone())]
+            .into_iter()
+            .collect();
+        let bob = TcpStreamNetworking::new_with_tls("bob", hosts, ca.tls_config("bob"))
+            .await
+            .unwrap();
+
+        // mallory authenticates with its own certificate but claims to be alice
+        let connector = TlsConnector::from(Arc::clone(&ca.tls_config("mallory").client));
+        let stream = TcpStream::connect(&address).await.unwrap();
+        let mut stream = connect_tls(&connector, stream, &address, &Identity::from("bob"))
+            .await
+            .unwrap();
+        write_frame(&mut stream, &hello("alice")).await.unwrap();
+        write_frame(
+            &mut stream,
+            &Frame::Data {
+                seq: 1,
+                data: send_data("alice"),
+            },
+        )
+        .await
+        .unwrap();
+
+        // the connection is closed without announcing codecs or acknowledging the value
+        let res = read_frame(&mut stream, MAX_FRAME_SIZE).await;
+        assert!(!matches!(res, Ok(Some(_))));
+        assert!(bob.outstanding_cells().is_empty());
+    }
 }
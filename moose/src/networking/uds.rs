@@ -0,0 +1,241 @@
+//! Unix domain socket networking implementation, for parties running on the same host.
+
+use crate::{
+    computation::{RendezvousKey, SessionId, Value},
+    execution::Identity,
+    networking::codec::WireCodec,
+    networking::rendezvous::{BufferLimits, SessionNetworking},
+    networking::tcpstream::{
+        handle_connection, DeliveredType, Peer, PeerAddress, StoreType, TcpStreamNetworking,
+    },
+    networking::AsyncNetworking,
+    Error, Result,
+};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::UnixListener;
+use tokio::time::{sleep, Duration};
+
+/// Networking over Unix domain sockets, with every identity mapped to the path of its socket.
+///
+/// Uses the same framing, acknowledgements and retransmission as `TcpStreamNetworking`,
+/// without TLS since access to the sockets is controlled through the filesystem.
+pub struct UdsNetworking {
+    inner: TcpStreamNetworking,
+}
+
+async fn server(listener: UnixListener, store: StoreType, delivered: DeliveredType) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("failed to accept connection: {}", e);
+                sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let store = Arc::clone(&store);
+        let delivered = Arc::clone(&delivered);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, store, delivered, None).await {
+                tracing::error!("closed connection: {}", e);
+            }
+        });
+    }
+}
+
+impl UdsNetworking {
+    /// Listen on the socket of `own_name` in `hosts` and connect to the sockets of all others.
+    ///
+    /// A socket left at the own socket path, e.g. by a previous run, is removed first, while
+    /// anything else found there is an error.
+    pub async fn new(own_name: &str, hosts: HashMap<String, PathBuf>) -> Result<UdsNetworking> {
+        tracing::debug!("own name: {}", own_name);
+        let own_path = hosts
+            .get(own_name)
+            .ok_or_else(|| Error::Networking("own host name not in hosts map".to_string()))?;
+
+        if let Ok(metadata) = std::fs::symlink_metadata(own_path) {
+            if !metadata.file_type().is_socket() {
+                return Err(Error::Networking(format!(
+                    "not replacing {} since it is not a socket",
+                    own_path.display()
+                )));
+            }
+            std::fs::remove_file(own_path).map_err(|e| {
+                Error::Networking(format!(
+                    "could not remove existing socket {}: {}",
+                    own_path.display(),
+                    e
+                ))
+            })?;
+        }
+        let listener = UnixListener::bind(own_path).map_err(|e| {
+            Error::Networking(format!(
+                "could not bind to socket: {}: {}",
+                own_path.display(),
+                e
+            ))
+        })?;
+        tracing::debug!("listening on: {}", own_path.display());
+        let store = StoreType::default();
+        tokio::spawn(server(
+            listener,
+            Arc::clone(&store),
+            DeliveredType::default(),
+        ));
+
+        let peers = hosts
+            .into_iter()
+            .filter(|(placement, _)| placement != own_name)
+            .map(|(placement, path)| Peer {
+                identity: Identity::from(placement),
+                address: PeerAddress::Unix(path),
+                connector: None,
+            })
+            .collect();
+        let inner = TcpStreamNetworking::connect_peers(own_name.to_string(), store, peers).await?;
+        Ok(UdsNetworking { inner })
+    }
+
+    /// Encode values with the given codec instead of plain bincode.
+    pub fn with_codec(self, codec: WireCodec) -> UdsNetworking {
+        UdsNetworking {
+            inner: self.inner.with_codec(codec),
+        }
+    }
+
+    /// Limit the values buffered for sessions that have not started locally.
+    pub fn with_buffer_limits(self, limits: BufferLimits) -> UdsNetworking {
+        UdsNetworking {
+            inner: self.inner.with_buffer_limits(limits),
+        }
+    }
+
+    /// Number of cells per session that are either waiting for or holding a value.
+    pub fn outstanding_cells(&self) -> HashMap<SessionId, usize> {
+        self.inner.outstanding_cells()
+    }
+
+    /// Networking for a single session, whose values are dropped once it is dropped.
+    pub fn new_session(self: &Arc<Self>, session_id: SessionId) -> Arc<impl AsyncNetworking> {
+        Arc::new(SessionNetworking::new(
+            Arc::clone(self),
+            Arc::clone(&self.inner.store),
+            session_id,
+        ))
+    }
+}
+
+#[async_trait]
+impl AsyncNetworking for UdsNetworking {
+    async fn send(
+        &self,
+        value: &Value,
+        receiver: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<()> {
+        self.inner
+            .send(value, receiver, rendezvous_key, session_id)
+            .await
+    }
+
+    async fn receive(
+        &self,
+        sender: &Identity,
+        rendezvous_key: &RendezvousKey,
+        session_id: &SessionId,
+    ) -> Result<Value> {
+        self.inner.receive(sender, rendezvous_key, session_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::{HostPlacement, HostString};
+    use std::convert::TryFrom;
+
+    #[tokio::test]
+    async fn test_send_receive() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts: HashMap<String, PathBuf> = ["alice", "bob"]
+            .iter()
+            .map(|name| (name.to_string(), dir.path().join(format!("{}.sock", name))))
+            .collect();
+
+        // both parties must be listening before either finishes connecting
+        let (alice, bob) = tokio::join!(
+            UdsNetworking::new("alice", hosts.clone()),
+            UdsNetworking::new("bob", hosts.clone()),
+        );
+        let (alice, bob) = (alice.unwrap(), bob.unwrap());
+
+        let session_id = SessionId::try_from("session").unwrap();
+        let key = RendezvousKey::from_bytes([0; 16]);
+        let value: Value = HostString("hello".to_string(), HostPlacement::from("alice")).into();
+        alice
+            .send(&value, &Identity::from("bob"), &key, &session_id)
+            .await
+            .unwrap();
+        let received = bob
+            .receive(&Identity::from("alice"), &key, &session_id)
+            .await
+            .unwrap();
+        assert_eq!(received, value);
+    }
+
+    #[tokio::test]
+    async fn test_replace_only_sockets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alice.sock");
+        let hosts = HashMap::from([("alice".to_string(), path.clone())]);
+
+        // a socket left behind is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        UdsNetworking::new("alice", hosts.clone()).await.unwrap();
+
+        // other files are kept
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, b"data").unwrap();
+        let res = UdsNetworking::new("alice", hosts).await;
+        assert!(matches!(res, Err(Error::Networking(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), b"data");
+    }
+
+    #[tokio::test]
+    async fn test_drop_values_when_session_ends() {
+        let dir = tempfile::tempdir().unwrap();
+        let hosts: HashMap<String, PathBuf> = ["alice", "bob"]
+            .iter()
+            .map(|name| (name.to_string(), dir.path().join(format!("{}.sock", name))))
+            .collect();
+        let (alice, bob) = tokio::join!(
+            UdsNetworking::new("alice", hosts.clone()),
+            UdsNetworking::new("bob", hosts.clone()),
+        );
+        let (alice, bob) = (alice.unwrap(), Arc::new(bob.unwrap()));
+
+        let session_id = SessionId::try_from("session").unwrap();
+        let session = bob.new_session(session_id.clone());
+        // the value is sent but never received
+        let value: Value = HostString("hello".to_string(), HostPlacement::from("alice")).into();
+        alice
+            .send(
+                &value,
+                &Identity::from("bob"),
+                &RendezvousKey::from_bytes([0; 16]),
+                &session_id,
+            )
+            .await
+            .unwrap();
+        assert_eq!(bob.outstanding_cells()[&session_id], 1);
+
+        drop(session);
+        assert!(bob.outstanding_cells().is_empty());
+    }
+}
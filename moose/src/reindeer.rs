@@ -1,5 +1,12 @@
 //! Common library (helper functions) for the reindeer.
 
+use crate::choreography::StorageStrategy;
+use crate::networking::quic::QuicTlsConfig;
+use crate::networking::tcpstream::TcpStreamTlsConfig;
+use crate::storage::encrypted::{EncryptedShareStorage, RoutedShareStorage};
+use crate::storage::filesystem::AsyncFilesystemStorage;
+use std::path::Path;
+use std::sync::Arc;
 use tonic::transport::{Certificate, ClientTlsConfig, Identity, ServerTlsConfig};
 
 /// Setup Jaeger tracing via OpenTelemetry
@@ -61,18 +68,65 @@ pub fn load_server_tlc_config(
     Ok(server_tls)
 }
 
+/// Load TLS configuration for `TcpStreamNetworking` from files
+pub fn load_tcpstream_tls_config(
+    my_cert_name: &str,
+    certs_dir: &str,
+) -> Result<TcpStreamTlsConfig, Box<dyn std::error::Error>> {
+    let (my_cert_raw, my_key_raw, ca_cert_raw) = read_identity_and_ca(my_cert_name, certs_dir)?;
+    let tls_config = TcpStreamTlsConfig::from_pem(&my_cert_raw, &my_key_raw, &ca_cert_raw)?;
+    Ok(tls_config)
+}
+
+/// Load TLS configuration for `QuicNetworking` from files
+pub fn load_quic_tls_config(
+    my_cert_name: &str,
+    certs_dir: &str,
+) -> Result<QuicTlsConfig, Box<dyn std::error::Error>> {
+    let (my_cert_raw, my_key_raw, ca_cert_raw) = read_identity_and_ca(my_cert_name, certs_dir)?;
+    let tls_config = QuicTlsConfig::from_pem(&my_cert_raw, &my_key_raw, &ca_cert_raw)?;
+    Ok(tls_config)
+}
+
+/// Storage on the filesystem, keeping shares saved under `share_dir` encrypted at rest if given
+pub fn storage_strategy(
+    share_dir: Option<&Path>,
+    share_key_file: Option<&Path>,
+) -> Result<StorageStrategy, Box<dyn std::error::Error>> {
+    match (share_dir, share_key_file) {
+        (Some(share_dir), Some(share_key_file)) => {
+            let shares = EncryptedShareStorage::from_key_file(share_dir.into(), share_key_file)?;
+            Ok(Box::new(move || {
+                Arc::new(RoutedShareStorage::new(
+                    shares.clone(),
+                    Arc::new(AsyncFilesystemStorage::default()),
+                ))
+            }))
+        }
+        (None, None) => Ok(Box::new(|| Arc::new(AsyncFilesystemStorage::default()))),
+        _ => Err("a share directory and a share key must be given together".into()),
+    }
+}
+
 const CA_NAME: &str = "ca";
 
 fn load_identity_and_ca(
     my_cert_name: &str,
     certs_dir: &str,
 ) -> Result<(Identity, Certificate), Box<dyn std::error::Error>> {
-    let my_cert_raw = std::fs::read(format!("{}/{}.crt", certs_dir, my_cert_name))?;
-    let my_key_raw = std::fs::read(format!("{}/{}.key", certs_dir, my_cert_name))?;
+    let (my_cert_raw, my_key_raw, ca_cert_raw) = read_identity_and_ca(my_cert_name, certs_dir)?;
     let identity = Identity::from_pem(my_cert_raw, my_key_raw);
-
-    let ca_cert_raw = std::fs::read(format!("{}/{}.crt", certs_dir, CA_NAME))?;
     let ca_cert = Certificate::from_pem(ca_cert_raw);
-
     Ok((identity, ca_cert))
 }
+
+/// Read PEM-encoded certificate, private key, and CA certificate
+fn read_identity_and_ca(
+    my_cert_name: &str,
+    certs_dir: &str,
+) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), Box<dyn std::error::Error>> {
+    let my_cert_raw = std::fs::read(format!("{}/{}.crt", certs_dir, my_cert_name))?;
+    let my_key_raw = std::fs::read(format!("{}/{}.key", certs_dir, my_cert_name))?;
+    let ca_cert_raw = std::fs::read(format!("{}/{}.crt", certs_dir, CA_NAME))?;
+    Ok((my_cert_raw, my_key_raw, ca_cert_raw))
+}
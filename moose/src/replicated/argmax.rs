@@ -1,6 +1,6 @@
 use super::*;
 use crate::computation::ArgmaxOp;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::execution::Session;
 
 pub(crate) trait TreeReduceArgmax<S: Session, T, O> {
@@ -33,8 +33,8 @@ impl ArgmaxOp {
     pub(crate) fn rep_fixed_kernel<S: Session, RepRingT1, RepRingT2>(
         sess: &S,
         rep: &ReplicatedPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: RepFixedTensor<RepRingT1>,
     ) -> Result<RepUintTensor<RepRingT2>>
     where
@@ -50,8 +50,8 @@ impl RingFixedpointArgmaxOp {
     pub(crate) fn rep_ring_kernel<S: Session, RepRingT, RepRingT2, ShapeT>(
         sess: &S,
         rep: &ReplicatedPlacement,
-        axis: usize,
-        upmost_index: usize,
+        axis: Option<usize>,
+        upmost_index: Option<usize>,
         x: RepRingT,
     ) -> Result<RepRingT2>
     where
@@ -63,6 +63,12 @@ impl RingFixedpointArgmaxOp {
         ReplicatedPlacement: TreeReduceArgmax<S, RepRingT, RepRingT>,
         ReplicatedPlacement: PlacementCast<S, RepRingT, RepRingT2>,
     {
+        let axis = axis.unwrap_or(0);
+        let upmost_index = upmost_index.ok_or_else(|| {
+            Error::InvalidArgument(
+                "argmax requires an upmost index, which shape inference may fill in".to_string(),
+            )
+        })?;
         let xs: Vec<_> = (0..upmost_index)
             .map(|index| rep.index_axis(sess, axis, index, &x))
             .collect();
@@ -108,7 +114,7 @@ mod tests {
 
                 let x: HostRingTensor<_> = alice.from_raw(x);
                 let x_shared = rep.share(&sess, &x);
-                let argmax = rep.$test_func(&sess, axis, upmost_index, &x_shared); // output is ReplicatedRing64Tensor
+                let argmax = rep.$test_func(&sess, Some(axis), Some(upmost_index), &x_shared); // output is ReplicatedRing64Tensor
 
                 let opened_argmax = alice.reveal(&sess, &argmax);
                 let y_target: HostRing64Tensor = alice.from_raw(y_target);
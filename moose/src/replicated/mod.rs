@@ -33,6 +33,7 @@ mod ops;
 mod setup;
 mod softmax;
 mod sqrt;
+mod storage;
 mod zero_share;
 pub use self::aes::RepAesKey;
 pub use self::fixedpoint::RepFixedTensor;
@@ -2,7 +2,7 @@
 
 use super::*;
 use crate::computation::MaximumOp;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::execution::Session;
 use crate::fixedpoint::FixedpointTensor;
 use moose_macros::with_context;
@@ -57,7 +57,7 @@ impl SoftmaxOp {
         sess: &S,
         rep: &ReplicatedPlacement,
         axis: usize,
-        upmost_index: usize,
+        upmost_index: Option<usize>,
         x: RepFixedT,
     ) -> Result<RepFixedT>
     where
@@ -77,6 +77,11 @@ impl SoftmaxOp {
         ReplicatedPlacement: PlacementDiv<S, RepFixedT, RepFixedT, RepFixedT>,
         ReplicatedPlacement: PlacementSum<S, RepFixedT, RepFixedT>,
     {
+        let upmost_index = upmost_index.ok_or_else(|| {
+            Error::InvalidArgument(
+                "softmax requires an upmost index, which shape inference may fill in".to_string(),
+            )
+        })?;
         let xs: Vec<_> = (0..upmost_index)
             .map(|index| rep.index_axis(sess, axis, index, &x))
             .collect();
@@ -164,7 +169,7 @@ mod tests {
                     x_foo, $i_precision, $f_precision)
                 );
 
-                let exp_result = rep.$test_func(&sess, $axis, $upmost_index, &x);
+                let exp_result = rep.$test_func(&sess, $axis, Some($upmost_index), &x);
 
                 let opened_exp = match exp_result {
                     FixedTensor::Replicated(r) => alice.reveal(&sess, &r),
@@ -0,0 +1,251 @@
+use super::*;
+use crate::computation::{LoadOp, SaveOp};
+use crate::error::{Error, Result};
+use crate::execution::Session;
+use crate::host::HostPlacement;
+use crate::kernels::{fixed_key_suffix, share_key_suffix, PlacementStorageKey};
+
+impl SaveOp {
+    pub(crate) fn rep_ring_kernel<S: Session, HostRingT>(
+        sess: &S,
+        plc: &HostPlacement,
+        key: m!(HostString),
+        x: RepTensor<HostRingT>,
+    ) -> Result<m!(HostUnit)>
+    where
+        HostString: KnownType<S>,
+        HostUnit: KnownType<S>,
+        HostRingT: Placed<Placement = HostPlacement>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        HostPlacement: PlacementExpandDims<S, HostRingT, HostRingT>,
+        HostPlacement: PlacementConcatenate<S, HostRingT, HostRingT>,
+        HostPlacement: PlacementSave<S, m!(HostString), HostRingT, m!(HostUnit)>,
+    {
+        let rep = x.placement()?;
+        let owner_ix = rep
+            .owners
+            .iter()
+            .position(|owner| owner == &plc.owner)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "cannot save shares of a value on {:?} on {}, which is not one of its owners",
+                    rep, plc.owner
+                ))
+            })?;
+
+        // the two shares held by the owner are stacked and stored as a single host tensor
+        let [x0, x1] = &x.shares[owner_ix];
+        let x0 = plc.expand_dims(sess, vec![0], x0);
+        let x1 = plc.expand_dims(sess, vec![0], x1);
+        let shares = plc.concatenate(sess, 0, &[x0, x1]);
+
+        let key = plc.storage_key(sess, share_key_suffix(&rep.owners, &plc.owner), &key);
+        Ok(plc.save(sess, &key, &shares))
+    }
+
+    pub(crate) fn rep_fixed_kernel<S: Session, RepRingT>(
+        sess: &S,
+        plc: &HostPlacement,
+        key: m!(HostString),
+        x: RepFixedTensor<RepRingT>,
+    ) -> Result<m!(HostUnit)>
+    where
+        HostString: KnownType<S>,
+        HostUnit: KnownType<S>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        HostPlacement: PlacementSave<S, m!(HostString), RepRingT, m!(HostUnit)>,
+    {
+        // the precision is kept in the key since the shares themselves are plain ring tensors
+        let suffix = fixed_key_suffix(x.integral_precision, x.fractional_precision);
+        let key = plc.storage_key(sess, suffix, &key);
+        Ok(plc.save(sess, &key, &x.tensor))
+    }
+}
+
+impl LoadOp {
+    pub(crate) fn rep_ring_kernel<S: Session, HostRingT>(
+        sess: &S,
+        plc: &ReplicatedPlacement,
+        key: m!(HostString),
+        query: m!(HostString),
+    ) -> Result<RepTensor<HostRingT>>
+    where
+        HostString: KnownType<S>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        HostPlacement: PlacementLoad<S, m!(HostString), m!(HostString), HostRingT>,
+        HostPlacement: PlacementIndexAxis<S, HostRingT, HostRingT>,
+    {
+        let (player0, player1, player2) = plc.host_placements();
+
+        // each owner loads the stacked shares it saved under a key derived for this placement,
+        // and which the storage only returns if saved by that same owner
+        let load_shares = |player: &HostPlacement| {
+            let key = player.storage_key(sess, share_key_suffix(&plc.owners, &player.owner), &key);
+            let shares = player.load(sess, &key, &query);
+            [
+                player.index_axis(sess, 0, 0, &shares),
+                player.index_axis(sess, 0, 1, &shares),
+            ]
+        };
+
+        Ok(RepTensor {
+            shares: [
+                load_shares(&player0),
+                load_shares(&player1),
+                load_shares(&player2),
+            ],
+        })
+    }
+
+    pub(crate) fn rep_fixed_kernel<S: Session, RepRingT>(
+        sess: &S,
+        plc: &ReplicatedPlacement,
+        integral_precision: u32,
+        fractional_precision: u32,
+        key: m!(HostString),
+        query: m!(HostString),
+    ) -> Result<RepFixedTensor<RepRingT>>
+    where
+        HostString: KnownType<S>,
+        HostPlacement: PlacementStorageKey<S, m!(HostString), m!(HostString)>,
+        ReplicatedPlacement: PlacementLoad<S, m!(HostString), m!(HostString), RepRingT>,
+    {
+        // shares are only found if saved with the expected precision
+        let (player0, _, _) = plc.host_placements();
+        let suffix = fixed_key_suffix(integral_precision, fractional_precision);
+        let key = player0.storage_key(sess, suffix, &key);
+
+        let tensor = plc.load(sess, &key, &query);
+        Ok(RepFixedTensor {
+            tensor,
+            integral_precision,
+            fractional_precision,
+        })
+    }
+}
+
+#[cfg(feature = "sync_execute")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computation::SessionId;
+    use crate::kernels::{PlacementFixedpointEncode, PlacementReveal, PlacementShare};
+    use crate::prelude::*;
+    use crate::storage::local::LocalSyncStorage;
+    use crate::storage::SyncStorage;
+    use ndarray::prelude::*;
+    use std::convert::TryInto;
+    use std::rc::Rc;
+
+    fn session(storage: &Rc<LocalSyncStorage>) -> SyncSession {
+        SyncSession::from_storage(
+            SessionId::random(),
+            Default::default(),
+            Default::default(),
+            Rc::clone(storage) as Rc<dyn SyncStorage>,
+        )
+    }
+
+    #[test]
+    fn test_save_load_rep_ring() {
+        let alice = HostPlacement::from("alice");
+        let bob = HostPlacement::from("bob");
+        let carole = HostPlacement::from("carole");
+        let rep = ReplicatedPlacement::from(["alice", "bob", "carole"]);
+        let storage = Rc::new(LocalSyncStorage::default());
+
+        // Each owner saves its shares in a previous session
+        let sess0 = session(&storage);
+        let x: HostRing64Tensor = alice.from_raw(array![1u64, 2, 3]);
+        let x_shared = rep.share(&sess0, &x);
+        let key = HostString("x".to_string(), alice.clone());
+        for owner in [&alice, &bob, &carole] {
+            let _: HostUnit = owner.save(&sess0, &key, &x_shared);
+        }
+
+        let bob_shares: HostRing64Tensor = storage
+            .load("x/alice-bob-carole/bob", &SessionId::random(), None, "")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            bob.index_axis(&sess0, 0, 0, &bob_shares),
+            x_shared.shares[1][0]
+        );
+        assert_eq!(
+            bob.index_axis(&sess0, 0, 1, &bob_shares),
+            x_shared.shares[1][1]
+        );
+
+        // Shares are reassembled when loaded on the replicated placement
+        let sess1 = session(&storage);
+        let query = HostString("".to_string(), alice.clone());
+        let y: ReplicatedRing64Tensor = rep.load(&sess1, &key, &query);
+        let z = alice.reveal(&sess1, &y);
+        assert_eq!(x, z)
+    }
+
+    fn save_rep_fixed(
+        storage: &Rc<LocalSyncStorage>,
+        integral_precision: u32,
+        fractional_precision: u32,
+    ) -> HostFixed64Tensor {
+        let alice = HostPlacement::from("alice");
+        let bob = HostPlacement::from("bob");
+        let carole = HostPlacement::from("carole");
+        let rep = ReplicatedPlacement::from(["alice", "bob", "carole"]);
+
+        let sess = session(storage);
+        let x: HostFloat32Tensor = alice.from_raw(array![1.0, 2.0, 3.0]);
+        let x_encoded =
+            alice.fixedpoint_encode(&sess, fractional_precision, integral_precision, &x);
+        let x_shared: ReplicatedFixed64Tensor = rep.share(&sess, &x_encoded);
+        let key = HostString("x".to_string(), alice.clone());
+        for owner in [&alice, &bob, &carole] {
+            let _: HostUnit = owner.save(&sess, &key, &x_shared);
+        }
+        x_encoded
+    }
+
+    #[test]
+    fn test_save_load_rep_fixed() {
+        let alice = HostPlacement::from("alice");
+        let rep = ReplicatedPlacement::from(["alice", "bob", "carole"]);
+        let storage = Rc::new(LocalSyncStorage::default());
+        let x_encoded = save_rep_fixed(&storage, 20, 10);
+
+        // shares are stored under a key specific to their precision
+        let shares = storage.load(
+            "x/fixed-20-10/alice-bob-carole/bob",
+            &SessionId::random(),
+            None,
+            "",
+        );
+        assert!(shares.is_ok());
+
+        let sess1 = session(&storage);
+        let key = HostString("x".to_string(), alice.clone());
+        let query = HostString("".to_string(), alice.clone());
+        let y: ReplicatedFixed64Tensor =
+            LoadOp::rep_fixed_kernel(&sess1, &rep, 20, 10, key, query).unwrap();
+        assert_eq!(y.integral_precision, 20);
+        assert_eq!(y.fractional_precision, 10);
+        let z = alice.reveal(&sess1, &y);
+        assert_eq!(x_encoded, z)
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn test_reject_rep_fixed_of_other_precision() {
+        let alice = HostPlacement::from("alice");
+        let rep = ReplicatedPlacement::from(["alice", "bob", "carole"]);
+        let storage = Rc::new(LocalSyncStorage::default());
+        let _ = save_rep_fixed(&storage, 20, 10);
+
+        // loading a ReplicatedFixed64Tensor expects the default precision
+        let sess1 = session(&storage);
+        let key = HostString("x".to_string(), alice.clone());
+        let query = HostString("".to_string(), alice);
+        let _: ReplicatedFixed64Tensor = rep.load(&sess1, &key, &query);
+    }
+}
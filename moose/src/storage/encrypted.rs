@@ -0,0 +1,619 @@
+//! Storage of a party's own secret shares, encrypted at rest.
+
+use super::*;
+use crate::host::HostPlacement;
+use crate::kernels::parse_share_key_suffix;
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+pub const KEY_LEN: usize = 32;
+
+const NONCE_LEN: usize = 12;
+
+/// Metadata saved in the clear alongside an encrypted share, and authenticated with it.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+struct ShareHeader {
+    /// Key under which the value was saved.
+    key: String,
+    /// Owners of the placement the saved value belongs to.
+    owners: Vec<Role>,
+    /// Role of the party that saved the value.
+    role: Role,
+    /// Session in which the value was saved.
+    session_id: SessionId,
+    ty: Ty,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedShare {
+    header: ShareHeader,
+    nonce: [u8; NONCE_LEN],
+    ciphertext: Vec<u8>,
+}
+
+/// Optional query restricting which shares may be loaded.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ShareQuery {
+    /// Expected owners of the placement the share belongs to.
+    owners: Option<Vec<Role>>,
+}
+
+/// Storage for a single party's shares of replicated and additive values.
+///
+/// A party holds its shares as host ring and bit tensors placed on it, which are the only
+/// values that can be saved; replicated and additive values are rejected since they hold
+/// the shares of every owner, which together reveal the secret. Saving such a value on one
+/// of its owners in a computation instead saves the shares held by that owner, under a key
+/// derived for the placement and owner, and loading it on the placement reassembles it.
+///
+/// Values are encrypted with AES-256-GCM under a local key, with the key, the owners of the
+/// placement, the role of the party and the originating session authenticated alongside.
+/// Loading rejects shares saved under another key or placed on another party, so that
+/// shares cannot be moved to where those of another placement are expected.
+///
+/// A party may play different roles in different sessions, so by default shares of any role
+/// are accepted; `with_role` restricts the storage to the shares of a single role.
+#[derive(Clone)]
+pub struct EncryptedShareStorage {
+    directory: PathBuf,
+    role: Option<Role>,
+    cipher: Aes256Gcm,
+}
+
+impl EncryptedShareStorage {
+    pub fn new(directory: PathBuf, key: &[u8; KEY_LEN]) -> Self {
+        EncryptedShareStorage {
+            directory,
+            role: None,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Create storage using a key file containing exactly 32 raw bytes.
+    pub fn from_key_file(directory: PathBuf, key_file: &Path) -> Result<Self> {
+        let bytes = std::fs::read(key_file).map_err(|e| {
+            Error::Storage(format!(
+                "failed to read key file {}: {}",
+                key_file.display(),
+                e
+            ))
+        })?;
+        let key: [u8; KEY_LEN] = bytes.as_slice().try_into().map_err(|_| {
+            Error::Storage(format!(
+                "key file {} must contain exactly {} bytes, found {}",
+                key_file.display(),
+                KEY_LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(Self::new(directory, &key))
+    }
+
+    /// Only save and load the shares of the given role.
+    pub fn with_role(self, role: Role) -> Self {
+        EncryptedShareStorage {
+            role: Some(role),
+            ..self
+        }
+    }
+
+    /// Directory in which shares are stored.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let is_nested = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if key.is_empty() || !is_nested {
+            return Err(Error::Storage(format!(
+                "invalid key '{}', keys must be relative paths without '..'",
+                key
+            )));
+        }
+        Ok(self.directory.join(relative))
+    }
+
+    fn check_owner(&self, key: &str, owners: &[Role]) -> Result<()> {
+        match &self.role {
+            Some(role) if !owners.contains(role) => Err(Error::Storage(format!(
+                "share '{}' belongs to a placement owned by {:?} rather than role {}",
+                key, owners, role
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Placement of values that are the shares of a single party.
+fn share_placement(value: &Value) -> Result<HostPlacement> {
+    let placement = match value {
+        Value::HostBitTensor(x) => x.placement()?,
+        Value::HostBitArray64(x) => x.placement()?,
+        Value::HostBitArray128(x) => x.placement()?,
+        Value::HostBitArray224(x) => x.placement()?,
+        Value::HostBitArray256(x) => x.placement()?,
+        Value::HostRing64Tensor(x) => x.placement()?,
+        Value::HostRing128Tensor(x) => x.placement()?,
+        Value::HostFixed64Tensor(x) => x.placement()?,
+        Value::HostFixed128Tensor(x) => x.placement()?,
+        _ => {
+            return Err(Error::Storage(format!(
+            "only host ring and bit tensors holding a party's shares can be saved to encrypted share storage, found value of type {}",
+            value.ty()
+        )))
+        }
+    };
+    Ok(placement)
+}
+
+/// Owners of the placement a share saved by `role` under `key` belongs to.
+///
+/// Shares saved on behalf of a replicated or additive placement are stored under a key
+/// derived with `share_key_suffix`, naming its owners; any other share belongs to its holder.
+fn share_owners(key: &str, role: &Role) -> Vec<Role> {
+    match parse_share_key_suffix(key) {
+        Some((owners, owner)) if &owner == role && owners.contains(role) => owners,
+        _ => vec![role.clone()],
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for EncryptedShareStorage {
+    async fn save(&self, key: &str, session_id: &SessionId, val: &Value) -> Result<()> {
+        let path = self.path(key)?;
+        let role = share_placement(val)?.owner;
+        let owners = share_owners(key, &role);
+        self.check_owner(key, &owners)?;
+
+        let header = ShareHeader {
+            key: key.to_string(),
+            owners,
+            role,
+            session_id: session_id.clone(),
+            ty: val.ty(),
+        };
+        let aad = bincode::serialize(&header)
+            .map_err(|e| Error::Storage(format!("failed to serialize share header: {}", e)))?;
+        let plaintext = bincode::serialize(val)
+            .map_err(|e| Error::Storage(format!("failed to serialize share '{}': {}", key, e)))?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| Error::Storage(format!("failed to encrypt share '{}'", key)))?;
+
+        let sealed = SealedShare {
+            header,
+            nonce,
+            ciphertext,
+        };
+        let bytes = bincode::serialize(&sealed)
+            .map_err(|e| Error::Storage(format!("failed to serialize share '{}': {}", key, e)))?;
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                Error::Storage(format!(
+                    "failed to create directory {}: {}",
+                    parent.display(),
+                    e
+                ))
+            })?;
+        }
+        tokio::fs::write(&path, bytes)
+            .await
+            .map_err(|e| Error::Storage(format!("failed to write share '{}': {}", key, e)))
+    }
+
+    async fn load(
+        &self,
+        key: &str,
+        _session_id: &SessionId,
+        type_hint: Option<Ty>,
+        query: &str,
+    ) -> Result<Value> {
+        let query: ShareQuery = if query.is_empty() {
+            ShareQuery::default()
+        } else {
+            serde_json::from_str(query).map_err(|e| {
+                Error::Storage(format!("failed to parse query for share '{}': {}", key, e))
+            })?
+        };
+
+        let path = self.path(key)?;
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| Error::Storage(format!("failed to read share '{}': {}", key, e)))?;
+        let sealed: SealedShare = bincode::deserialize(&bytes)
+            .map_err(|e| Error::Storage(format!("failed to deserialize share '{}': {}", key, e)))?;
+
+        let aad = bincode::serialize(&sealed.header)
+            .map_err(|e| Error::Storage(format!("failed to serialize share header: {}", e)))?;
+        let plaintext = self
+            .cipher
+            .decrypt(
+                Nonce::from_slice(&sealed.nonce),
+                Payload {
+                    msg: &sealed.ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                Error::Storage(format!(
+                    "failed to decrypt share '{}', it was either modified or encrypted under a different key",
+                    key
+                ))
+            })?;
+
+        let header = sealed.header;
+        if header.key != key {
+            return Err(Error::Storage(format!(
+                "share '{}' was saved under key '{}' and cannot be loaded from another key",
+                key, header.key
+            )));
+        }
+        if let Some(role) = &self.role {
+            if &header.role != role {
+                return Err(Error::Storage(format!(
+                    "share '{}' was saved by role {} and cannot be loaded by role {}",
+                    key, header.role, role
+                )));
+            }
+        }
+        self.check_owner(key, &header.owners)?;
+        if let Some(owners) = query.owners {
+            if owners != header.owners {
+                return Err(Error::Storage(format!(
+                    "share '{}' belongs to a placement owned by {:?} rather than {:?}",
+                    key, header.owners, owners
+                )));
+            }
+        }
+        if let Some(ty) = type_hint {
+            if ty != header.ty {
+                return Err(Error::Storage(format!(
+                    "share '{}' has type {} but {} was expected",
+                    key, header.ty, ty
+                )));
+            }
+        }
+
+        let value: Value = bincode::deserialize(&plaintext)
+            .map_err(|e| Error::Storage(format!("failed to deserialize share '{}': {}", key, e)))?;
+        if share_placement(&value)?.owner != header.role {
+            return Err(Error::Storage(format!(
+                "share '{}' is not held by role {} which saved it",
+                key, header.role
+            )));
+        }
+        tracing::debug!(
+            "Loaded share '{}' saved in session {}",
+            key,
+            header.session_id
+        );
+        Ok(value)
+    }
+}
+
+/// Storage keeping shares in encrypted share storage and all other values in another storage.
+///
+/// Values are stored as shares if their key is a path inside the directory of the share
+/// storage, under the path relative to it; this lets a party save its shares alongside values
+/// stored in the clear, e.g. on the filesystem.
+pub struct RoutedShareStorage {
+    shares: EncryptedShareStorage,
+    other: Arc<dyn AsyncStorage + Send + Sync>,
+}
+
+impl RoutedShareStorage {
+    pub fn new(shares: EncryptedShareStorage, other: Arc<dyn AsyncStorage + Send + Sync>) -> Self {
+        RoutedShareStorage { shares, other }
+    }
+
+    /// Key of a share relative to the share directory, if the given key is inside it.
+    fn share_key<'k>(&self, key: &'k str) -> Option<&'k str> {
+        Path::new(key)
+            .strip_prefix(self.shares.directory())
+            .ok()
+            .and_then(|relative| relative.to_str())
+    }
+}
+
+#[async_trait]
+impl AsyncStorage for RoutedShareStorage {
+    async fn save(&self, key: &str, session_id: &SessionId, val: &Value) -> Result<()> {
+        match self.share_key(key) {
+            Some(share_key) => self.shares.save(share_key, session_id, val).await,
+            None => self.other.save(key, session_id, val).await,
+        }
+    }
+
+    async fn load(
+        &self,
+        key: &str,
+        session_id: &SessionId,
+        type_hint: Option<Ty>,
+        query: &str,
+    ) -> Result<Value> {
+        match self.share_key(key) {
+            Some(share_key) => {
+                self.shares
+                    .load(share_key, session_id, type_hint, query)
+                    .await
+            }
+            None => self.other.load(key, session_id, type_hint, query).await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+    use ndarray::array;
+    use std::convert::TryFrom;
+    use tempfile::tempdir;
+
+    fn replicated() -> ReplicatedRing64Tensor {
+        let alice = HostPlacement::from("alice");
+        let rep = ReplicatedPlacement::from(["alice", "bob", "carole"]);
+        let sess = SyncSession::default();
+        let x: HostRing64Tensor = alice.from_raw(array![1u64, 2, 3].into_dyn());
+        rep.share(&sess, &x)
+    }
+
+    /// A share held by the owner of the replicated placement with the given index.
+    fn own_share(owner: usize) -> Value {
+        Value::from(replicated().shares[owner][0].clone())
+    }
+
+    #[tokio::test]
+    async fn test_save_load_share() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let key = [7u8; KEY_LEN];
+        let storage =
+            EncryptedShareStorage::new(dir.path().to_path_buf(), &key).with_role("bob".into());
+
+        let share = own_share(1);
+        storage
+            .save("model/weights", &session_id, &share)
+            .await
+            .unwrap();
+
+        let bytes = std::fs::read(dir.path().join("model/weights")).unwrap();
+        let plaintext = bincode::serialize(&share).unwrap();
+        assert!(!bytes
+            .windows(plaintext.len())
+            .any(|window| window == plaintext.as_slice()));
+
+        let loaded = storage
+            .load(
+                "model/weights",
+                &session_id,
+                Some(Ty::HostRing64Tensor),
+                r#"{"owners": ["bob"]}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(loaded, share);
+
+        let wrong_owners = storage
+            .load(
+                "model/weights",
+                &session_id,
+                None,
+                r#"{"owners": ["alice"]}"#,
+            )
+            .await;
+        assert!(wrong_owners.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_save_load_share_of_replicated_placement() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let storage = EncryptedShareStorage::new(dir.path().to_path_buf(), &[7u8; KEY_LEN])
+            .with_role("bob".into());
+
+        let share = own_share(1);
+        storage
+            .save("x/alice-bob-carole/bob.npy", &session_id, &share)
+            .await
+            .unwrap();
+
+        let loaded = storage
+            .load(
+                "x/alice-bob-carole/bob.npy",
+                &session_id,
+                None,
+                r#"{"owners": ["alice", "bob", "carole"]}"#,
+            )
+            .await
+            .unwrap();
+        assert_eq!(loaded, share);
+
+        let wrong_owners = storage
+            .load(
+                "x/alice-bob-carole/bob.npy",
+                &session_id,
+                None,
+                r#"{"owners": ["bob"]}"#,
+            )
+            .await;
+        assert!(wrong_owners.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reject_share_of_other_party() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let key = [7u8; KEY_LEN];
+        let alice =
+            EncryptedShareStorage::new(dir.path().to_path_buf(), &key).with_role("alice".into());
+        let bob =
+            EncryptedShareStorage::new(dir.path().to_path_buf(), &key).with_role("bob".into());
+        let dave =
+            EncryptedShareStorage::new(dir.path().to_path_buf(), &key).with_role("dave".into());
+
+        alice
+            .save("share", &session_id, &own_share(0))
+            .await
+            .unwrap();
+        assert!(bob.load("share", &session_id, None, "").await.is_err());
+
+        // shares held by other parties cannot be saved
+        let res = bob.save("other", &session_id, &own_share(0)).await;
+        assert!(res.is_err());
+        let res = dave.save("other", &session_id, &own_share(0)).await;
+        assert!(res.is_err());
+        // nor can replicated values, which hold the shares of every owner
+        let res = bob
+            .save("other", &session_id, &Value::from(replicated()))
+            .await;
+        assert!(res.is_err());
+        assert!(!dir.path().join("other").exists());
+    }
+
+    #[tokio::test]
+    async fn test_reject_moved_share() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let storage = EncryptedShareStorage::new(dir.path().to_path_buf(), &[1u8; KEY_LEN])
+            .with_role("alice".into());
+
+        storage
+            .save("x/alice-bob-carole/alice", &session_id, &own_share(0))
+            .await
+            .unwrap();
+        std::fs::create_dir_all(dir.path().join("x/alice-bob-dave")).unwrap();
+        std::fs::copy(
+            dir.path().join("x/alice-bob-carole/alice"),
+            dir.path().join("x/alice-bob-dave/alice"),
+        )
+        .unwrap();
+        let res = storage
+            .load("x/alice-bob-dave/alice", &session_id, None, "")
+            .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reject_wrong_key() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let storage = EncryptedShareStorage::new(dir.path().to_path_buf(), &[1u8; KEY_LEN])
+            .with_role("alice".into());
+        let other = EncryptedShareStorage::new(dir.path().to_path_buf(), &[2u8; KEY_LEN])
+            .with_role("alice".into());
+
+        storage
+            .save("share", &session_id, &own_share(0))
+            .await
+            .unwrap();
+        assert!(other.load("share", &session_id, None, "").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reject_plaintext_values() {
+        let dir = tempdir().unwrap();
+        let session_id = SessionId::try_from("foobar").unwrap();
+        let storage = EncryptedShareStorage::new(dir.path().to_path_buf(), &[1u8; KEY_LEN])
+            .with_role("alice".into());
+
+        let alice = HostPlacement::from("alice");
+        let x: HostFloat64Tensor = alice.from_raw(array![1.0, 2.0].into_dyn());
+        let res = storage.save("x", &session_id, &Value::from(x)).await;
+        assert!(res.is_err());
+        let res = storage.save("../x", &session_id, &own_share(0)).await;
+        assert!(res.is_err());
+    }
+
+    #[cfg(all(feature = "async_execute", feature = "compile"))]
+    #[test]
+    fn test_save_load_across_sessions() {
+        use crate::compilation::{compile, Pass};
+        use crate::execution::{AsyncStorageImpl, AsyncTestRuntime};
+        use crate::storage::filesystem::AsyncFilesystemStorage;
+        use maplit::hashmap;
+        use std::collections::HashMap;
+
+        let dir = tempdir().unwrap();
+        let share_dir = dir.path().join("shares");
+        let key = format!("{}/x.npy", share_dir.display());
+        let runtime = || {
+            let mut runtime = AsyncTestRuntime::new(hashmap!(
+                "alice".to_string() => hashmap!(),
+                "bob".to_string() => hashmap!(),
+                "carole".to_string() => hashmap!(),
+            ));
+            for identity in runtime.identities.iter() {
+                let shares = EncryptedShareStorage::new(share_dir.clone(), &[3u8; KEY_LEN]);
+                let storage: AsyncStorageImpl = Arc::new(RoutedShareStorage::new(
+                    shares,
+                    Arc::new(AsyncFilesystemStorage::default()),
+                ));
+                runtime.runtime_storage.insert(identity.clone(), storage);
+            }
+            runtime
+        };
+
+        let mut save = format!(
+            r#"x = Constant{{value = HostRing64Tensor([1, 2, 3])}}: () -> HostRing64Tensor () @Host(alice)
+            xs = Share: (HostRing64Tensor) -> ReplicatedRing64Tensor (x) @Replicated(alice, bob, carole)
+            key = Constant{{value = HostString("{}")}}: () -> HostString () @Host(alice)"#,
+            key
+        );
+        for owner in ["alice", "bob", "carole"] {
+            save.push_str(&format!(
+                r#"
+            save_{0} = Save: (HostString, ReplicatedRing64Tensor) -> HostUnit (key, xs) @Host({0})
+            saved_{0} = Output{{tag = "saved_{0}"}}: (HostUnit) -> HostUnit (save_{0}) @Host({0})"#,
+                owner
+            ));
+        }
+        let save = compile::<Pass>(save.as_str().try_into().unwrap(), None).unwrap();
+        runtime()
+            .evaluate_computation(&save, HashMap::new())
+            .unwrap();
+
+        // each owner stores its own shares, keeping the extension of the key
+        for owner in ["alice", "bob", "carole"] {
+            let path = share_dir.join(format!("x/alice-bob-carole/{}.npy", owner));
+            assert!(path.exists(), "missing {}", path.display());
+        }
+
+        let load = format!(
+            r#"key = Constant{{value = HostString("{}")}}: () -> HostString () @Host(alice)
+            query = Constant{{value = HostString("{{\"owners\": [\"alice\", \"bob\", \"carole\"]}}")}}: () -> HostString () @Host(alice)
+            xs = Load: (HostString, HostString) -> ReplicatedRing64Tensor (key, query) @Replicated(alice, bob, carole)
+            x = Reveal: (ReplicatedRing64Tensor) -> HostRing64Tensor (xs) @Host(alice)
+            output = Output{{tag = "output"}}: (HostRing64Tensor) -> HostRing64Tensor (x) @Host(alice)"#,
+            key
+        );
+        let load = compile::<Pass>(load.as_str().try_into().unwrap(), None).unwrap();
+        let outputs = runtime()
+            .evaluate_computation(&load, HashMap::new())
+            .unwrap();
+
+        let alice = HostPlacement::from("alice");
+        let expected: HostRing64Tensor = alice.from_raw(array![1u64, 2, 3].into_dyn());
+        assert_eq!(outputs["output"], Value::from(expected));
+    }
+}
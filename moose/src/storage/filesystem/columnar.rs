@@ -0,0 +1,537 @@
+use crate::prelude::*;
+use crate::storage::query::LoadQuery;
+use crate::{Error, Result};
+use arrow::array::{
+    new_empty_array, Array, ArrayRef, BooleanArray, Float64Array, PrimitiveArray, UInt64Array,
+};
+use arrow::compute::{cast, concat, take};
+use arrow::datatypes::{
+    ArrowPrimitiveType, DataType, Field, Float32Type, Float64Type, Int16Type, Int32Type, Int64Type,
+    Int8Type, Schema, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::{RecordBatch, RecordBatchReader};
+use ndarray::prelude::*;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::Arc;
+
+#[allow(dead_code)]
+pub(crate) async fn read_parquet(
+    filename: &str,
+    query: &LoadQuery,
+    placement: &HostPlacement,
+    dtype: Option<Ty>,
+) -> Result<Value> {
+    let file = open(filename)?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(storage_error("failed to read parquet file", filename))?;
+    read_batches(filename, reader, query, placement, dtype)
+}
+
+#[allow(dead_code)]
+pub(crate) async fn write_parquet(filename: &str, data: &Value) -> Result<()> {
+    let batch = value_to_batch(filename, data)?;
+    let file = create(filename)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    writer.write(&batch).map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    writer.close().map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub(crate) async fn read_arrow(
+    filename: &str,
+    query: &LoadQuery,
+    placement: &HostPlacement,
+    dtype: Option<Ty>,
+) -> Result<Value> {
+    let file = open(filename)?;
+    let reader = FileReader::try_new(file, None)
+        .map_err(storage_error("failed to read arrow file", filename))?;
+    read_batches(filename, reader, query, placement, dtype)
+}
+
+#[allow(dead_code)]
+pub(crate) async fn write_arrow(filename: &str, data: &Value) -> Result<()> {
+    let batch = value_to_batch(filename, data)?;
+    let file = create(filename)?;
+    let mut writer = FileWriter::try_new(file, &batch.schema()).map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    writer.write(&batch).map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    writer.finish().map_err(storage_error(
+        "failed to write moose value to file",
+        filename,
+    ))?;
+    Ok(())
+}
+
+fn storage_error<'a, E: std::fmt::Display>(
+    action: &'static str,
+    filename: &'a str,
+) -> impl FnOnce(E) -> Error + 'a {
+    move |e| Error::Storage(format!("{}: '{}': {}", action, filename, e))
+}
+
+fn open(filename: &str) -> Result<File> {
+    File::open(filename).map_err(storage_error("could not open file", filename))
+}
+
+fn create(filename: &str) -> Result<File> {
+    File::create(filename).map_err(storage_error("failed to open file", filename))
+}
+
+/// Read all record batches and turn the selected columns into a two-dimensional tensor.
+///
+/// Without a type hint all selected columns must have the same type; with a type hint the
+/// columns are cast to the corresponding Arrow type instead.
+fn read_batches<R: RecordBatchReader>(
+    filename: &str,
+    reader: R,
+    query: &LoadQuery,
+    placement: &HostPlacement,
+    dtype: Option<Ty>,
+) -> Result<Value> {
+    let schema = reader.schema();
+    let batches = reader
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(storage_error("could not get record batch from", filename))?;
+
+    let column_index = |column: &str| {
+        schema
+            .index_of(column)
+            .map_err(|_| Error::Storage(format!("column '{}' not found in: {}", column, filename)))
+    };
+    let indices = if query.select_columns.is_empty() {
+        (0..schema.fields().len()).collect()
+    } else {
+        let mut indices = query
+            .select_columns
+            .iter()
+            .map(|column| column_index(column))
+            .collect::<Result<Vec<_>>>()?;
+        indices.sort_unstable();
+        indices.dedup();
+        indices
+    };
+    if indices.is_empty() {
+        return Err(Error::Storage(format!(
+            "no columns found for file: {}",
+            filename
+        )));
+    }
+
+    let read_column = |i: usize| {
+        let chunks: Vec<&dyn Array> = batches.iter().map(|b| b.column(i).as_ref()).collect();
+        if chunks.is_empty() {
+            Ok(new_empty_array(schema.field(i).data_type()))
+        } else {
+            concat(&chunks).map_err(storage_error(
+                "could not concatenate columns from",
+                filename,
+            ))
+        }
+    };
+    let mut arrays = indices
+        .iter()
+        .map(|&i| read_column(i))
+        .collect::<Result<Vec<_>>>()?;
+
+    if !query.selects_all_rows() {
+        let mut columns = HashMap::new();
+        for column in query.predicate_columns() {
+            let array = cast(&read_column(column_index(column)?)?, &DataType::Float64)
+                .map_err(storage_error("could not cast column from", filename))?;
+            let array = array
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .ok_or_else(|| {
+                    Error::Storage(format!("unexpected column type in: {}", filename))
+                })?;
+            // missing values are represented as NaN
+            let values = array.iter().map(|x| x.unwrap_or(f64::NAN)).collect();
+            columns.insert(column.to_string(), values);
+        }
+        let nrows = batches.iter().map(|b| b.num_rows()).sum();
+        let rows = query.select_rows(nrows, &columns)?;
+        let rows = UInt64Array::from_iter_values(rows.into_iter().map(|row| row as u64));
+        arrays = arrays
+            .iter()
+            .map(|array| {
+                take(array.as_ref(), &rows, None)
+                    .map_err(storage_error("could not select rows from", filename))
+            })
+            .collect::<Result<Vec<_>>>()?;
+    }
+
+    let dtype = match dtype {
+        Some(dtype) => dtype,
+        None => {
+            let data_type = arrays[0].data_type();
+            if arrays.iter().any(|array| array.data_type() != data_type) {
+                return Err(Error::Storage(format!(
+                    "selected columns of {} have different types, a type hint is required",
+                    filename
+                )));
+            }
+            moose_type(data_type)?
+        }
+    };
+    let data_type = arrow_type(&dtype)?;
+    let arrays = arrays
+        .into_iter()
+        .map(|array| {
+            if array.data_type() == &data_type {
+                Ok(array)
+            } else {
+                cast(&array, &data_type)
+                    .map_err(storage_error("could not cast column from", filename))
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    match dtype {
+        Ty::HostFloat32Tensor => {
+            let tensor: HostFloat32Tensor =
+                placement.from_raw(primitive_matrix::<Float32Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostFloat64Tensor => {
+            let tensor: HostFloat64Tensor =
+                placement.from_raw(primitive_matrix::<Float64Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt8Tensor => {
+            let tensor: HostInt8Tensor =
+                placement.from_raw(primitive_matrix::<Int8Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt16Tensor => {
+            let tensor: HostInt16Tensor =
+                placement.from_raw(primitive_matrix::<Int16Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt32Tensor => {
+            let tensor: HostInt32Tensor =
+                placement.from_raw(primitive_matrix::<Int32Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt64Tensor => {
+            let tensor: HostInt64Tensor =
+                placement.from_raw(primitive_matrix::<Int64Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint8Tensor => {
+            let tensor: HostUint8Tensor =
+                placement.from_raw(primitive_matrix::<UInt8Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint16Tensor => {
+            let tensor: HostUint16Tensor =
+                placement.from_raw(primitive_matrix::<UInt16Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint32Tensor => {
+            let tensor: HostUint32Tensor =
+                placement.from_raw(primitive_matrix::<UInt32Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint64Tensor => {
+            let tensor: HostUint64Tensor =
+                placement.from_raw(primitive_matrix::<UInt64Type>(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostBitTensor => {
+            let tensor: HostBitTensor = placement.from_raw(bool_matrix(filename, &arrays)?);
+            Ok(Value::from(tensor))
+        }
+        _ => Err(Error::Storage(format!(
+            "invalid dtype for columnar storage read: {}",
+            dtype
+        ))),
+    }
+}
+
+fn moose_type(data_type: &DataType) -> Result<Ty> {
+    match data_type {
+        DataType::Boolean => Ok(Ty::HostBitTensor),
+        DataType::Float32 => Ok(Ty::HostFloat32Tensor),
+        DataType::Float64 => Ok(Ty::HostFloat64Tensor),
+        DataType::Int8 => Ok(Ty::HostInt8Tensor),
+        DataType::Int16 => Ok(Ty::HostInt16Tensor),
+        DataType::Int32 => Ok(Ty::HostInt32Tensor),
+        DataType::Int64 => Ok(Ty::HostInt64Tensor),
+        DataType::UInt8 => Ok(Ty::HostUint8Tensor),
+        DataType::UInt16 => Ok(Ty::HostUint16Tensor),
+        DataType::UInt32 => Ok(Ty::HostUint32Tensor),
+        DataType::UInt64 => Ok(Ty::HostUint64Tensor),
+        _ => Err(Error::Storage(format!(
+            "unsupported arrow data type: {}",
+            data_type
+        ))),
+    }
+}
+
+fn arrow_type(dtype: &Ty) -> Result<DataType> {
+    match dtype {
+        Ty::HostBitTensor => Ok(DataType::Boolean),
+        Ty::HostFloat32Tensor => Ok(DataType::Float32),
+        Ty::HostFloat64Tensor => Ok(DataType::Float64),
+        Ty::HostInt8Tensor => Ok(DataType::Int8),
+        Ty::HostInt16Tensor => Ok(DataType::Int16),
+        Ty::HostInt32Tensor => Ok(DataType::Int32),
+        Ty::HostInt64Tensor => Ok(DataType::Int64),
+        Ty::HostUint8Tensor => Ok(DataType::UInt8),
+        Ty::HostUint16Tensor => Ok(DataType::UInt16),
+        Ty::HostUint32Tensor => Ok(DataType::UInt32),
+        Ty::HostUint64Tensor => Ok(DataType::UInt64),
+        _ => Err(Error::Storage(format!(
+            "invalid dtype for columnar storage: {}",
+            dtype
+        ))),
+    }
+}
+
+fn check_nulls(filename: &str, array: &dyn Array) -> Result<()> {
+    if array.null_count() > 0 {
+        return Err(Error::Storage(format!(
+            "cannot load columns with missing values from: {}",
+            filename
+        )));
+    }
+    Ok(())
+}
+
+fn primitive_matrix<A: ArrowPrimitiveType>(
+    filename: &str,
+    arrays: &[ArrayRef],
+) -> Result<Array2<A::Native>> {
+    let columns = arrays
+        .iter()
+        .map(|array| {
+            check_nulls(filename, array.as_ref())?;
+            array
+                .as_any()
+                .downcast_ref::<PrimitiveArray<A>>()
+                .ok_or_else(|| Error::Storage(format!("unexpected column type in: {}", filename)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let nrows = columns[0].len();
+    let matrix = (0..nrows)
+        .flat_map(|row| columns.iter().map(move |column| column.value(row)))
+        .collect();
+    Array2::from_shape_vec((nrows, columns.len()), matrix).map_err(storage_error(
+        "could not convert data to matrix from",
+        filename,
+    ))
+}
+
+fn bool_matrix(filename: &str, arrays: &[ArrayRef]) -> Result<Array2<u8>> {
+    let columns = arrays
+        .iter()
+        .map(|array| {
+            check_nulls(filename, array.as_ref())?;
+            array
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .ok_or_else(|| Error::Storage(format!("unexpected column type in: {}", filename)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let nrows = columns[0].len();
+    let matrix = (0..nrows)
+        .flat_map(|row| {
+            columns
+                .iter()
+                .map(move |column| u8::from(column.value(row)))
+        })
+        .collect();
+    Array2::from_shape_vec((nrows, columns.len()), matrix).map_err(storage_error(
+        "could not convert data to matrix from",
+        filename,
+    ))
+}
+
+/// Split a tensor of one or two dimensions into its columns.
+fn split_columns<T: Clone>(array: ArrayViewD<T>) -> Result<Vec<Vec<T>>> {
+    match array.ndim() {
+        1 => Ok(vec![array.iter().cloned().collect()]),
+        2 => Ok(array
+            .axis_iter(Axis(1))
+            .map(|column| column.iter().cloned().collect())
+            .collect()),
+        _ => Err(Error::Storage(format!(
+            "can only save tensors of 1 or 2 dimensions to columnar files, got shape: {:?}",
+            array.shape()
+        ))),
+    }
+}
+
+fn primitive_columns<A: ArrowPrimitiveType>(array: ArrayViewD<A::Native>) -> Result<Vec<ArrayRef>> {
+    Ok(split_columns(array)?
+        .into_iter()
+        .map(|column| Arc::new(PrimitiveArray::<A>::from_iter_values(column)) as ArrayRef)
+        .collect())
+}
+
+fn bool_columns(array: ArrayViewD<u8>) -> Result<Vec<ArrayRef>> {
+    Ok(split_columns(array)?
+        .into_iter()
+        .map(|column| {
+            let column: Vec<bool> = column.into_iter().map(|item| item != 0).collect();
+            Arc::new(BooleanArray::from(column)) as ArrayRef
+        })
+        .collect())
+}
+
+/// Turn a tensor into a record batch with columns named `col_0`, `col_1`, etc.
+fn value_to_batch(filename: &str, data: &Value) -> Result<RecordBatch> {
+    let arrays = match data {
+        Value::HostFloat32Tensor(t) => primitive_columns::<Float32Type>(t.0.view()),
+        Value::HostFloat64Tensor(t) => primitive_columns::<Float64Type>(t.0.view()),
+        Value::HostInt8Tensor(t) => primitive_columns::<Int8Type>(t.0.view()),
+        Value::HostInt16Tensor(t) => primitive_columns::<Int16Type>(t.0.view()),
+        Value::HostInt32Tensor(t) => primitive_columns::<Int32Type>(t.0.view()),
+        Value::HostInt64Tensor(t) => primitive_columns::<Int64Type>(t.0.view()),
+        Value::HostUint8Tensor(t) => primitive_columns::<UInt8Type>(t.0.view()),
+        Value::HostUint16Tensor(t) => primitive_columns::<UInt16Type>(t.0.view()),
+        Value::HostUint32Tensor(t) => primitive_columns::<UInt32Type>(t.0.view()),
+        Value::HostUint64Tensor(t) => primitive_columns::<UInt64Type>(t.0.view()),
+        Value::HostBitTensor(t) => {
+            let array = t.0.into_array::<u8>().map_err(|e| {
+                Error::Storage(format!("could not convert bit tensor to array: {}", e))
+            })?;
+            bool_columns(array.view())
+        }
+        _ => {
+            return Err(Error::Storage(format!(
+                "cannot write unsupported tensor to columnar file: {}",
+                filename
+            )))
+        }
+    }?;
+    let fields = arrays
+        .iter()
+        .enumerate()
+        .map(|(i, array)| Field::new(&format!("col_{}", i), array.data_type().clone(), false))
+        .collect();
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays).map_err(storage_error(
+        "failed to create record batch for file",
+        filename,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_write_read_parquet() {
+        let plc = HostPlacement::from("host");
+        let tensor: HostFloat64Tensor = plc.from_raw(array![[1.1, 2.2], [3.3, 4.4], [5.5, 6.6]]);
+        let expected = Value::from(tensor);
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data.parquet");
+        let filename = path.to_str().unwrap();
+
+        write_parquet(filename, &expected).await.unwrap();
+        let data = read_parquet(filename, &LoadQuery::default(), &plc, None)
+            .await
+            .unwrap();
+        assert_eq!(data, expected);
+
+        let columns = LoadQuery::parse(r#"{"select_columns": ["col_1"]}"#).unwrap();
+        let data = read_parquet(filename, &columns, &plc, None).await.unwrap();
+        let tensor: HostFloat64Tensor = plc.from_raw(array![[2.2], [4.4], [6.6]]);
+        assert_eq!(data, Value::from(tensor));
+    }
+
+    #[tokio::test]
+    async fn test_write_read_arrow_bits() {
+        let plc = HostPlacement::from("host");
+        let tensor: HostBitTensor = plc.from_raw(array![[1u8, 0], [0, 1], [1, 1]]);
+        let expected = Value::from(tensor);
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data.arrow");
+        let filename = path.to_str().unwrap();
+
+        write_arrow(filename, &expected).await.unwrap();
+        let data = read_arrow(filename, &LoadQuery::default(), &plc, None)
+            .await
+            .unwrap();
+        assert_eq!(data, expected);
+    }
+
+    #[tokio::test]
+    async fn test_read_arrow_mixed_columns() {
+        let plc = HostPlacement::from("host");
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("age", DataType::Int32, false),
+            Field::new("income", DataType::Float64, false),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(PrimitiveArray::<Int32Type>::from_iter_values(vec![1, 2])) as ArrayRef,
+                Arc::new(PrimitiveArray::<Int32Type>::from_iter_values(vec![30, 40])),
+                Arc::new(PrimitiveArray::<Float64Type>::from_iter_values(vec![
+                    1000.5, 2000.5,
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("data.arrow");
+        let filename = path.to_str().unwrap();
+        let mut writer = FileWriter::try_new(File::create(&path).unwrap(), &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        // columns are returned in file order, not in the order selected
+        let columns = LoadQuery::parse(r#"{"select_columns": ["age", "id"]}"#).unwrap();
+        let data = read_arrow(filename, &columns, &plc, None).await.unwrap();
+        let tensor: HostInt32Tensor = plc.from_raw(array![[1, 30], [2, 40]]);
+        assert_eq!(data, Value::from(tensor));
+
+        // columns of different types need a type hint
+        let columns = LoadQuery::parse(r#"{"select_columns": ["age", "income"]}"#).unwrap();
+        assert!(read_arrow(filename, &columns, &plc, None).await.is_err());
+        let data = read_arrow(filename, &columns, &plc, Some(Ty::HostFloat64Tensor))
+            .await
+            .unwrap();
+        let tensor: HostFloat64Tensor = plc.from_raw(array![[30.0, 1000.5], [40.0, 2000.5]]);
+        assert_eq!(data, Value::from(tensor));
+
+        // rows are filtered on columns that need not be selected
+        let query = LoadQuery::parse(
+            r#"{"select_columns": ["id"], "where": [{"column": "income", "op": ">", "value": 1500}]}"#,
+        )
+        .unwrap();
+        let data = read_arrow(filename, &query, &plc, None).await.unwrap();
+        let tensor: HostInt32Tensor = plc.from_raw(array![[2]]);
+        assert_eq!(data, Value::from(tensor));
+    }
+}
@@ -1,29 +1,98 @@
 use crate::prelude::*;
+use crate::storage::query::LoadQuery;
 use crate::{Error, Result};
 use csv::WriterBuilder;
 use ndarray::prelude::*;
 use ndarray::ArcArray;
-use serde::Serialize;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fs::File;
+use std::str::FromStr;
+
+/// Options for reading CSV files, given in the JSON query next to `select_columns`.
+///
+/// For example `{"delimiter": ";", "has_headers": false, "missing": {"fill": 0}}`. The columns
+/// of files without headers are named `col_0`, `col_1`, etc., as when writing.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct CsvOptions {
+    pub delimiter: char,
+    pub has_headers: bool,
+    pub missing: MissingValues,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            has_headers: true,
+            missing: MissingValues::Error,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub(crate) fn from_query(query: &str) -> Result<CsvOptions> {
+        match query {
+            "" => Ok(CsvOptions::default()),
+            query_str => serde_json::from_str(query_str).map_err(|e| {
+                Error::Storage(format!("failed to parse csv options from query: {}", e))
+            }),
+        }
+    }
+}
+
+/// Handling of empty cells in the selected columns.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MissingValues {
+    /// Fail with the line and column of the first empty cell in the rows returned.
+    Error,
+    /// Skip rows containing empty cells.
+    Drop,
+    /// Replace empty cells with the given value.
+    Fill(serde_json::Value),
+}
 
 #[allow(dead_code)]
 pub(crate) async fn read_csv(
     filename: &str,
-    columns: &[String],
+    query: &LoadQuery,
     placement: &HostPlacement,
+    dtype: Option<Ty>,
+    options: &CsvOptions,
 ) -> Result<Value> {
-    let include_columns: HashSet<&String> = columns.iter().collect();
+    let delimiter = u8::try_from(options.delimiter)
+        .ok()
+        .filter(u8::is_ascii)
+        .ok_or_else(|| {
+            Error::Storage(format!(
+                "csv delimiter must be a single ascii character, got: '{}'",
+                options.delimiter
+            ))
+        })?;
 
-    let mut reader = csv::Reader::from_path(filename)
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(options.has_headers)
+        .from_path(filename)
         .map_err(|e| Error::Storage(format!("could not open file: {}: {}", filename, e)))?;
 
-    let headers: Vec<String> = reader
+    // without headers this is the first record, which is not consumed
+    let first_record = reader
         .headers()
-        .map_err(|e| Error::Storage(format!("could not get headers from: {}: {}", filename, e)))?
-        .into_iter()
-        .map(|header| header.to_string())
-        .collect();
+        .map_err(|e| Error::Storage(format!("could not get headers from: {}: {}", filename, e)))?;
+    let headers: Vec<String> = if options.has_headers {
+        first_record
+            .into_iter()
+            .map(|header| header.to_string())
+            .collect()
+    } else {
+        (0..first_record.len())
+            .map(|i| format!("col_{}", i))
+            .collect()
+    };
     if headers.is_empty() {
         return Err(Error::Storage(format!(
             "no columns found for file: {}",
@@ -31,35 +100,255 @@ pub(crate) async fn read_csv(
         )));
     }
 
-    let mut matrix: Vec<f64> = Vec::new();
-    let mut nrows = 0;
-    let mut ncols = 0;
-    for record in reader.records() {
-        nrows += 1;
-        let record = record.map_err(|e| {
-            Error::Storage(format!("could not get record from: {}: {}", filename, e))
-        })?;
-        for (header, value) in headers.iter().zip(record.iter()) {
-            if include_columns.contains(header) || include_columns.is_empty() {
-                if nrows == 1 {
-                    // i.e., only count number of cols for the first row
-                    ncols += 1;
-                }
-                let value = value.parse::<f64>().map_err(|e| {
-                    Error::Storage(format!("could not parse '{}' to f64: {}", value, e))
+    let column_index = |column: &str| {
+        headers
+            .iter()
+            .position(|header| header == column)
+            .ok_or_else(|| {
+                Error::Storage(format!("column '{}' not found in: {}", column, filename))
+            })
+    };
+    for column in query.select_columns.iter() {
+        column_index(column)?;
+    }
+    let predicates = query
+        .predicate_columns()
+        .map(|column| Ok((column.to_string(), column_index(column)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    let include_columns: HashSet<&String> = query.select_columns.iter().collect();
+    let selected: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, header)| include_columns.contains(header) || include_columns.is_empty())
+        .map(|(i, _)| i)
+        .collect();
+
+    let table = CsvTable {
+        filename,
+        reader,
+        headers,
+        selected,
+        predicates,
+        query,
+    };
+    let dtype = dtype.unwrap_or(Ty::HostFloat64Tensor);
+    match dtype {
+        Ty::HostFloat64Tensor => {
+            let tensor: HostFloat64Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<f64>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostFloat32Tensor => {
+            let tensor: HostFloat32Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<f32>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt8Tensor => {
+            let tensor: HostInt8Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<i8>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt16Tensor => {
+            let tensor: HostInt16Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<i16>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt32Tensor => {
+            let tensor: HostInt32Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<i32>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostInt64Tensor => {
+            let tensor: HostInt64Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<i64>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint8Tensor => {
+            let tensor: HostUint8Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<u8>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint16Tensor => {
+            let tensor: HostUint16Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<u16>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint32Tensor => {
+            let tensor: HostUint32Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<u32>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostUint64Tensor => {
+            let tensor: HostUint64Tensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_number::<u64>)?);
+            Ok(Value::from(tensor))
+        }
+        Ty::HostBitTensor => {
+            let tensor: HostBitTensor =
+                placement.from_raw(table.read(&options.missing, &dtype, parse_bit)?);
+            Ok(Value::from(tensor))
+        }
+        _ => Err(Error::Storage(format!(
+            "invalid dtype for csv storage read: {}",
+            dtype
+        ))),
+    }
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Option<T> {
+    value.parse().ok()
+}
+
+fn parse_bit(value: &str) -> Option<u8> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" => Some(1),
+        "0" | "false" => Some(0),
+        _ => None,
+    }
+}
+
+struct CsvTable<'a> {
+    filename: &'a str,
+    reader: csv::Reader<File>,
+    headers: Vec<String>,
+    selected: Vec<usize>,
+    /// Indices of the columns used in predicates, which need not be selected.
+    predicates: HashMap<String, usize>,
+    query: &'a LoadQuery,
+}
+
+impl<'a> CsvTable<'a> {
+    /// Parse the selected columns of the records chosen by the query into a matrix.
+    ///
+    /// Cells are only parsed for records in the range of stored rows selected by the query.
+    /// Cells that cannot be parsed are an error anywhere in that range, while missing values
+    /// are only an error in the rows returned.
+    fn read<T: Clone>(
+        self,
+        missing: &MissingValues,
+        dtype: &Ty,
+        parse: fn(&str) -> Option<T>,
+    ) -> Result<Array2<T>> {
+        let CsvTable {
+            filename,
+            mut reader,
+            headers,
+            selected,
+            predicates,
+            query,
+        } = self;
+        let fill = match missing {
+            MissingValues::Fill(value) => {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                let fill = parse(&value).ok_or_else(|| {
+                    Error::Storage(format!(
+                        "could not parse fill value '{}' as {}",
+                        value, dtype
+                    ))
+                })?;
+                Some(fill)
+            }
+            _ => None,
+        };
+
+        // missing values are only an error in rows that end up being returned, so until rows
+        // are selected they are kept as `None` along with the line of each row
+        let ncols = selected.len();
+        let mut cells: Vec<Option<T>> = Vec::new();
+        let mut lines: Vec<u64> = Vec::new();
+        let mut predicate_values: HashMap<String, Vec<f64>> = HashMap::new();
+        // rows with missing values may be dropped, so the range of rows selected by the query
+        // is matched against the index of each row among all stored rows
+        let mut stored: Vec<usize> = Vec::new();
+        let mut nstored = 0;
+        'records: for record in reader.records() {
+            if query.rows_end().map_or(false, |end| nstored >= end) {
+                // no later rows can be selected
+                break;
+            }
+            let index = nstored;
+            nstored += 1;
+            let record = record.map_err(|e| {
+                Error::Storage(format!("could not get record from: {}: {}", filename, e))
+            })?;
+            if index < query.rows_start() {
+                // no earlier rows can be selected either, so their cells are never parsed
+                continue;
+            }
+            let line = record.position().map_or(0, |position| position.line());
+            let row_start = cells.len();
+            for &i in selected.iter() {
+                let cell = record.get(i).unwrap_or_default().trim();
+                let value = if cell.is_empty() {
+                    match missing {
+                        MissingValues::Drop => {
+                            cells.truncate(row_start);
+                            continue 'records;
+                        }
+                        _ => fill.clone(),
+                    }
+                } else {
+                    Some(parse(cell).ok_or_else(|| {
+                        Error::Storage(format!(
+                            "could not parse '{}' on line {} in column '{}' as {}",
+                            cell, line, headers[i], dtype
+                        ))
+                    })?)
+                };
+                cells.push(value);
+            }
+            for (column, &i) in predicates.iter() {
+                let cell = record.get(i).unwrap_or_default().trim();
+                let value = if cell.is_empty() {
+                    f64::NAN
+                } else {
+                    parse_number::<f64>(cell)
+                        .or_else(|| parse_bit(cell).map(f64::from))
+                        .ok_or_else(|| {
+                            Error::Storage(format!(
+                                "could not parse '{}' on line {} in column '{}' as a number",
+                                cell, line, column
+                            ))
+                        })?
+                };
+                predicate_values
+                    .entry(column.clone())
+                    .or_default()
+                    .push(value);
+            }
+            stored.push(index);
+            lines.push(line);
+        }
+
+        let rows = if query.selects_all_rows() {
+            (0..stored.len()).collect()
+        } else {
+            query.select_remaining_rows(&stored, &predicate_values)?
+        };
+        let mut matrix: Vec<T> = Vec::with_capacity(rows.len() * ncols);
+        for &row in rows.iter() {
+            let row_cells = &cells[row * ncols..(row + 1) * ncols];
+            for (cell, &i) in row_cells.iter().zip(selected.iter()) {
+                let value = cell.clone().ok_or_else(|| {
+                    Error::Storage(format!(
+                        "missing value on line {} in column '{}' of: {}",
+                        lines[row], headers[i], filename
+                    ))
                 })?;
                 matrix.push(value);
             }
         }
+        Array2::from_shape_vec((rows.len(), ncols), matrix).map_err(|e| {
+            Error::Storage(format!(
+                "could not convert data from: {} to matrix: {}",
+                filename, e
+            ))
+        })
     }
-    let ndarr: Array2<f64> = Array2::from_shape_vec((nrows, ncols), matrix).map_err(|e| {
-        Error::Storage(format!(
-            "could not convert data from: {} to matrix: {}",
-            filename, e
-        ))
-    })?;
-    let tensor: HostFloat64Tensor = placement.from_raw(ndarr);
-    Ok(Value::from(tensor))
 }
 
 #[allow(dead_code)]
@@ -114,6 +403,49 @@ pub(crate) async fn write_csv(filename: &str, data: &Value) -> Result<()> {
                 ))
             })?;
         }
+        Value::HostInt8Tensor(t) => {
+            write_array_to_csv(filename, &t.0).map_err(|e| {
+                Error::Storage(format!(
+                    "failed to write moose value to file: '{}': {}",
+                    filename, e
+                ))
+            })?;
+        }
+        Value::HostInt16Tensor(t) => {
+            write_array_to_csv(filename, &t.0).map_err(|e| {
+                Error::Storage(format!(
+                    "failed to write moose value to file: '{}': {}",
+                    filename, e
+                ))
+            })?;
+        }
+        Value::HostUint8Tensor(t) => {
+            write_array_to_csv(filename, &t.0).map_err(|e| {
+                Error::Storage(format!(
+                    "failed to write moose value to file: '{}': {}",
+                    filename, e
+                ))
+            })?;
+        }
+        Value::HostUint16Tensor(t) => {
+            write_array_to_csv(filename, &t.0).map_err(|e| {
+                Error::Storage(format!(
+                    "failed to write moose value to file: '{}': {}",
+                    filename, e
+                ))
+            })?;
+        }
+        Value::HostBitTensor(t) => {
+            let array = t.0.into_array::<u8>().map_err(|e| {
+                Error::Storage(format!("could not convert bit tensor to array: {}", e))
+            })?;
+            write_array_to_csv(filename, &array.into_shared()).map_err(|e| {
+                Error::Storage(format!(
+                    "failed to write moose value to file: '{}': {}",
+                    filename, e
+                ))
+            })?;
+        }
         _ => {
             return Err(Error::Storage(format!(
                 "cannot write unsupported tensor to csv file: {}",
@@ -214,7 +546,15 @@ mod tests {
             .to_string();
 
         let plc = HostPlacement::from("host");
-        let data = read_csv(&filename, &[], &plc).await.unwrap();
+        let data = read_csv(
+            &filename,
+            &LoadQuery::default(),
+            &plc,
+            None,
+            &CsvOptions::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(data, expected);
     }
 
@@ -234,7 +574,188 @@ mod tests {
 
         write_csv(&filename, &expected).await.unwrap();
 
-        let data = read_csv(&filename, &[], &plc).await.unwrap();
+        let data = read_csv(
+            &filename,
+            &LoadQuery::default(),
+            &plc,
+            None,
+            &CsvOptions::default(),
+        )
+        .await
+        .unwrap();
         assert_eq!(data, expected);
     }
+
+    fn select(columns: &[&str]) -> LoadQuery {
+        LoadQuery {
+            select_columns: columns.iter().map(|column| column.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn csv_file(data: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("trying to create tempfile");
+        file.write_all(data.as_bytes()).unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn test_read_csv_typed() {
+        let plc = HostPlacement::from("host");
+        let file = csv_file(concat!("a;b;c\n", "1;true;-3\n", "4;false;6\n"));
+        let filename = file.path().to_str().unwrap();
+        let options = CsvOptions::from_query(r#"{"delimiter": ";"}"#).unwrap();
+
+        let columns = select(&["a", "c"]);
+        let data = read_csv(
+            filename,
+            &columns,
+            &plc,
+            Some(Ty::HostInt32Tensor),
+            &options,
+        )
+        .await
+        .unwrap();
+        let expected: HostInt32Tensor = plc.from_raw(array![[1, -3], [4, 6]]);
+        assert_eq!(data, Value::from(expected));
+
+        let columns = select(&["b"]);
+        let data = read_csv(filename, &columns, &plc, Some(Ty::HostBitTensor), &options)
+            .await
+            .unwrap();
+        let expected: HostBitTensor = plc.from_raw(array![[1u8], [0]]);
+        assert_eq!(data, Value::from(expected));
+
+        // negative values do not fit unsigned integers
+        let columns = select(&["c"]);
+        let res = read_csv(
+            filename,
+            &columns,
+            &plc,
+            Some(Ty::HostUint32Tensor),
+            &options,
+        )
+        .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_csv_missing_values() {
+        let plc = HostPlacement::from("host");
+        let file = csv_file(concat!("1,2\n", "3,\n", "5,6\n"));
+        let filename = file.path().to_str().unwrap();
+
+        let options = CsvOptions::from_query(r#"{"has_headers": false}"#).unwrap();
+        let res = read_csv(filename, &LoadQuery::default(), &plc, None, &options).await;
+        match res {
+            Err(Error::Storage(msg)) => {
+                assert!(msg.contains("line 2 in column 'col_1'"), "{}", msg)
+            }
+            _ => panic!("expected missing value error"),
+        }
+
+        let query = r#"{"has_headers": false, "missing": "drop"}"#;
+        let options = CsvOptions::from_query(query).unwrap();
+        let data = read_csv(
+            filename,
+            &LoadQuery::default(),
+            &plc,
+            Some(Ty::HostInt64Tensor),
+            &options,
+        )
+        .await
+        .unwrap();
+        let expected: HostInt64Tensor = plc.from_raw(array![[1, 2], [5, 6]]);
+        assert_eq!(data, Value::from(expected));
+
+        // the range of rows refers to stored rows, including those dropped
+        let query = r#"{"has_headers": false, "missing": "drop", "rows": {"end": 2}}"#;
+        let options = CsvOptions::from_query(query).unwrap();
+        let data = read_csv(
+            filename,
+            &LoadQuery::parse(query).unwrap(),
+            &plc,
+            Some(Ty::HostInt64Tensor),
+            &options,
+        )
+        .await
+        .unwrap();
+        let expected: HostInt64Tensor = plc.from_raw(array![[1, 2]]);
+        assert_eq!(data, Value::from(expected));
+
+        // missing values are fine in rows that are not returned
+        let options = CsvOptions::from_query(r#"{"has_headers": false}"#).unwrap();
+        for query in [
+            r#"{"rows": {"start": 2}}"#,
+            r#"{"where": [{"column": "col_0", "op": "!=", "value": 3}]}"#,
+            r#"{"limit": 1}"#,
+        ] {
+            let res = read_csv(
+                filename,
+                &LoadQuery::parse(query).unwrap(),
+                &plc,
+                Some(Ty::HostInt64Tensor),
+                &options,
+            )
+            .await;
+            assert!(res.is_ok(), "{}", query);
+        }
+
+        let query = r#"{"has_headers": false, "missing": {"fill": 0}}"#;
+        let options = CsvOptions::from_query(query).unwrap();
+        let data = read_csv(
+            filename,
+            &LoadQuery::default(),
+            &plc,
+            Some(Ty::HostInt64Tensor),
+            &options,
+        )
+        .await
+        .unwrap();
+        let expected: HostInt64Tensor = plc.from_raw(array![[1, 2], [3, 0], [5, 6]]);
+        assert_eq!(data, Value::from(expected));
+    }
+
+    #[tokio::test]
+    async fn test_read_csv_query() {
+        let plc = HostPlacement::from("host");
+        let file = csv_file(concat!(
+            "id,age,income\n",
+            "1,17,10.0\n",
+            "2,25,20.0\n",
+            "3,,30.0\n",
+            "4,40,40.0\n",
+            "5,33,50.0\n",
+            "6,70,60.0\n",
+        ));
+        let filename = file.path().to_str().unwrap();
+        let options = CsvOptions::default();
+
+        // predicates may use columns that are not selected, and missing values never match
+        let query = LoadQuery::parse(
+            r#"{"select_columns": ["id", "income"], "rows": {"end": 5},
+                "where": [{"column": "age", "op": ">=", "value": 18}], "offset": 1}"#,
+        )
+        .unwrap();
+        let data = read_csv(filename, &query, &plc, None, &options)
+            .await
+            .unwrap();
+        let expected: HostFloat64Tensor = plc.from_raw(array![[4.0, 40.0], [5.0, 50.0]]);
+        assert_eq!(data, Value::from(expected));
+
+        let query =
+            LoadQuery::parse(r#"{"select_columns": ["id"], "sample": {"size": 3, "seed": 1}}"#)
+                .unwrap();
+        let first = read_csv(filename, &query, &plc, None, &options)
+            .await
+            .unwrap();
+        let second = read_csv(filename, &query, &plc, None, &options)
+            .await
+            .unwrap();
+        assert_eq!(first, second);
+        match first {
+            Value::HostFloat64Tensor(t) => assert_eq!(t.0.shape(), &[3, 1]),
+            _ => panic!("expected float tensor"),
+        }
+    }
 }
@@ -1,12 +1,15 @@
 //! Filesystem-based storage implementation.
 
+pub(crate) mod columnar;
 pub(crate) mod csv;
 pub(crate) mod numpy;
 
-use self::csv::{read_csv, write_csv};
-use self::numpy::{read_numpy, write_numpy};
+use self::columnar::{read_arrow, read_parquet, write_arrow, write_parquet};
+use self::csv::{read_csv, write_csv, CsvOptions};
+use self::numpy::{read_npz, read_numpy, write_npz, write_numpy, NpzOptions};
 use crate::error::Error;
 use crate::prelude::*;
+use crate::storage::query::LoadQuery;
 use crate::storage::AsyncStorage;
 use crate::Result;
 use async_trait::async_trait;
@@ -25,8 +28,11 @@ impl AsyncStorage for AsyncFilesystemStorage {
         match extension.to_str() {
             Some("csv") => write_csv(key, val).await,
             Some("npy") => write_numpy(key, val).await,
+            Some("npz") => write_npz(key, val).await,
+            Some("parquet") => write_parquet(key, val).await,
+            Some("arrow") => write_arrow(key, val).await,
             _ => Err(Error::Storage(format!(
-                "key must provide an extension of either '.csv' or '.npy', got: {}",
+                "key must provide an extension of either '.csv', '.npy', '.npz', '.parquet' or '.arrow', got: {}",
                 key
             ))),
         }
@@ -44,45 +50,27 @@ impl AsyncStorage for AsyncFilesystemStorage {
             .extension()
             .ok_or_else(|| Error::Storage(format!("failed to get extension from key: {}", key)))?;
         let plc = HostPlacement::from("host");
+        let load_query = LoadQuery::parse(query)?;
         match extension.to_str() {
             Some("csv") => {
-                let query = parse_columns(query)?;
-                read_csv(key, &query, &plc).await
+                let options = CsvOptions::from_query(query)?;
+                read_csv(key, &load_query, &plc, type_hint, &options).await
             }
-            Some("npy") => read_numpy(key, &plc, type_hint).await,
+            Some("npy") => read_numpy(key, &load_query, &plc, type_hint).await,
+            Some("npz") => {
+                let options = NpzOptions::from_query(query)?;
+                read_npz(key, &load_query, &options, &plc, type_hint).await
+            }
+            Some("parquet") => read_parquet(key, &load_query, &plc, type_hint).await,
+            Some("arrow") => read_arrow(key, &load_query, &plc, type_hint).await,
             _ => Err(Error::Storage(format!(
-                "key must provide an extension of either '.csv' or '.npy', got: {}",
+                "key must provide an extension of either '.csv', '.npy', '.npz', '.parquet' or '.arrow', got: {}",
                 key
             ))),
         }
     }
 }
 
-fn parse_columns(query: &str) -> Result<Vec<String>> {
-    match query {
-        "" => Ok(Vec::new()),
-        query_str => {
-            let jsn: serde_json::Value = serde_json::from_str(query_str)
-                .map_err(|e| Error::Storage(format!("failed to parse query as json: {}", e)))?;
-            let as_vec = match &jsn.get("select_columns") {
-                Some(serde_json::Value::Array(v)) => v.to_vec(),
-                _ => Vec::new(),
-            };
-            let select_columns: Result<Vec<String>> = as_vec
-                .iter()
-                .map(|i| match i {
-                    serde_json::Value::String(s) => Ok(s.to_string()),
-                    _ => Err(Error::Storage(
-                        "select_columns must contain an array of strings of column names"
-                            .to_string(),
-                    )),
-                })
-                .collect();
-            select_columns
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +144,34 @@ mod tests {
             .unwrap();
         assert_eq!(data, expected);
     }
+
+    #[tokio::test]
+    async fn test_select_columns_in_file_order() {
+        let storage = AsyncFilesystemStorage::default();
+
+        let plc = HostPlacement::from("host");
+        let tensor: HostFloat64Tensor = plc.from_raw(array![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let value = Value::from(tensor);
+        let expected: HostFloat64Tensor = plc.from_raw(array![[1.0, 3.0], [4.0, 6.0]]);
+
+        let temp_dir = tempdir().unwrap();
+        let session_id_str = "01FGSQ37YDJSVJXSA6SSY7G4Y2";
+        let session_id = SessionId::try_from(session_id_str).unwrap();
+        // all formats return the selected columns in the same order
+        for extension in ["csv", "npy", "parquet", "arrow"] {
+            let path = temp_dir.path().join(format!("data.{}", extension));
+            let filename = path
+                .to_str()
+                .expect("trying to get path from temp file")
+                .to_string();
+            storage.save(&filename, &session_id, &value).await.unwrap();
+
+            let query = r#"{"select_columns": ["col_2", "col_0"]}"#;
+            let data = storage
+                .load(&filename, &session_id, Some(Ty::HostFloat64Tensor), query)
+                .await
+                .unwrap();
+            assert_eq!(data, Value::from(expected.clone()), "{}", extension);
+        }
+    }
 }
@@ -1,297 +1,388 @@
 use crate::prelude::*;
+use crate::storage::query::{query_array, LoadQuery};
 use crate::{Error, Result};
-use ndarray::ArrayD;
-use ndarray_npy::{read_npy, write_npy};
+use ndarray::{ArrayD, ArrayViewD};
+use ndarray_npy::{write_npy, NpzWriter, ReadNpyExt, ReadableElement, WritableElement};
+use serde::Deserialize;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+
+/// Name under which arrays are saved in `.npz` archives, as done by `numpy.savez`.
+const NPZ_ARRAY_NAME: &str = "arr_0";
+
+/// Largest array read from a `.npz` archive, whose members may decompress to much more than
+/// the size of the archive.
+const MAX_NPZ_MEMBER_SIZE: u64 = 1 << 32;
+
+/// Options for reading `.npz` archives, given in the JSON query.
+///
+/// For example `{"array": "x"}` loads the array saved as `x`. The array may be omitted if the
+/// archive contains a single array.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct NpzOptions {
+    pub array: Option<String>,
+}
+
+impl NpzOptions {
+    pub(crate) fn from_query(query: &str) -> Result<NpzOptions> {
+        match query {
+            "" => Ok(NpzOptions::default()),
+            query_str => serde_json::from_str(query_str).map_err(|e| {
+                Error::Storage(format!("failed to parse npz options from query: {}", e))
+            }),
+        }
+    }
+}
 
 #[allow(dead_code)]
 pub(crate) async fn read_numpy(
     filename: &str,
+    query: &LoadQuery,
     placement: &HostPlacement,
     dtype: Option<Ty>,
 ) -> Result<Value> {
-    let dtype = match dtype {
-        Some(dtype) => Ok(dtype),
-        None => extract_dtype(filename).map_err(|e| {
-            Error::Storage(format!(
-                "parsing failure from numpy data file: {}: {}",
-                filename, e
-            ))
-        }),
-    }?;
-    match dtype {
-        Ty::HostFloat64Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
+    let file = File::open(filename).map_err(|e| {
+        Error::Storage(format!(
+            "failed to open numpy data file for reading: {}: {}",
+            filename, e
+        ))
+    })?;
+    read_array(filename, BufReader::new(file), query, placement, dtype)
+}
+
+#[allow(dead_code)]
+pub(crate) async fn read_npz(
+    filename: &str,
+    query: &LoadQuery,
+    options: &NpzOptions,
+    placement: &HostPlacement,
+    dtype: Option<Ty>,
+) -> Result<Value> {
+    let file = File::open(filename).map_err(|e| {
+        Error::Storage(format!(
+            "failed to open numpy archive for reading: {}: {}",
+            filename, e
+        ))
+    })?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| {
+        Error::Storage(format!("failed to read numpy archive: {}: {}", filename, e))
+    })?;
+
+    // numpy stores arrays as members named after the array with an `.npy` extension
+    let member_name = match &options.array {
+        Some(name) => archive
+            .file_names()
+            .find(|member| {
+                *member == name.as_str() || member.strip_suffix(".npy") == Some(name.as_str())
+            })
+            .map(String::from)
+            .ok_or_else(|| {
                 Error::Storage(format!(
-                    "failed to read numpy data file: {}: {}",
-                    filename, e
+                    "array '{}' not found in numpy archive: {}",
+                    name, filename
                 ))
-            })?;
-            let tensor: HostFloat64Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
+            })?,
+        None => {
+            let members: Vec<&str> = archive.file_names().collect();
+            match members.as_slice() {
+                [member] => member.to_string(),
+                _ => {
+                    return Err(Error::Storage(format!(
+                        "numpy archive {} contains {} arrays, select one with the query: {:?}",
+                        filename,
+                        members.len(),
+                        members
+                    )))
+                }
+            }
         }
-        Ty::HostFloat32Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
+    };
+
+    let member_filename = format!("{}:{}", filename, member_name);
+    let member = archive.by_name(&member_name).map_err(|e| {
+        Error::Storage(format!(
+            "failed to read '{}' from numpy archive: {}: {}",
+            member_name, filename, e
+        ))
+    })?;
+    let bytes = read_bounded(&member_filename, member, MAX_NPZ_MEMBER_SIZE)?;
+    read_array(
+        &member_filename,
+        Cursor::new(bytes),
+        query,
+        placement,
+        dtype,
+    )
+}
+
+/// Read everything from a reader, failing once more than `max_size` bytes have been read.
+fn read_bounded<R: Read>(filename: &str, reader: R, max_size: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(|e| Error::Storage(format!("failed to read: {}: {}", filename, e)))?;
+    if bytes.len() as u64 > max_size {
+        return Err(Error::Storage(format!(
+            "{} is larger than {} bytes",
+            filename, max_size
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Read an array in the numpy format, taking its dtype from the header if not given.
+///
+/// Both byte orders as well as C and Fortran order are supported.
+fn read_array<R: Read + Seek>(
+    filename: &str,
+    mut reader: R,
+    query: &LoadQuery,
+    placement: &HostPlacement,
+    dtype: Option<Ty>,
+) -> Result<Value> {
+    let dtype = match dtype {
+        Some(dtype) => dtype,
+        None => {
+            let descr = read_descr(&mut reader).map_err(|e| {
                 Error::Storage(format!(
-                    "failed to read numpy data file: {}: {}",
+                    "parsing failure from numpy data file: {}: {}",
                     filename, e
                 ))
             })?;
-            let tensor: HostFloat32Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
-        }
-        Ty::HostInt32Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
+            reader.seek(SeekFrom::Start(0)).map_err(|e| {
                 Error::Storage(format!(
                     "failed to read numpy data file: {}: {}",
                     filename, e
                 ))
             })?;
-            let tensor: HostInt32Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
+            descr_to_dtype(&descr)?
         }
-        Ty::HostInt64Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to read numpy data file: {}: {}",
-                    filename, e
-                ))
-            })?;
-            let tensor: HostInt64Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
+    };
+    match dtype {
+        Ty::HostFloat64Tensor => {
+            let arr: ArrayD<f64> = read_npy_array(filename, reader)?;
+            let tensor: HostFloat64Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Ty::HostUint64Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to read numpy data file: {}: {}",
-                    filename, e
-                ))
-            })?;
-            let tensor: HostUint64Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
+        Ty::HostFloat32Tensor => {
+            let arr: ArrayD<f32> = read_npy_array(filename, reader)?;
+            let tensor: HostFloat32Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Ty::HostUint32Tensor => {
-            let arr: ArrayD<_> = read_npy(filename).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to read numpy data file: {}: {}",
-                    filename, e
-                ))
-            })?;
-            let tensor: HostUint32Tensor = placement.from_raw(arr);
-            let value = Value::from(tensor);
-            Ok(value)
+        Ty::HostInt8Tensor => {
+            let arr: ArrayD<i8> = read_npy_array(filename, reader)?;
+            let tensor: HostInt8Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        _ => Err(Error::Storage(format!(
-            "invalid dtype for numpy storage read: {}",
-            dtype
-        ))),
-    }
-}
-
-#[allow(dead_code)]
-pub(crate) async fn write_numpy(filename: &str, data: &Value) -> Result<()> {
-    match data {
-        Value::HostFloat64Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostInt16Tensor => {
+            let arr: ArrayD<i16> = read_npy_array(filename, reader)?;
+            let tensor: HostInt16Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Value::HostFloat32Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostInt32Tensor => {
+            let arr: ArrayD<i32> = read_npy_array(filename, reader)?;
+            let tensor: HostInt32Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Value::HostUint32Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostInt64Tensor => {
+            let arr: ArrayD<i64> = read_npy_array(filename, reader)?;
+            let tensor: HostInt64Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Value::HostUint64Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostUint8Tensor => {
+            let arr: ArrayD<u8> = read_npy_array(filename, reader)?;
+            let tensor: HostUint8Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Value::HostInt32Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostUint16Tensor => {
+            let arr: ArrayD<u16> = read_npy_array(filename, reader)?;
+            let tensor: HostUint16Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        Value::HostInt64Tensor(t) => {
-            write_npy(filename, &t.0).map_err(|e| {
-                Error::Storage(format!(
-                    "failed to write moose value to file: '{}': {}",
-                    filename, e
-                ))
-            })?;
+        Ty::HostUint32Tensor => {
+            let arr: ArrayD<u32> = read_npy_array(filename, reader)?;
+            let tensor: HostUint32Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
-        _ => {
-            return Err(Error::Storage(format!(
-                "cannot write unsupported tensor to numpy file: {}",
-                filename
-            )))
+        Ty::HostUint64Tensor => {
+            let arr: ArrayD<u64> = read_npy_array(filename, reader)?;
+            let tensor: HostUint64Tensor = placement.from_raw(query_array(arr, query)?);
+            Ok(Value::from(tensor))
         }
+        Ty::HostBitTensor => {
+            let arr: ArrayD<bool> = read_npy_array(filename, reader)?;
+            let tensor: HostBitTensor = placement.from_raw(query_array(arr.mapv(u8::from), query)?);
+            Ok(Value::from(tensor))
+        }
+        _ => Err(Error::Storage(format!(
+            "invalid dtype for numpy storage read: {}",
+            dtype
+        ))),
     }
-    Ok(())
 }
 
-fn match_char(got: u8, expected: char) -> Result<()> {
-    if got != expected as u8 {
-        Err(Error::Storage(format!(
-            "expecting: {} got: {}",
-            expected, got
-        )))
+fn read_npy_array<T: ReadableElement + Clone, R: Read>(
+    filename: &str,
+    reader: R,
+) -> Result<ArrayD<T>> {
+    let arr = ArrayD::<T>::read_npy(reader).map_err(|e| {
+        Error::Storage(format!(
+            "failed to read numpy data file: {}: {}",
+            filename, e
+        ))
+    })?;
+    // arrays stored in Fortran order are read as such
+    if arr.is_standard_layout() {
+        Ok(arr)
     } else {
-        Ok(())
+        Ok(arr.as_standard_layout().into_owned())
     }
 }
 
-fn consume_spaces(file: &mut File) -> Result<u8> {
-    loop {
-        let c = getc(file)?;
-        if c != b' ' {
-            return Ok(c);
-        }
-    }
+#[allow(dead_code)]
+pub(crate) async fn write_numpy(filename: &str, data: &Value) -> Result<()> {
+    write_value(filename, data, false)
 }
 
-fn getc(file: &mut File) -> Result<u8> {
-    let mut buf: [u8; 1] = [0; 1];
-    file.read(&mut buf)
-        .map_err(|e| Error::Storage(format!("failed to read byte from file: {}", e)))?;
-    let byte = buf[0];
-    Ok(byte)
+/// Save a value as the single array of a `.npz` archive, named `arr_0`.
+#[allow(dead_code)]
+pub(crate) async fn write_npz(filename: &str, data: &Value) -> Result<()> {
+    write_value(filename, data, true)
 }
 
-// Lexical analysis of the numpy data file to find the dtype
-// description of numpy binary file format here:
-//     https://numpy.org/devdocs/reference/generated/numpy.lib.format.html
-fn extract_descr(file: &mut File) -> Result<Vec<char>> {
-    // First 10 bytes are magic numbers
-    for _ in 0..10 {
-        getc(file)?;
-    }
-    let c = getc(file)?;
-
-    // Found start of dictionary
-    match_char(c, '{')?;
-    let c = consume_spaces(file)?;
-    match_char(c, '\'')?;
-
-    // Find the key "descr". This is the entry for the dtype of the numpy object
-    loop {
-        let mut word: String = String::new();
-        loop {
-            let c = getc(file)?;
-            if c == b'\'' || c == b'"' {
-                break;
-            }
-            word.push(c as char);
-        }
-        if word == "descr" {
-            break;
-        }
-    }
-    match_char(c, '\'')?;
-
-    let c = consume_spaces(file)?;
-
-    // ':' denotes the beginning of the value section for this dict entry
-    match_char(c, ':')?;
-    let c = consume_spaces(file)?;
-    match_char(c, '\'')?;
-
-    // Now we are at the value corresponding to the "descr" key in the
-    // dictionary. Let's now read what the value actually is.
-    let mut descr = Vec::new();
-    loop {
-        let c = getc(file)?;
-        if c == b'\'' {
-            break;
+fn write_value(filename: &str, data: &Value, archive: bool) -> Result<()> {
+    match data {
+        Value::HostFloat64Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostFloat32Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostInt8Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostInt16Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostInt32Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostInt64Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostUint8Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostUint16Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostUint32Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostUint64Tensor(t) => write_array(filename, t.0.view(), archive),
+        Value::HostBitTensor(t) => {
+            // saved as booleans, i.e. with descr '|b1'
+            let arr = t.0.into_array::<u8>().map_err(|e| {
+                Error::Storage(format!("could not convert bit tensor to array: {}", e))
+            })?;
+            write_array(filename, arr.mapv(|x| x != 0).view(), archive)
         }
-        descr.push(c as char);
+        _ => Err(Error::Storage(format!(
+            "cannot write unsupported tensor to numpy file: {}",
+            filename
+        ))),
     }
+}
 
-    if descr.is_empty() {
-        Err(Error::Storage(
-            "could not find \"descr\" in numpy data dictionary".to_string(),
+fn write_array<T: WritableElement>(
+    filename: &str,
+    arr: ArrayViewD<T>,
+    archive: bool,
+) -> Result<()> {
+    let write_error = |e: &dyn std::fmt::Display| {
+        Error::Storage(format!(
+            "failed to write moose value to file: '{}': {}",
+            filename, e
         ))
+    };
+    if archive {
+        let file = File::create(filename).map_err(|e| write_error(&e))?;
+        let mut writer = NpzWriter::new(file);
+        writer
+            .add_array(NPZ_ARRAY_NAME, &arr)
+            .map_err(|e| write_error(&e))?;
+        writer.finish().map_err(|e| write_error(&e))?;
     } else {
-        Ok(descr)
+        write_npy(filename, &arr).map_err(|e| write_error(&e))?;
     }
+    Ok(())
 }
 
-fn descr_to_dtype(descr: &[char]) -> Result<Ty> {
-    if descr.is_empty() {
-        return Err(Error::Storage(
-            "descr is empty in numpy data dictionary".to_string(),
-        ));
-    }
+/// Read the dtype description from the header of a numpy data file, as described at
+///     https://numpy.org/devdocs/reference/generated/numpy.lib.format.html
+fn read_descr<R: Read>(reader: &mut R) -> Result<String> {
+    let io_error = |e: std::io::Error| Error::Storage(format!("failed to read header: {}", e));
 
-    // we can ignore byte order marks to get the dtype
-    let skip_byte_order = descr[0] == '<' || descr[0] == '>';
-    let dtype_start = usize::from(skip_byte_order);
-
-    let letter_code = descr
-        .get(dtype_start)
-        .ok_or_else(|| Error::Storage("missing letter code from numpy file descr".to_string()))?;
-    let number_code = descr.get(dtype_start + 1);
-
-    // letter_code:
-    //     specifies overall type, e.g., float is f, int is i, uint is u.
-    // number_code:
-    //     specifies the number of bytes, e.g., 4 means 32 bits, 8 means 64 bits
-    match (letter_code, number_code) {
-        ('f', Some('4')) => Ok(Ty::HostFloat32Tensor),
-        ('f', Some('8')) => Ok(Ty::HostFloat64Tensor),
-        ('d', None) => Ok(Ty::HostFloat64Tensor),
-        ('i', Some('4')) => Ok(Ty::HostInt32Tensor),
-        ('i', Some('8')) => Ok(Ty::HostInt64Tensor),
-        ('u', Some('4')) => Ok(Ty::HostUint32Tensor),
-        ('u', Some('8')) => Ok(Ty::HostUint64Tensor),
-        _ => {
-            let number_code_display = match number_code {
-                Some(c) => c.to_string(),
-                None => String::new(),
-            };
-            Err(Error::Storage(format!(
-                "unknown numpy descr: {}{}",
-                letter_code, number_code_display
+    // magic string followed by the major and minor version
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble).map_err(io_error)?;
+    if &preamble[..6] != b"\x93NUMPY" {
+        return Err(Error::Storage("missing numpy magic string".to_string()));
+    }
+    let header_len = match preamble[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len).map_err(io_error)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len).map_err(io_error)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => {
+            return Err(Error::Storage(format!(
+                "unsupported numpy format version: {}",
+                version
             )))
         }
-    }
+    };
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header).map_err(io_error)?;
+    parse_descr(&String::from_utf8_lossy(&header))
 }
 
-fn extract_dtype(npy_filename: &str) -> Result<Ty> {
-    let mut file = std::fs::File::open(npy_filename).map_err(|e| {
-        Error::Storage(format!(
-            "failed to open numpy data file for reading: {}: {}",
-            npy_filename, e
-        ))
-    })?;
-    let descr = extract_descr(&mut file)?;
-    let numpy_dtype = descr_to_dtype(&descr)?;
-    Ok(numpy_dtype)
+/// Extract the value of the "descr" key from the header dictionary.
+fn parse_descr(header: &str) -> Result<String> {
+    let key_end = ["'descr'", "\"descr\""]
+        .iter()
+        .find_map(|key| header.find(key).map(|start| start + key.len()))
+        .ok_or_else(|| {
+            Error::Storage("could not find \"descr\" in numpy data dictionary".to_string())
+        })?;
+    let value = header[key_end..]
+        .trim_start()
+        .strip_prefix(':')
+        .map(str::trim_start)
+        .ok_or_else(|| Error::Storage("expecting ':' after \"descr\"".to_string()))?;
+    // structured dtypes are given as lists rather than strings
+    let quote = value
+        .chars()
+        .next()
+        .filter(|c| *c == '\'' || *c == '"')
+        .ok_or_else(|| Error::Storage("only simple numpy dtypes are supported".to_string()))?;
+    let value = &value[1..];
+    let end = value
+        .find(quote)
+        .ok_or_else(|| Error::Storage("unterminated \"descr\" value".to_string()))?;
+    Ok(value[..end].to_string())
+}
+
+fn descr_to_dtype(descr: &str) -> Result<Ty> {
+    // byte order marks do not affect the dtype, with '|' used where byte order does not apply
+    let code = descr.trim_start_matches(|c| matches!(c, '<' | '>' | '|' | '='));
+
+    // code:
+    //     letter specifies overall type, e.g., bool is b, float is f, int is i, uint is u.
+    //     number specifies the number of bytes, e.g., 4 means 32 bits, 8 means 64 bits
+    match code {
+        "b1" | "?" => Ok(Ty::HostBitTensor),
+        "f4" => Ok(Ty::HostFloat32Tensor),
+        "f8" | "d" => Ok(Ty::HostFloat64Tensor),
+        "i1" => Ok(Ty::HostInt8Tensor),
+        "i2" => Ok(Ty::HostInt16Tensor),
+        "i4" => Ok(Ty::HostInt32Tensor),
+        "i8" => Ok(Ty::HostInt64Tensor),
+        "u1" => Ok(Ty::HostUint8Tensor),
+        "u2" => Ok(Ty::HostUint16Tensor),
+        "u4" => Ok(Ty::HostUint32Tensor),
+        "u8" => Ok(Ty::HostUint64Tensor),
+        _ => Err(Error::Storage(format!("unknown numpy descr: {}", descr))),
+    }
 }
 
 #[cfg(test)]
@@ -301,6 +392,16 @@ mod tests {
     use std::io::Write;
     use tempfile::NamedTempFile;
 
+    #[test]
+    fn test_read_bounded() {
+        let data = [0u8; 16];
+        assert_eq!(read_bounded("data", &data[..], 16).unwrap().len(), 16);
+        assert!(matches!(
+            read_bounded("data", &data[..], 15),
+            Err(Error::Storage(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_read_numpy() {
         let plc = HostPlacement::from("host");
@@ -326,7 +427,9 @@ mod tests {
         file.write_all(&raw_bytes).unwrap();
 
         let plc = HostPlacement::from("host");
-        let data = read_numpy(&filename, &plc, None).await.unwrap();
+        let data = read_numpy(&filename, &LoadQuery::default(), &plc, None)
+            .await
+            .unwrap();
         assert_eq!(data, expected);
     }
 
@@ -348,7 +451,81 @@ mod tests {
 
         write_numpy(&filename, &expected).await.unwrap();
 
-        let data = read_numpy(&filename, &plc, None).await.unwrap();
+        let data = read_numpy(&filename, &LoadQuery::default(), &plc, None)
+            .await
+            .unwrap();
         assert_eq!(data, expected);
     }
+
+    #[tokio::test]
+    async fn test_read_big_endian_fortran_order() {
+        let header = "{'descr': '>i2', 'fortran_order': True, 'shape': (2, 3), }";
+        // the header is padded with spaces and a newline to a multiple of 64 bytes
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        let padded_len = 128 - 10;
+        bytes.extend_from_slice(&(padded_len as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.resize(10 + padded_len - 1, b' ');
+        bytes.push(b'\n');
+        // column-major order of [[1, 2, 3], [4, 5, 6]]
+        for x in [1i16, 4, 2, 5, 3, 6].iter() {
+            bytes.extend_from_slice(&x.to_be_bytes());
+        }
+        let mut file = NamedTempFile::new().expect("trying to create tempfile");
+        file.write_all(&bytes).unwrap();
+        let filename = file.path().to_str().unwrap();
+
+        let plc = HostPlacement::from("host");
+        let data = read_numpy(filename, &LoadQuery::default(), &plc, None)
+            .await
+            .unwrap();
+        let expected: HostInt16Tensor = plc.from_raw(array![[1i16, 2, 3], [4, 5, 6]]);
+        assert_eq!(data, Value::from(expected));
+    }
+
+    #[tokio::test]
+    async fn test_write_read_npz() {
+        let plc = HostPlacement::from("host");
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // bit tensors are saved as booleans
+        let tensor: HostBitTensor = plc.from_raw(array![[1u8, 0, 1], [0, 0, 1]]);
+        let expected = Value::from(tensor);
+        let path = temp_dir.path().join("bits.npz");
+        let filename = path.to_str().unwrap();
+        write_npz(filename, &expected).await.unwrap();
+        let options = NpzOptions::default();
+        let data = read_npz(filename, &LoadQuery::default(), &options, &plc, None)
+            .await
+            .unwrap();
+        assert_eq!(data, expected);
+
+        let path = temp_dir.path().join("arrays.npz");
+        let filename = path.to_str().unwrap();
+        let mut writer = NpzWriter::new(File::create(&path).unwrap());
+        writer.add_array("x", &array![1u16, 2, 3]).unwrap();
+        writer.add_array("y", &array![-1i8, -2]).unwrap();
+        writer.finish().unwrap();
+
+        let options = NpzOptions::default();
+        let res = read_npz(filename, &LoadQuery::default(), &options, &plc, None).await;
+        assert!(res.is_err());
+
+        let options = NpzOptions::from_query(r#"{"array": "y"}"#).unwrap();
+        let data = read_npz(filename, &LoadQuery::default(), &options, &plc, None)
+            .await
+            .unwrap();
+        let expected: HostInt8Tensor = plc.from_raw(array![-1i8, -2]);
+        assert_eq!(data, Value::from(expected));
+    }
+
+    #[test]
+    fn test_descr_to_dtype() {
+        assert_eq!(descr_to_dtype("|b1").unwrap(), Ty::HostBitTensor);
+        assert_eq!(descr_to_dtype("|i1").unwrap(), Ty::HostInt8Tensor);
+        assert_eq!(descr_to_dtype(">u2").unwrap(), Ty::HostUint16Tensor);
+        assert_eq!(descr_to_dtype("<f8").unwrap(), Ty::HostFloat64Tensor);
+        assert!(descr_to_dtype("<f2").is_err());
+        assert!(descr_to_dtype("<U5").is_err());
+    }
 }
@@ -4,8 +4,10 @@ use crate::computation::*;
 use crate::error::{Error, Result};
 use async_trait::async_trait;
 
+pub mod encrypted;
 pub mod filesystem;
 pub mod local;
+pub mod query;
 
 pub trait SyncStorage {
     fn save(&self, key: &str, session_id: &SessionId, val: &Value) -> Result<()>;
@@ -0,0 +1,413 @@
+//! Queries selecting the columns and rows of stored data to load.
+
+use crate::error::{Error, Result};
+use aes_prng::AesRng;
+use ndarray::{ArrayD, Axis};
+use num_traits::ToPrimitive;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Keys of options interpreted by specific file formats rather than by `LoadQuery`.
+const FORMAT_KEYS: [&str; 4] = ["delimiter", "has_headers", "missing", "array"];
+
+/// Query given as JSON to load operations, such as
+///
+/// ```json
+/// {
+///     "select_columns": ["age", "income"],
+///     "rows": {"start": 0, "end": 1000000},
+///     "where": [{"column": "age", "op": ">=", "value": 18}],
+///     "sample": {"fraction": 0.1, "seed": 42},
+///     "offset": 10,
+///     "limit": 100
+/// }
+/// ```
+///
+/// Rows are selected in that order: first the range of stored rows, then the rows matching
+/// all predicates, then the seeded sample, and finally the offset and limit. Predicates may
+/// refer to columns that are not selected. The columns of files without names, such as numpy
+/// files, are named `col_0`, `col_1`, etc. Selected columns are always returned in the order
+/// in which they appear in the file, whatever their order in `select_columns`.
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadQuery {
+    pub select_columns: Vec<String>,
+    pub rows: Option<RowRange>,
+    #[serde(rename = "where")]
+    pub predicates: Vec<Predicate>,
+    pub sample: Option<Sample>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// Range of stored rows, with the end being exclusive.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RowRange {
+    #[serde(default)]
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+/// Comparison of the values of a column with a constant.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Predicate {
+    pub column: String,
+    pub op: Comparison,
+    #[serde(deserialize_with = "number_or_bool")]
+    pub value: f64,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub enum Comparison {
+    #[serde(rename = "==")]
+    Equal,
+    #[serde(rename = "!=")]
+    NotEqual,
+    #[serde(rename = "<")]
+    Less,
+    #[serde(rename = "<=")]
+    LessEqual,
+    #[serde(rename = ">")]
+    Greater,
+    #[serde(rename = ">=")]
+    GreaterEqual,
+}
+
+/// Deterministic sample of either a fraction or a fixed number of rows.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Sample {
+    pub fraction: Option<f64>,
+    pub size: Option<usize>,
+    pub seed: u64,
+}
+
+fn number_or_bool<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrBool {
+        Number(f64),
+        Bool(bool),
+    }
+    match NumberOrBool::deserialize(deserializer)? {
+        NumberOrBool::Number(x) => Ok(x),
+        NumberOrBool::Bool(b) => Ok(if b { 1.0 } else { 0.0 }),
+    }
+}
+
+impl Predicate {
+    /// Missing values, represented as NaN, never match.
+    fn matches(&self, x: f64) -> bool {
+        if x.is_nan() {
+            return false;
+        }
+        match self.op {
+            Comparison::Equal => x == self.value,
+            Comparison::NotEqual => x != self.value,
+            Comparison::Less => x < self.value,
+            Comparison::LessEqual => x <= self.value,
+            Comparison::Greater => x > self.value,
+            Comparison::GreaterEqual => x >= self.value,
+        }
+    }
+}
+
+impl Sample {
+    fn select(&self, rows: Vec<usize>) -> Vec<usize> {
+        let mut seed = [0u8; 16];
+        seed[..8].copy_from_slice(&self.seed.to_le_bytes());
+        let mut rng = AesRng::from_seed(seed);
+        match (self.fraction, self.size) {
+            (Some(fraction), _) => rows
+                .into_iter()
+                .filter(|_| rng.gen::<f64>() < fraction)
+                .collect(),
+            (None, Some(size)) if size < rows.len() => {
+                let mut picked = rand::seq::index::sample(&mut rng, rows.len(), size).into_vec();
+                picked.sort_unstable();
+                picked.into_iter().map(|i| rows[i]).collect()
+            }
+            _ => rows,
+        }
+    }
+}
+
+impl LoadQuery {
+    /// Parse and validate a query, with the empty string selecting everything.
+    ///
+    /// Options of specific file formats, such as the CSV `delimiter`, are accepted but
+    /// otherwise ignored.
+    pub fn parse(query: &str) -> Result<LoadQuery> {
+        if query.is_empty() {
+            return Ok(LoadQuery::default());
+        }
+        let mut jsn: serde_json::Value = serde_json::from_str(query)
+            .map_err(|e| Error::Storage(format!("failed to parse query as json: {}", e)))?;
+        let fields = jsn
+            .as_object_mut()
+            .ok_or_else(|| Error::Storage("query must be a json object".to_string()))?;
+        for key in FORMAT_KEYS.iter() {
+            fields.remove(*key);
+        }
+        let query: LoadQuery = serde_json::from_value(jsn)
+            .map_err(|e| Error::Storage(format!("invalid query: {}", e)))?;
+        query.validate()?;
+        Ok(query)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(RowRange {
+            start,
+            end: Some(end),
+        }) = self.rows
+        {
+            if end < start {
+                return Err(Error::Storage(format!(
+                    "row range must not end before it starts, got {}..{}",
+                    start, end
+                )));
+            }
+        }
+        if let Some(sample) = &self.sample {
+            match (sample.fraction, sample.size) {
+                (Some(fraction), None) if (0.0..=1.0).contains(&fraction) => (),
+                (Some(fraction), None) => {
+                    return Err(Error::Storage(format!(
+                        "sample fraction must be between 0 and 1, got {}",
+                        fraction
+                    )))
+                }
+                (None, Some(_)) => (),
+                _ => {
+                    return Err(Error::Storage(
+                        "sample must specify exactly one of fraction and size".to_string(),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether all stored rows are loaded.
+    pub fn selects_all_rows(&self) -> bool {
+        self.rows.is_none()
+            && self.predicates.is_empty()
+            && self.sample.is_none()
+            && self.offset == 0
+            && self.limit.is_none()
+    }
+
+    /// Number of stored rows before which no rows can be selected.
+    pub fn rows_start(&self) -> usize {
+        self.rows.map_or(0, |rows| rows.start)
+    }
+
+    /// Number of stored rows after which no more rows can be selected, if any.
+    pub fn rows_end(&self) -> Option<usize> {
+        self.rows.and_then(|rows| rows.end)
+    }
+
+    /// Names of the columns referred to by predicates.
+    pub fn predicate_columns(&self) -> impl Iterator<Item = &str> {
+        self.predicates
+            .iter()
+            .map(|predicate| predicate.column.as_str())
+    }
+
+    /// Indices of the selected rows out of `nrows` stored rows.
+    ///
+    /// The values of all predicate columns must be given, with missing values as NaN.
+    pub fn select_rows(
+        &self,
+        nrows: usize,
+        columns: &HashMap<String, Vec<f64>>,
+    ) -> Result<Vec<usize>> {
+        let start = self.rows_start().min(nrows);
+        let end = self.rows_end().unwrap_or(nrows).min(nrows).max(start);
+        self.filter_rows((start..end).collect(), columns)
+    }
+
+    /// Same as `select_rows` for rows remaining after others were dropped while loading, where
+    /// `stored` gives the index among the stored rows of each remaining row.
+    ///
+    /// The range of rows refers to the stored rows, so dropping rows does not shift it.
+    pub fn select_remaining_rows(
+        &self,
+        stored: &[usize],
+        columns: &HashMap<String, Vec<f64>>,
+    ) -> Result<Vec<usize>> {
+        let start = self.rows_start();
+        let end = self.rows_end().unwrap_or(usize::MAX);
+        let rows = (0..stored.len())
+            .filter(|&row| start <= stored[row] && stored[row] < end)
+            .collect();
+        self.filter_rows(rows, columns)
+    }
+
+    /// Apply the predicates, sample, offset and limit to the given rows.
+    fn filter_rows(
+        &self,
+        mut rows: Vec<usize>,
+        columns: &HashMap<String, Vec<f64>>,
+    ) -> Result<Vec<usize>> {
+        for predicate in &self.predicates {
+            let values = columns.get(&predicate.column).ok_or_else(|| {
+                Error::Storage(format!(
+                    "column '{}' used in predicate not found",
+                    predicate.column
+                ))
+            })?;
+            rows.retain(|&row| values.get(row).map_or(false, |&x| predicate.matches(x)));
+        }
+        if let Some(sample) = &self.sample {
+            rows = sample.select(rows);
+        }
+        Ok(rows
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+}
+
+fn column_index(name: &str, ncols: usize) -> Result<usize> {
+    name.strip_prefix("col_")
+        .and_then(|i| i.parse::<usize>().ok())
+        .filter(|&i| i < ncols)
+        .ok_or_else(|| Error::Storage(format!("column '{}' not found", name)))
+}
+
+/// Apply a query to an array of rows, with columns named `col_0`, `col_1`, etc.
+///
+/// Columns can only be selected or used in predicates if the array has two dimensions.
+pub(crate) fn query_array<T: Clone + ToPrimitive>(
+    array: ArrayD<T>,
+    query: &LoadQuery,
+) -> Result<ArrayD<T>> {
+    let uses_columns = !query.select_columns.is_empty() || !query.predicates.is_empty();
+    if uses_columns && array.ndim() != 2 {
+        return Err(Error::Storage(format!(
+            "columns can only be used with two dimensional data, got shape: {:?}",
+            array.shape()
+        )));
+    }
+
+    let array = if query.selects_all_rows() {
+        array
+    } else {
+        if array.ndim() == 0 {
+            return Err(Error::Storage(
+                "rows cannot be selected from scalar data".to_string(),
+            ));
+        }
+        let mut columns = HashMap::new();
+        for name in query.predicate_columns() {
+            let index = column_index(name, array.shape()[1])?;
+            let values = array
+                .index_axis(Axis(1), index)
+                .iter()
+                .map(|x| x.to_f64().unwrap_or(f64::NAN))
+                .collect();
+            columns.insert(name.to_string(), values);
+        }
+        let rows = query.select_rows(array.shape()[0], &columns)?;
+        array.select(Axis(0), &rows)
+    };
+
+    if query.select_columns.is_empty() {
+        Ok(array)
+    } else {
+        let mut indices = query
+            .select_columns
+            .iter()
+            .map(|name| column_index(name, array.shape()[1]))
+            .collect::<Result<Vec<_>>>()?;
+        indices.sort_unstable();
+        indices.dedup();
+        Ok(array.select(Axis(1), &indices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    #[test]
+    fn test_parse() {
+        let query = LoadQuery::parse(
+            r#"{"select_columns": ["a"], "delimiter": ";", "rows": {"end": 10},
+                "where": [{"column": "b", "op": ">=", "value": true}], "limit": 5}"#,
+        )
+        .unwrap();
+        assert_eq!(query.select_columns, vec!["a".to_string()]);
+        assert_eq!(
+            query.rows,
+            Some(RowRange {
+                start: 0,
+                end: Some(10)
+            })
+        );
+        assert_eq!(query.predicates[0].op, Comparison::GreaterEqual);
+        assert_eq!(query.predicates[0].value, 1.0);
+        assert_eq!(query.limit, Some(5));
+
+        assert!(LoadQuery::parse(r#"{"selected_columns": ["a"]}"#).is_err());
+        assert!(LoadQuery::parse(r#"{"rows": {"start": 2, "end": 1}}"#).is_err());
+        assert!(
+            LoadQuery::parse(r#"{"where": [{"column": "a", "op": "=", "value": 1}]}"#).is_err()
+        );
+        assert!(
+            LoadQuery::parse(r#"{"sample": {"fraction": 0.5, "size": 2, "seed": 1}}"#).is_err()
+        );
+        assert!(LoadQuery::parse(r#"{"sample": {"fraction": 1.5, "seed": 1}}"#).is_err());
+    }
+
+    #[test]
+    fn test_select_rows() {
+        let columns = maplit::hashmap! {
+            "x".to_string() => vec![1.0, 5.0, f64::NAN, 7.0, 2.0, 9.0],
+        };
+        let query = LoadQuery::parse(
+            r#"{"rows": {"start": 1}, "where": [{"column": "x", "op": ">", "value": 1.5}],
+                "offset": 1, "limit": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(query.select_rows(6, &columns).unwrap(), vec![3, 4]);
+
+        let query =
+            LoadQuery::parse(r#"{"where": [{"column": "y", "op": "==", "value": 0}]}"#).unwrap();
+        assert!(query.select_rows(6, &columns).is_err());
+    }
+
+    #[test]
+    fn test_sample_is_deterministic() {
+        let query = LoadQuery::parse(r#"{"sample": {"size": 10, "seed": 7}}"#).unwrap();
+        let rows = query.select_rows(1000, &HashMap::new()).unwrap();
+        assert_eq!(rows.len(), 10);
+        assert!(rows.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(rows, query.select_rows(1000, &HashMap::new()).unwrap());
+
+        let query = LoadQuery::parse(r#"{"sample": {"fraction": 0.5, "seed": 7}}"#).unwrap();
+        let rows = query.select_rows(1000, &HashMap::new()).unwrap();
+        assert!(rows.len() > 400 && rows.len() < 600);
+        assert_eq!(rows, query.select_rows(1000, &HashMap::new()).unwrap());
+    }
+
+    #[test]
+    fn test_query_array() {
+        let array = array![[1, 10], [2, 20], [3, 30], [4, 40]].into_dyn();
+        let query = LoadQuery::parse(
+            r#"{"select_columns": ["col_1"], "where": [{"column": "col_0", "op": "!=", "value": 2}],
+                "limit": 2}"#,
+        )
+        .unwrap();
+        let result = query_array(array, &query).unwrap();
+        assert_eq!(result, array![[10], [30]].into_dyn());
+    }
+}
@@ -1199,6 +1199,7 @@ impl ToTextual for Operator {
             Cast(op) => op.to_textual(),
             Load(op) => op.to_textual(),
             Save(op) => op.to_textual(),
+            StorageKey(op) => op.to_textual(),
             Send(op) => op.to_textual(),
             Receive(op) => op.to_textual(),
             Input(op) => op.to_textual(),
@@ -1338,6 +1339,51 @@ impl ToTextual for RingFixedpointMeanOp {
     }
 }
 
+macro_rules! argmax_to_textual {
+    ($op:tt) => {
+        impl ToTextual for $op {
+            fn to_textual(&self) -> String {
+                let mut attributes = Vec::new();
+                if let Some(axis) = self.axis {
+                    attributes.push(format!("axis = {}", axis));
+                }
+                if let Some(upmost_index) = self.upmost_index {
+                    attributes.push(format!("upmost_index = {}", upmost_index));
+                }
+                format!(
+                    "{}{{{}}}: {}",
+                    self.short_name(),
+                    attributes.join(", "),
+                    self.sig.to_textual()
+                )
+            }
+        }
+    };
+}
+
+argmax_to_textual!(ArgmaxOp);
+argmax_to_textual!(RingFixedpointArgmaxOp);
+
+impl ToTextual for SoftmaxOp {
+    fn to_textual(&self) -> String {
+        match self.upmost_index {
+            Some(upmost_index) => format!(
+                "{}{{axis = {}, upmost_index = {}}}: {}",
+                self.short_name(),
+                self.axis,
+                upmost_index,
+                self.sig.to_textual()
+            ),
+            None => format!(
+                "{}{{axis = {}}}: {}",
+                self.short_name(),
+                self.axis,
+                self.sig.to_textual()
+            ),
+        }
+    }
+}
+
 impl ToTextual for SampleOp {
     fn to_textual(&self) -> String {
         match self {
@@ -2149,6 +2195,9 @@ mod tests {
         parse_assignment::<(&str, ErrorKind)>(
             "load = Load: (HostString, HostString) -> HostFloat64Tensor (xuri, xconstant) @Host(alice)",
         )?;
+        parse_assignment::<(&str, ErrorKind)>(
+            r#"key = StorageKey{suffix = "alice-bob-carole/alice"}: (HostString) -> HostString (xuri) @Host(alice)"#,
+        )?;
         parse_assignment::<(&str, ErrorKind)>(
             "addN = AddN: [HostString] -> HostString (xuri, xconstant) @Host(alice)",
         )?;
@@ -0,0 +1,87 @@
+//! Parsing of quantities with units, as given on the command line and in configuration files.
+
+use std::time::Duration;
+
+/// Parses durations such as `20ms`, `1.5s` or `500us`.
+pub fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+    let seconds = match unit.trim() {
+        "s" => number,
+        "ms" => number / 1e3,
+        "us" => number / 1e6,
+        "ns" => number / 1e9,
+        _ => return Err(format!("invalid unit of duration '{}'", s)),
+    };
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid duration '{}': {}", s, e))
+}
+
+/// Parses bandwidths such as `1Gbit`, `100Mbit` or `64kbit`, returning bits per second.
+pub fn parse_bandwidth(s: &str) -> std::result::Result<u64, String> {
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid bandwidth '{}'", s))?;
+    let multiplier = match unit.trim().trim_end_matches("/s").trim_end_matches("ps") {
+        "bit" | "b" => 1e0,
+        "kbit" | "Kbit" | "kb" => 1e3,
+        "Mbit" | "Mb" => 1e6,
+        "Gbit" | "Gb" => 1e9,
+        _ => return Err(format!("invalid unit of bandwidth '{}'", s)),
+    };
+    match (number * multiplier) as u64 {
+        0 => Err(format!("bandwidth must be at least 1bit, got '{}'", s)),
+        bandwidth => Ok(bandwidth),
+    }
+}
+
+/// Parses the fraction of messages lost, such as `0.01`.
+///
+/// The fraction must be at least 0 and below 1, since otherwise no message would ever get
+/// through.
+pub fn parse_drop_rate(s: &str) -> std::result::Result<f64, String> {
+    let rate: f64 = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid drop rate '{}'", s))?;
+    if (0.0..1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!(
+            "drop rate must be at least 0 and below 1, got '{}'",
+            s
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse_duration("20ms"), Ok(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert!(parse_duration("20").is_err());
+        // too large to be represented
+        assert!(parse_duration("100000000000000000000000000000s").is_err());
+        assert_eq!(parse_bandwidth("1Gbit"), Ok(1_000_000_000));
+        assert_eq!(parse_bandwidth("100Mbit/s"), Ok(100_000_000));
+        assert_eq!(parse_bandwidth("64kbps"), Ok(64_000));
+        assert!(parse_bandwidth("fast").is_err());
+        assert!(parse_bandwidth("0bit").is_err());
+        assert_eq!(parse_drop_rate("0.01"), Ok(0.01));
+        assert_eq!(parse_drop_rate("0"), Ok(0.0));
+        assert!(parse_drop_rate("1").is_err());
+        assert!(parse_drop_rate("-0.1").is_err());
+        assert!(parse_drop_rate("NaN").is_err());
+    }
+}
@@ -67,6 +67,7 @@ from pymoose.edsl.tracer import trace
 from pymoose.edsl.tracer import trace_and_compile
 from pymoose.pymoose import elk_compiler
 from pymoose.pymoose import moose_runtime as _moose_runtime
+from pymoose.runtime import ExecutionError
 from pymoose.runtime import GrpcMooseRuntime
 from pymoose.runtime import LocalMooseRuntime
 
@@ -90,6 +91,7 @@ __all__ = [
     div,
     dot,
     elk_compiler,
+    ExecutionError,
     exp,
     expand_dims,
     fixed,
@@ -10,6 +10,8 @@ from pymoose.edsl.base import HostPlacementExpression
 from pymoose.edsl.base import set_current_runtime
 from pymoose.pymoose import moose_runtime
 
+ExecutionError = moose_runtime.ExecutionError
+
 
 class LocalMooseRuntime(moose_runtime.LocalRuntime):
     """Locally-simulated Moose runtime.
@@ -83,6 +85,10 @@ class GrpcMooseRuntime(moose_runtime.GrpcRuntime):
 
     Creates a Moose runtime backed by a fixed set of gRPC servers.
 
+    If execution fails on any of the servers then `evaluate_computation` raises an
+    `ExecutionError` describing the root cause, including the name and placement
+    of the failing operation when known.
+
     Args:
         identities: Mapping of identities (e.g. host placement identifiers) to gRPC
             host addresses.
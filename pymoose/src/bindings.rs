@@ -9,6 +9,7 @@ use moose::textual::{parallel_parse_computation, ToTextual};
 use moose::tokio;
 use ndarray::LinalgScalar;
 use numpy::{Element, PyArrayDescr, PyArrayDyn, ToPyArray};
+use pyo3::create_exception;
 use pyo3::exceptions::PyRuntimeError;
 use pyo3::types::{PyBytes, PyFloat, PyString, PyType};
 use pyo3::wrap_pymodule;
@@ -18,6 +19,8 @@ use std::convert::TryInto;
 
 type PyGrpcOutputs = (HashMap<String, PyObject>, Option<HashMap<String, PyObject>>);
 
+create_exception!(moose_runtime, ExecutionError, PyRuntimeError);
+
 fn create_computation_graph_from_py_bytes(computation: Vec<u8>) -> Computation {
     let comp: PyComputation = rmp_serde::from_slice(&computation).unwrap();
     let rust_comp: Computation = comp.try_into().unwrap();
@@ -305,7 +308,11 @@ impl GrpcRuntime {
                 &physical_computation,
                 typed_arguments,
             ))
-            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+            .map_err(|e| match e.downcast_ref::<moose::Error>() {
+                // errors raised during execution by one of the parties
+                Some(e) => ExecutionError::new_err(e.to_string()),
+                None => PyRuntimeError::new_err(e.to_string()),
+            })?;
 
         let mut outputs_py_val: HashMap<String, PyObject> = HashMap::new();
         for (output_name, value) in output_metrics.outputs {
@@ -421,7 +428,8 @@ fn elk_compiler(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
 }
 
 #[pymodule]
-fn moose_runtime(_py: Python, m: &PyModule) -> PyResult<()> {
+fn moose_runtime(py: Python, m: &PyModule) -> PyResult<()> {
+    m.add("ExecutionError", py.get_type::<ExecutionError>())?;
     m.add_class::<LocalRuntime>()?;
     m.add_class::<GrpcRuntime>()?;
     m.add_class::<MooseComputation>()?;
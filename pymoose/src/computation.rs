@@ -1249,7 +1249,7 @@ impl TryFrom<PyComputation> for Computation {
                                 &["x"],
                             )?,
                             axis: op.axis,
-                            upmost_index: op.upmost_index as usize,
+                            upmost_index: Some(op.upmost_index as usize),
                         }
                         .into(),
                         inputs: map_inputs(&op.inputs, &["x"])
@@ -1265,8 +1265,8 @@ impl TryFrom<PyComputation> for Computation {
                                 &op.placement_name,
                                 &["x"],
                             )?,
-                            axis: op.axis,
-                            upmost_index: op.upmost_index as usize,
+                            axis: Some(op.axis),
+                            upmost_index: Some(op.upmost_index as usize),
                         }
                         .into(),
                         inputs: map_inputs(&op.inputs, &["x"])